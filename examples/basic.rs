@@ -45,6 +45,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             Some(realitydefender::GetResultOptions {
                 max_attempts: Some(30),
                 polling_interval: Some(2000),
+                ..Default::default()
             }),
         )
         .await?;
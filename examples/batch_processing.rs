@@ -1,4 +1,4 @@
-use realitydefender::{BatchOptions, Client, Config};
+use realitydefender::{AnalysisStatus, BatchOptions, Client, Config};
 use std::{env, path::Path};
 
 #[tokio::main]
@@ -34,21 +34,43 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     println!("Processing {} valid files in batch...", valid_files.len());
 
     // Process multiple files concurrently with batch processing
-    let results = client
+    let report = client
         .process_batch(
-            valid_files.clone(),
+            valid_files,
             BatchOptions {
                 max_concurrency: Some(2), // Process 2 files at a time
                 max_attempts: Some(60),   // Wait for results
                 polling_interval: Some(2000),
+                ..Default::default()
             },
         )
         .await?;
 
     // Print results
     println!("\nBatch processing complete!");
-    for (i, result) in results.iter().enumerate() {
-        println!("\nFile: {}", valid_files[i]);
+    println!(
+        "Succeeded: {}, Failed: {}, Pending: {}",
+        report.succeeded, report.failed, report.pending
+    );
+
+    for entry in &report.entries {
+        println!("\nFile: {}", entry.path.display());
+
+        let result = match &entry.result {
+            Some(Ok(result)) => result,
+            Some(Err(err)) => {
+                println!("Error: {}", err);
+                continue;
+            }
+            None => {
+                println!(
+                    "Uploaded with request ID {}, result not awaited",
+                    entry.request_id().unwrap_or("N/A")
+                );
+                continue;
+            }
+        };
+
         println!("Status: {}", result.status);
 
         // Display normalized score (normalized by SDK)
@@ -62,7 +84,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         if !result.models.is_empty() {
             println!("Model-specific results:");
             for model in &result.models {
-                if model.status != "NOT_APPLICABLE" {
+                if model.status != AnalysisStatus::NotApplicable {
                     println!(
                         "- {}: Status: {}, Score: {}",
                         model.name,
@@ -1,4 +1,4 @@
-use realitydefender::{Client, Config, GetResultOptions};
+use realitydefender::{Client, Config, ContentTypePolicy, GetResultOptions};
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
@@ -11,10 +11,17 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         api_key,
         base_url: None,        // Uses default production URL
         timeout_seconds: None, // Uses default timeout
+        retry_policy: None,    // Does not retry failed requests
+        rate_limit: None,      // Does not throttle requests
+        content_type_policy: ContentTypePolicy::TrustExtension, // Labels uploads by extension
+        multipart_upload: None, // Sends every upload in a single request
+        dedupe: None,          // Uploads identical content again
+        check_upload_structure: false, // Leaves broken media to the server
+        image_normalization: None, // Rejects images the API does not accept
     })?;
 
     // Example social media URLs to analyze
-    let social_media_urls = vec![
+    let social_media_urls = [
         "https://www.youtube.com/watch?v=6O0fySNw-Lw",
         "https://youtube.com/watch?v=ABC123",
     ];
@@ -39,6 +46,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                         Some(GetResultOptions {
                             max_attempts: Some(30),       // Try up to 30 times
                             polling_interval: Some(2000), // Wait 2 seconds between attempts
+                            ..Default::default()
                         }),
                     )
                     .await
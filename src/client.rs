@@ -1,33 +1,231 @@
 use crate::config::Config;
+use crate::dedupe::{hash_bytes, hash_file, DedupeCache, DedupeEntry};
+use crate::directory::collect_files;
 use crate::error::{Error, Result};
-use crate::http::{api_paths, HttpClient};
+use crate::http::{api_paths, HttpClient, ProgressCallback};
+use crate::inspect::MediaInfo;
+use crate::journal::{BatchJournal, JournalState};
 use crate::models::{
-    AnalysisResult, BatchOptions, DetectionModelResult, DetectionResult, DetectionResultList,
-    FloatOrObject, FormattedDetectionResultList, GetResultOptions, GetResultsOptions,
-    UploadOptions, UploadResult,
+    AnalysisResult, AnalysisStatus, BatchEntry, BatchOptions, BatchProgress, BatchReport,
+    CachedUpload, DetectionModelResult, DetectionResult, DetectionResultList, DirectoryOptions,
+    DirectoryReport, FetchOptions, FloatOrObject, FormattedDetectionResultList, GetResultOptions,
+    GetResultsOptions, PollerOptions, UploadOptions, UploadProgress, UploadResult,
 };
-use futures::future;
-use std::time::{Duration, Instant};
+use crate::poll::{next_delay, select_strategy, PollStrategy};
+use crate::poller::ResultPoller;
+use crate::utils::lock;
+use bytes::Bytes;
+use futures::{stream, Future, Stream, StreamExt};
+use std::collections::HashMap;
+use std::ops::ControlFlow;
+use std::path::{Path, PathBuf};
+use std::slice;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime};
+use tokio::io::AsyncRead;
+use tokio::sync::{watch, Mutex as AsyncMutex, Semaphore};
 use tokio::time::sleep;
+use tokio_util::sync::CancellationToken;
 
 /// Client for interacting with the Reality Defender API
 pub struct Client {
     http_client: HttpClient,
+    dedupe: Option<DedupeCache>,
+
+    /// Locks held while content with a given hash is uploaded, so identical
+    /// content uploaded concurrently is only sent once
+    uploading: Mutex<HashMap<String, Arc<AsyncMutex<()>>>>,
 }
 
 impl Client {
     /// Create a new client with the given configuration
     pub fn new(config: Config) -> Result<Self> {
+        let dedupe = config.dedupe.clone();
         let http_client = HttpClient::new(config)?;
-        Ok(Self { http_client })
+        Ok(Self {
+            http_client,
+            dedupe,
+            uploading: Mutex::new(HashMap::new()),
+        })
     }
 
-    /// Upload a file for analysis
+    /// Upload a file for analysis. With a dedupe cache, the upload of
+    /// identical content is reused.
     pub async fn upload(&self, options: UploadOptions) -> Result<UploadResult> {
-        // Upload file using the presigned URL flow
-        self.http_client
-            .upload_file::<UploadResult>(&options.file_path)
-            .await
+        let (_, entry, _) = self.upload_path(&options.file_path, None, false).await?;
+        Ok(entry.upload)
+    }
+
+    /// Upload a file for analysis through the dedupe cache. Identical content
+    /// uploaded before is reused along with its result, unless `refresh` is
+    /// set to force a new upload. Without a dedupe cache the file is always
+    /// uploaded.
+    pub async fn upload_cached(
+        &self,
+        options: UploadOptions,
+        refresh: bool,
+    ) -> Result<CachedUpload> {
+        let (_, entry, cached) = self.upload_path(&options.file_path, None, refresh).await?;
+        Ok(CachedUpload {
+            upload: entry.upload,
+            result: entry.result,
+            cached,
+        })
+    }
+
+    /// Upload a file unless the dedupe cache holds an upload of identical
+    /// content, returning the content hash when the cache is used, the
+    /// cache entry and whether it was reused
+    async fn upload_path(
+        &self,
+        file_path: &str,
+        progress: Option<ProgressCallback>,
+        refresh: bool,
+    ) -> Result<(Option<String>, DedupeEntry, bool)> {
+        let hash = match &self.dedupe {
+            Some(_) => Some(hash_file(Path::new(file_path)).await?),
+            None => None,
+        };
+        self.upload_deduped(
+            hash,
+            refresh,
+            self.http_client
+                .upload_file::<UploadResult>(file_path, progress),
+        )
+        .await
+    }
+
+    /// Run `upload` unless the dedupe cache holds an upload of content with
+    /// the given hash, recording the new upload in the cache. Concurrent
+    /// uploads of the same content wait for the first one to finish and
+    /// reuse it.
+    async fn upload_deduped<F>(
+        &self,
+        hash: Option<String>,
+        refresh: bool,
+        upload: F,
+    ) -> Result<(Option<String>, DedupeEntry, bool)>
+    where
+        F: Future<Output = Result<UploadResult>>,
+    {
+        let (dedupe, hash) = match (&self.dedupe, hash) {
+            (Some(dedupe), Some(hash)) => (dedupe, hash),
+            _ => {
+                let entry = DedupeEntry {
+                    upload: upload.await?,
+                    result: None,
+                    stored_at: SystemTime::now(),
+                };
+                return Ok((None, entry, false));
+            }
+        };
+
+        let hash_lock = lock(&self.uploading, "dedupe uploads")?
+            .entry(hash.clone())
+            .or_default()
+            .clone();
+        let guard = hash_lock.lock().await;
+
+        let outcome: Result<(DedupeEntry, bool)> = async {
+            if !refresh {
+                if let Some(entry) = dedupe.lookup(&hash)? {
+                    return Ok((entry, true));
+                }
+            }
+
+            let entry = DedupeEntry {
+                upload: upload.await?,
+                result: None,
+                stored_at: SystemTime::now(),
+            };
+            dedupe.store.put(&hash, &entry)?;
+            Ok((entry, false))
+        }
+        .await;
+
+        // Forget the lock once no other upload of the content waits for it
+        drop(guard);
+        let mut uploading = lock(&self.uploading, "dedupe uploads")?;
+        if Arc::strong_count(&hash_lock) == 2 {
+            uploading.remove(&hash);
+        }
+        drop(uploading);
+
+        let (entry, cached) = outcome?;
+        Ok((Some(hash), entry, cached))
+    }
+
+    /// Upload a file for analysis, publishing the bytes sent to the presigned
+    /// URL on `progress` as the upload goes
+    pub async fn upload_with_progress(
+        &self,
+        options: UploadOptions,
+        progress: watch::Sender<UploadProgress>,
+    ) -> Result<UploadResult> {
+        let progress: ProgressCallback = Arc::new(move |update| {
+            progress.send_replace(update);
+        });
+        let (_, entry, _) = self
+            .upload_path(&options.file_path, Some(progress), false)
+            .await?;
+        Ok(entry.upload)
+    }
+
+    /// Upload in-memory content for analysis. The extension of `file_name`
+    /// selects the file type and size limit, as for files on disk. With a
+    /// dedupe cache, the upload of identical content is reused.
+    pub async fn upload_bytes(&self, file_name: &str, content: Bytes) -> Result<UploadResult> {
+        let hash = self.dedupe.as_ref().map(|_| hash_bytes(&content));
+        let (_, entry, _) = self
+            .upload_deduped(
+                hash,
+                false,
+                self.http_client.upload_bytes(file_name, content),
+            )
+            .await?;
+        Ok(entry.upload)
+    }
+
+    /// Upload `len` bytes read from `reader` for analysis. The extension of
+    /// `file_name` selects the file type and size limit, and the reader must
+    /// yield exactly `len` bytes. The content is streamed as it is read, so a
+    /// failed upload is not retried. With a dedupe cache, the content is read
+    /// into memory first so identical content can be found before uploading.
+    pub async fn upload_reader<R>(
+        &self,
+        file_name: &str,
+        reader: R,
+        len: u64,
+    ) -> Result<UploadResult>
+    where
+        R: AsyncRead + Unpin,
+    {
+        if self.dedupe.is_some() {
+            let content = self.http_client.read_reader(file_name, reader, len).await?;
+            return self.upload_bytes(file_name, content).await;
+        }
+        self.http_client.upload_reader(file_name, reader, len).await
+    }
+
+    /// Inspect a file locally before uploading it. The file is validated
+    /// like an upload, then media whose headers or container structure are
+    /// broken or truncated, or images that do not decode, are rejected with
+    /// an `Error::InvalidFile` explaining what is wrong. Compressed audio and
+    /// video data is not decoded, so damage inside it goes undetected.
+    pub async fn inspect(&self, path: impl AsRef<Path>) -> Result<MediaInfo> {
+        let path = path.as_ref();
+        let file_path = path
+            .to_str()
+            .ok_or_else(|| Error::InvalidFile(format!("Invalid file path: {}", path.display())))?;
+        self.http_client.inspect_file(file_path).await
+    }
+
+    /// Download media from a direct URL, such as a CDN or signed storage
+    /// link, and upload it for analysis. With a dedupe cache, the upload of
+    /// identical content is reused.
+    pub async fn upload_from_url(&self, url: &str, options: FetchOptions) -> Result<UploadResult> {
+        let (file_name, content) = self.http_client.fetch_from_url(url, &options).await?;
+        self.upload_bytes(&file_name, content).await
     }
 
     // Upload a social media link for analysis.
@@ -44,23 +242,26 @@ impl Client {
         options: Option<GetResultOptions>,
     ) -> Result<DetectionResult> {
         let opts = options.unwrap_or_default();
-        let should_wait =
-            opts.max_attempts.unwrap_or(0) > 0 && opts.polling_interval.unwrap_or(0) > 0;
-
-        if should_wait {
-            self.wait_for_result(
-                request_id,
-                opts.max_attempts.unwrap(),
-                opts.polling_interval.unwrap(),
-            )
-            .await
-        } else {
-            self.fetch_result(request_id).await
-        }
+        let strategy = select_strategy(
+            &opts.poll_strategy,
+            opts.max_attempts,
+            opts.polling_interval,
+            opts.deadline,
+        );
+        cancellable(opts.cancel.as_ref(), &[request_id], async {
+            match strategy {
+                Some(strategy) => {
+                    self.wait_for_result(request_id, strategy.as_ref(), opts.deadline)
+                        .await
+                }
+                None => self.fetch_result(request_id).await,
+            }
+        })
+        .await
     }
 
     /// Fetch a result without waiting
-    async fn fetch_result(&self, request_id: &str) -> Result<DetectionResult> {
+    pub(crate) async fn fetch_result(&self, request_id: &str) -> Result<DetectionResult> {
         let endpoint = format!("{}/{}", api_paths::MEDIA_RESULT, request_id);
         let result = self.http_client.get::<AnalysisResult>(&endpoint).await?;
 
@@ -71,20 +272,15 @@ impl Client {
     /// Normalize scores from 0-100 to 0-1 range
     fn normalize_scores(&self, result: &AnalysisResult) -> DetectionResult {
         let mut detection_result = DetectionResult {
-            // Replace FAKE with MANIPULATED in overall status
-            status: if result.status == "FAKE" {
-                "MANIPULATED".to_string()
-            } else {
-                result.status.clone()
-            },
+            status: result.status.clone(),
             request_id: result.request_id.clone(),
             score: result.final_score.map(|final_score| final_score / 100.0),
             models: vec![],
         };
 
         // Check if we have a score in resultsSummary metadata
-        if result.results_summary.is_some() {
-            if let Some(metadata) = &result.results_summary.as_ref().unwrap().metadata {
+        if let Some(results_summary) = &result.results_summary {
+            if let Some(metadata) = &results_summary.metadata {
                 if let Some(final_score) = metadata.get("finalScore") {
                     if let Some(score_value) = final_score.as_f64() {
                         detection_result.score = Some(score_value / 100.0)
@@ -97,14 +293,10 @@ impl Client {
         detection_result.models = result
             .models
             .iter()
-            .filter(|model| model.status != "NOT_APPLICABLE")
+            .filter(|model| model.status != AnalysisStatus::NotApplicable)
             .map(|model| DetectionModelResult {
                 name: model.name.clone(),
-                status: if model.status == "FAKE" {
-                    "MANIPULATED".to_string()
-                } else {
-                    model.status.clone()
-                },
+                status: model.status.clone(),
                 score: match model.prediction_number {
                     Some(FloatOrObject::Float(val)) => Some(val),
                     _ => None,
@@ -115,136 +307,440 @@ impl Client {
         detection_result
     }
 
-    /// Wait for a result to be ready
+    /// Wait for a result to be ready, giving up with the last result fetched
+    /// when the strategy or the deadline says so
     async fn wait_for_result(
         &self,
         request_id: &str,
-        max_attempts: u64,
-        polling_interval: u64,
+        strategy: &dyn PollStrategy,
+        deadline: Option<Duration>,
     ) -> Result<DetectionResult> {
         let start_time = Instant::now();
+        let mut attempt = 0;
 
-        for _ in 0..max_attempts {
+        loop {
             let result = self.fetch_result(request_id).await?;
+            attempt += 1;
 
-            // Check if analysis is complete. The API uses "ANALYZING" while processing
-            // and various status values when complete.
-            match result.status.as_str() {
-                "ANALYZING" | "DOWNLOADING" => sleep(Duration::from_millis(polling_interval)).await,
-                // Any other status means the analysis is done (COMPLETED, ERROR, etc.)
-                _ => {
-                    return Ok(result);
+            if result.status.is_terminal() {
+                return Ok(result);
+            }
+            match next_delay(strategy, deadline, attempt, start_time.elapsed()) {
+                Some(delay) => sleep(delay).await,
+                None => {
+                    return Err(Error::Timeout {
+                        request_id: request_id.to_string(),
+                        elapsed: start_time.elapsed(),
+                        last: Some(Box::new(result)),
+                    })
                 }
             }
         }
+    }
+
+    /// Watch a result as it is analyzed, polling according to `strategy`.
+    ///
+    /// The stream yields the first result fetched, then each result whose
+    /// status or scores changed, and ends after a terminal result. A failed
+    /// fetch ends it with the error, and giving up ends it with
+    /// `Error::Timeout`.
+    pub fn watch_result<'a>(
+        &'a self,
+        request_id: &str,
+        strategy: Arc<dyn PollStrategy>,
+    ) -> impl Stream<Item = Result<DetectionResult>> + 'a {
+        let request_id = request_id.to_string();
+        let start_time = Instant::now();
 
-        Err(Error::UnknownError(format!(
-            "Timed out waiting for result after {} seconds",
-            (Instant::now() - start_time).as_secs()
-        )))
+        // The state holds the last result yielded and the number of polls,
+        // or `None` once the stream has ended
+        stream::unfold(Some((None, 0)), move |state| {
+            let request_id = request_id.clone();
+            let strategy = strategy.clone();
+            async move {
+                let (last, mut attempt): (Option<DetectionResult>, u64) = state?;
+                loop {
+                    if attempt > 0 {
+                        match strategy.next_delay(attempt, start_time.elapsed()) {
+                            Some(delay) => sleep(delay).await,
+                            None => {
+                                let timeout = Error::Timeout {
+                                    request_id,
+                                    elapsed: start_time.elapsed(),
+                                    last: last.map(Box::new),
+                                };
+                                return Some((Err(timeout), None));
+                            }
+                        }
+                    }
+
+                    let result = match self.fetch_result(&request_id).await {
+                        Ok(result) => result,
+                        Err(err) => return Some((Err(err), None)),
+                    };
+                    attempt += 1;
+
+                    if result.status.is_terminal() {
+                        return Some((Ok(result), None));
+                    }
+                    if last.as_ref() != Some(&result) {
+                        return Some((Ok(result.clone()), Some((Some(result), attempt))));
+                    }
+                }
+            }
+        })
+    }
+
+    /// Create a poller that waits on many requests at once, refreshing them
+    /// in bulk through the paginated results list
+    pub fn result_poller(&self, options: PollerOptions) -> ResultPoller<'_> {
+        ResultPoller::new(self, options)
     }
 
-    /// Process a batch of files concurrently
+    /// Process a batch of files concurrently.
+    ///
+    /// Returns one entry per input path, in input order, so failed uploads and
+    /// failed result fetches are reported alongside the successful ones.
     pub async fn process_batch(
         &self,
         file_paths: Vec<&str>,
         options: BatchOptions,
-    ) -> Result<Vec<DetectionResult>> {
+    ) -> Result<BatchReport> {
+        let file_paths = file_paths.into_iter().map(PathBuf::from).collect();
+        self.process_paths(file_paths, options).await
+    }
+
+    /// Walk a directory tree and process every supported file in a batch.
+    ///
+    /// Files are selected with the include and exclude globs, hidden-file,
+    /// symlink and depth policies of the options, and must pass the supported
+    /// file type and size checks. Everything else is reported as skipped.
+    pub async fn process_directory(
+        &self,
+        root: impl AsRef<Path>,
+        options: DirectoryOptions,
+    ) -> Result<DirectoryReport> {
+        let root = root.as_ref().to_path_buf();
+        let walk_options = options.clone();
+
+        // Walking a large tree is blocking filesystem work
+        let files = tokio::task::spawn_blocking(move || collect_files(&root, &walk_options))
+            .await
+            .map_err(|err| Error::UnknownError(format!("Directory walk failed: {err}")))??;
+
+        let batch = self.process_paths(files.selected, options.batch).await?;
+
+        Ok(DirectoryReport {
+            batch,
+            skipped: files.skipped,
+        })
+    }
+
+    /// Process a batch of paths, keeping the report in input order
+    async fn process_paths(
+        &self,
+        file_paths: Vec<PathBuf>,
+        options: BatchOptions,
+    ) -> Result<BatchReport> {
         if file_paths.is_empty() {
-            return Ok(Vec::new());
+            return Ok(BatchReport::default());
         }
 
-        let max_concurrency = options.max_concurrency.unwrap_or(5);
-        let should_wait =
-            options.max_attempts.unwrap_or(0) > 0 && options.polling_interval.unwrap_or(0) > 0;
+        let limits = BatchLimits::new(&options);
+        let tracker = BatchTracker::new(&options, Some(file_paths.len()));
+        let get_options = batch_get_options(&options);
+
+        let journal = match &options.journal_path {
+            Some(journal_path) => {
+                let journal = BatchJournal::open(journal_path)?;
+                journal.record_queued(&file_paths)?;
+                Some(journal)
+            }
+            None => None,
+        };
 
-        // Upload all files concurrently with limited concurrency
-        let uploads = future::join_all(
-            file_paths
-                .chunks(max_concurrency)
-                .map(|chunk| {
-                    let chunk_futures = chunk.iter().map(|&path| {
-                        let upload_options = UploadOptions {
-                            file_path: path.to_string(),
+        // Run the per-file pipelines out of order so a slow file does not hold
+        // back the others, then restore the input order for the report.
+        // Uploaded files wait in a stage of their own, bounded by the polling
+        // permits alone, so files waiting for a poll never hold back uploads.
+        let mut entries: Vec<(usize, BatchEntry)> =
+            stream::iter(file_paths.into_iter().enumerate())
+                .map(|(index, path)| {
+                    let limits = &limits;
+                    let tracker = &tracker;
+                    let journal = journal.as_ref();
+                    async move {
+                        tracker.started();
+                        let stage = self
+                            .upload_batch_entry(path, limits, tracker, journal)
+                            .await;
+                        (index, stage)
+                    }
+                })
+                .buffer_unordered(limits.max_uploads)
+                .map(|(index, stage)| {
+                    let limits = &limits;
+                    let tracker = &tracker;
+                    let get_options = get_options.as_ref();
+                    let journal = journal.as_ref();
+                    async move {
+                        let entry = match stage {
+                            ControlFlow::Break(entry) => entry,
+                            ControlFlow::Continue((path, upload)) => {
+                                self.await_batch_entry(path, upload, limits, get_options, journal)
+                                    .await
+                            }
                         };
-                        self.upload(upload_options)
-                    });
-                    future::join_all(chunk_futures)
+                        tracker.finished(entry.result.as_ref());
+                        (index, entry)
+                    }
                 })
-                .collect::<Vec<_>>(),
-        )
-        .await
-        .into_iter()
-        .flatten()
-        .collect::<Vec<Result<UploadResult>>>();
-
-        // Collect all request IDs from successful uploads
-        let request_ids: Vec<String> = uploads
-            .into_iter()
-            .filter_map(|upload_result| match upload_result {
-                Ok(result) => Some(result.request_id),
-                Err(_) => None,
+                .buffer_unordered(usize::MAX)
+                .collect()
+                .await;
+        entries.sort_by_key(|(index, _)| *index);
+
+        Ok(BatchReport::from_entries(
+            entries.into_iter().map(|(_, entry)| entry).collect(),
+        ))
+    }
+
+    /// Process a stream of files concurrently, yielding each file's outcome as
+    /// soon as it reaches a terminal state.
+    ///
+    /// Outcomes are yielded in completion order, not input order. Results are
+    /// always awaited; when `max_attempts` and `polling_interval` are not set,
+    /// the same defaults as [`Client::detect_file`] are used. Once the batch
+    /// is cancelled, files yield `Error::Cancelled` with the request ID of
+    /// their upload, if any. With a journal, each path is recorded as it
+    /// arrives; if the journal cannot be opened, every file yields that error
+    /// without being uploaded.
+    pub fn process_stream<'a, S>(
+        &'a self,
+        file_paths: S,
+        options: BatchOptions,
+    ) -> impl Stream<Item = (PathBuf, Result<DetectionResult>)> + 'a
+    where
+        S: Stream<Item = PathBuf> + 'a,
+    {
+        let limits = Arc::new(BatchLimits::new(&options));
+        let tracker = Arc::new(BatchTracker::new(&options, None));
+        let get_options = batch_get_options(&options).unwrap_or_else(default_get_options);
+        let journal = Arc::new(options.journal_path.as_deref().map(BatchJournal::open));
+        let max_uploads = limits.max_uploads;
+        let (upload_limits, upload_tracker, upload_journal) =
+            (limits.clone(), tracker.clone(), journal.clone());
+
+        // As in `process_paths`, waits run in their own stage so they never
+        // hold back uploads
+        file_paths
+            .map(move |path| {
+                let limits = upload_limits.clone();
+                let tracker = upload_tracker.clone();
+                let journal = upload_journal.clone();
+                async move {
+                    tracker.started();
+                    let journal = match journal.as_ref() {
+                        Some(Ok(journal)) => Some(journal),
+                        Some(Err(err)) => {
+                            return ControlFlow::Break(BatchEntry {
+                                path,
+                                upload: None,
+                                result: Some(Err(err.duplicate())),
+                            })
+                        }
+                        None => None,
+                    };
+                    if let Some(journal) = journal {
+                        if let Err(err) = journal.record_queued(slice::from_ref(&path)) {
+                            return ControlFlow::Break(BatchEntry {
+                                path,
+                                upload: None,
+                                result: Some(Err(err)),
+                            });
+                        }
+                    }
+                    self.upload_batch_entry(path, &limits, &tracker, journal)
+                        .await
+                }
             })
-            .collect();
+            .buffer_unordered(max_uploads)
+            .map(move |stage| {
+                let limits = limits.clone();
+                let tracker = tracker.clone();
+                let get_options = get_options.clone();
+                let journal = journal.clone();
+                async move {
+                    let entry = match stage {
+                        ControlFlow::Break(entry) => entry,
+                        ControlFlow::Continue((path, upload)) => {
+                            let journal = journal
+                                .as_ref()
+                                .as_ref()
+                                .and_then(|journal| journal.as_ref().ok());
+                            self.await_batch_entry(
+                                path,
+                                upload,
+                                &limits,
+                                Some(&get_options),
+                                journal,
+                            )
+                            .await
+                        }
+                    };
+                    // Only a cancelled wait leaves a file without a result
+                    let result = entry.result.unwrap_or_else(|| {
+                        Err(Error::Cancelled {
+                            request_ids: entry
+                                .upload
+                                .map(|upload| upload.request_id)
+                                .into_iter()
+                                .collect(),
+                        })
+                    });
+                    tracker.finished(Some(&result));
+                    (entry.path, result)
+                }
+            })
+            .buffer_unordered(usize::MAX)
+    }
 
-        // If waiting for results is enabled, get all results
-        if should_wait {
-            let get_options = GetResultOptions {
-                max_attempts: options.max_attempts,
-                polling_interval: options.polling_interval,
-            };
+    /// Upload a single batch file, resuming from the journal state when one
+    /// was recorded. Breaks with the entry of a file that needs no wait.
+    async fn upload_batch_entry(
+        &self,
+        path: PathBuf,
+        limits: &BatchLimits,
+        tracker: &BatchTracker,
+        journal: Option<&BatchJournal>,
+    ) -> ControlFlow<BatchEntry, (PathBuf, UploadResult)> {
+        let upload = match journal.and_then(|journal| journal.state(&path)) {
+            Some(JournalState::Completed { upload, result }) => {
+                return ControlFlow::Break(BatchEntry {
+                    path,
+                    upload: Some(upload.clone()),
+                    result: Some(Ok(result.clone())),
+                })
+            }
+            Some(JournalState::Uploaded { upload }) => upload.clone(),
+            Some(JournalState::Queued) | None => {
+                let upload = match self.upload_batch_file(&path, limits, tracker).await {
+                    Ok(upload) => upload,
+                    Err(err) => {
+                        return ControlFlow::Break(BatchEntry {
+                            path,
+                            upload: None,
+                            result: Some(Err(err)),
+                        })
+                    }
+                };
+
+                if let Some(journal) = journal {
+                    if let Err(err) = journal.record_uploaded(&path, &upload) {
+                        return ControlFlow::Break(BatchEntry {
+                            path,
+                            upload: Some(upload),
+                            result: Some(Err(err)),
+                        });
+                    }
+                }
+                upload
+            }
+        };
 
-            // Get results concurrently with limited concurrency
-            let results = future::join_all(
-                request_ids
-                    .chunks(max_concurrency)
-                    .map(|chunk| {
-                        let chunk_futures = chunk
-                            .iter()
-                            .map(|id| self.get_result(id, Some(get_options.clone())));
-                        future::join_all(chunk_futures)
-                    })
-                    .collect::<Vec<_>>(),
-            )
-            .await
-            .into_iter()
-            .flatten()
-            .collect::<Vec<Result<DetectionResult>>>();
+        ControlFlow::Continue((path, upload))
+    }
 
-            // Filter out errors and return successful results
-            Ok(results.into_iter().filter_map(|r| r.ok()).collect())
-        } else {
-            // Just return empty results with request IDs if not waiting
-            Ok(request_ids
-                .into_iter()
-                .map(|id| DetectionResult {
-                    request_id: id,
-                    status: "PROCESSING".to_string(),
-                    score: None,
-                    models: Vec::new(),
-                })
-                .collect())
+    /// Optionally wait for the result of an uploaded batch file
+    async fn await_batch_entry(
+        &self,
+        path: PathBuf,
+        upload: UploadResult,
+        limits: &BatchLimits,
+        get_options: Option<&GetResultOptions>,
+        journal: Option<&BatchJournal>,
+    ) -> BatchEntry {
+        let result = match get_options {
+            Some(get_options) => {
+                match self
+                    .wait_batch_result(&upload, limits, get_options.clone())
+                    .await
+                {
+                    // A cancelled wait leaves the file pending with its upload
+                    Err(Error::Cancelled { .. }) => None,
+                    result => Some(result.and_then(|result| {
+                        if let Some(journal) = journal {
+                            journal.record_completed(&path, &upload, &result)?;
+                        }
+                        Ok(result)
+                    })),
+                }
+            }
+            None => None,
+        };
+
+        BatchEntry {
+            path,
+            upload: Some(upload),
+            result,
         }
     }
 
+    /// Upload a batch file while holding an upload permit. With a dedupe
+    /// cache, the upload of identical content is reused.
+    async fn upload_batch_file(
+        &self,
+        path: &Path,
+        limits: &BatchLimits,
+        tracker: &BatchTracker,
+    ) -> Result<UploadResult> {
+        // The semaphores are never closed, so acquiring a permit cannot fail.
+        // Once it is acquired, the upload is finished even if the batch is
+        // cancelled, so its request ID is not lost.
+        let _permit = cancellable(limits.cancel.as_ref(), &[], async {
+            Ok(limits.uploads.acquire().await)
+        })
+        .await?;
+        let (_, entry, _) = self
+            .upload_path(&path.to_string_lossy(), tracker.upload_progress(), false)
+            .await?;
+        Ok(entry.upload)
+    }
+
+    /// Wait for the result of a batch upload while holding a polling permit
+    async fn wait_batch_result(
+        &self,
+        upload: &UploadResult,
+        limits: &BatchLimits,
+        get_options: GetResultOptions,
+    ) -> Result<DetectionResult> {
+        cancellable(limits.cancel.as_ref(), &[&upload.request_id], async {
+            let _permit = limits.polls.acquire().await;
+            self.get_result(&upload.request_id, Some(get_options)).await
+        })
+        .await
+    }
+
     /// Get a paginated list of detection results with optional filters
     pub async fn get_results(
         &self,
         options: Option<GetResultsOptions>,
     ) -> Result<FormattedDetectionResultList> {
         let opts = options.unwrap_or_default();
-        let should_wait =
-            opts.max_attempts.unwrap_or(0) > 0 && opts.polling_interval.unwrap_or(0) > 0;
-
-        if should_wait {
-            self.wait_for_results(opts).await
-        } else {
-            self.fetch_results(opts).await
+        let strategy = select_strategy(
+            &opts.poll_strategy,
+            opts.max_attempts,
+            opts.polling_interval,
+            None,
+        );
+        match strategy {
+            Some(strategy) => self.wait_for_results(opts, strategy.as_ref()).await,
+            None => self.fetch_results(opts).await,
         }
     }
 
     /// Fetch results without waiting
-    async fn fetch_results(
+    pub(crate) async fn fetch_results(
         &self,
         options: GetResultsOptions,
     ) -> Result<FormattedDetectionResultList> {
@@ -278,33 +774,38 @@ impl Client {
         Ok(self.format_results_list(&raw_result))
     }
 
-    /// Wait for results with retry logic
+    /// Wait until no listed result is still being analyzed, returning
+    /// `Error::ResultsTimeout` with the last list fetched when the strategy
+    /// gives up
     async fn wait_for_results(
         &self,
         options: GetResultsOptions,
+        strategy: &dyn PollStrategy,
     ) -> Result<FormattedDetectionResultList> {
-        let max_attempts = options.max_attempts.unwrap_or(5);
-        let polling_interval = options.polling_interval.unwrap_or(2000);
-
         let start_time = Instant::now();
+        let mut attempt = 0;
 
-        for _ in 0..max_attempts {
+        loop {
             let result = self.fetch_results(options.clone()).await?;
+            attempt += 1;
 
             // Check if any results are still analyzing
-            let still_analyzing = result.items.iter().any(|item| item.status == "ANALYZING");
+            let still_analyzing = result.items.iter().any(|item| !item.status.is_terminal());
 
             if !still_analyzing {
                 return Ok(result);
             }
 
-            sleep(Duration::from_millis(polling_interval)).await;
+            match strategy.next_delay(attempt, start_time.elapsed()) {
+                Some(delay) => sleep(delay).await,
+                None => {
+                    return Err(Error::ResultsTimeout {
+                        elapsed: start_time.elapsed(),
+                        last: Box::new(result),
+                    })
+                }
+            }
         }
-
-        Err(Error::UnknownError(format!(
-            "Timed out waiting for results after {} seconds",
-            (Instant::now() - start_time).as_secs()
-        )))
     }
 
     /// Format raw results list into user-friendly format
@@ -327,32 +828,253 @@ impl Client {
         }
     }
 
-    /// Simplified method to detect a file
+    /// Simplified method to detect a file. With a dedupe cache, the result
+    /// of identical content is reused.
     pub async fn detect_file(&self, file_path: &str) -> Result<DetectionResult> {
-        let upload_result = self
-            .upload(UploadOptions {
-                file_path: file_path.to_string(),
-            })
-            .await?;
+        self.detect_file_cached(file_path, false).await
+    }
 
-        self.get_result(
-            &upload_result.request_id,
-            Some(GetResultOptions {
-                max_attempts: Some(150),
-                polling_interval: Some(2000),
-            }),
+    /// Detect a file through the dedupe cache, reusing the terminal result
+    /// of identical content unless `refresh` is set to force a new upload
+    pub async fn detect_file_cached(
+        &self,
+        file_path: &str,
+        refresh: bool,
+    ) -> Result<DetectionResult> {
+        self.detect(file_path, refresh, default_get_options()).await
+    }
+
+    /// Detect a file, polling for its result according to `strategy`
+    pub async fn detect_file_with_strategy(
+        &self,
+        file_path: &str,
+        strategy: Arc<dyn PollStrategy>,
+    ) -> Result<DetectionResult> {
+        let options = GetResultOptions {
+            poll_strategy: Some(strategy),
+            ..Default::default()
+        };
+        self.detect(file_path, false, options).await
+    }
+
+    /// Detect a file with explicit result options, e.g. to set a deadline or
+    /// to cancel the wait. Without any polling setting, the same defaults as
+    /// [`Client::detect_file`] are used.
+    pub async fn detect_file_with_options(
+        &self,
+        file_path: &str,
+        options: GetResultOptions,
+    ) -> Result<DetectionResult> {
+        let waits = select_strategy(
+            &options.poll_strategy,
+            options.max_attempts,
+            options.polling_interval,
+            options.deadline,
         )
-        .await
+        .is_some();
+        let options = if waits {
+            options
+        } else {
+            GetResultOptions {
+                cancel: options.cancel,
+                ..default_get_options()
+            }
+        };
+        self.detect(file_path, false, options).await
+    }
+
+    /// Upload a file through the dedupe cache and wait for its result
+    async fn detect(
+        &self,
+        file_path: &str,
+        refresh: bool,
+        options: GetResultOptions,
+    ) -> Result<DetectionResult> {
+        if options
+            .cancel
+            .as_ref()
+            .is_some_and(|cancel| cancel.is_cancelled())
+        {
+            return Err(Error::Cancelled {
+                request_ids: vec![],
+            });
+        }
+
+        let (hash, mut entry, _) = self.upload_path(file_path, None, refresh).await?;
+        if let Some(result) = entry.result {
+            return Ok(result);
+        }
+
+        let result = self
+            .get_result(&entry.upload.request_id, Some(options))
+            .await?;
+
+        // Verdicts are reused. A failed analysis is recorded too, so the next
+        // lookup uploads the content again instead of polling the failed
+        // request.
+        if let (Some(dedupe), Some(hash)) = (&self.dedupe, hash) {
+            if result.status.is_terminal() {
+                entry.result = Some(result.clone());
+                dedupe.store.put(&hash, &entry)?;
+            }
+        }
+
+        Ok(result)
+    }
+}
+
+/// Concurrency limits shared by all files of a batch
+struct BatchLimits {
+    uploads: Semaphore,
+    polls: Semaphore,
+    max_uploads: usize,
+    cancel: Option<CancellationToken>,
+}
+
+impl BatchLimits {
+    fn new(options: &BatchOptions) -> Self {
+        let max_uploads = options.max_concurrency.unwrap_or(5).max(1);
+        let max_polls = options
+            .max_polling_concurrency
+            .unwrap_or(max_uploads)
+            .max(1);
+
+        Self {
+            uploads: Semaphore::new(max_uploads),
+            polls: Semaphore::new(max_polls),
+            max_uploads,
+            cancel: options.cancel.clone(),
+        }
+    }
+}
+
+/// Publishes the progress of a batch on the channel of its options, if any
+struct BatchTracker {
+    progress: Option<watch::Sender<BatchProgress>>,
+}
+
+impl BatchTracker {
+    fn new(options: &BatchOptions, total_files: Option<usize>) -> Self {
+        if let Some(progress) = &options.progress {
+            progress.send_replace(BatchProgress {
+                total_files,
+                ..Default::default()
+            });
+        }
+
+        Self {
+            progress: options.progress.clone(),
+        }
+    }
+
+    fn update(&self, modify: impl FnOnce(&mut BatchProgress)) {
+        if let Some(progress) = &self.progress {
+            progress.send_modify(modify);
+        }
+    }
+
+    /// Record that a file entered the pipeline
+    fn started(&self) {
+        self.update(|progress| progress.files_in_flight += 1);
+    }
+
+    /// Record that a file left the pipeline with the given result, `None`
+    /// when it was uploaded without awaiting its result
+    fn finished(&self, result: Option<&Result<DetectionResult>>) {
+        self.update(|progress| {
+            progress.files_in_flight -= 1;
+            progress.count_finished(result);
+        });
+    }
+
+    /// Callback adding the progress of a single upload to the batch totals.
+    /// A retried upload replaces the bytes counted for its previous attempt.
+    fn upload_progress(&self) -> Option<ProgressCallback> {
+        let progress = self.progress.clone()?;
+        let last = Mutex::new(UploadProgress::default());
+
+        Some(Arc::new(move |update: UploadProgress| {
+            let mut last = last.lock().unwrap_or_else(|err| err.into_inner());
+            progress.send_modify(|batch| {
+                batch.bytes_sent = batch.bytes_sent - last.bytes_sent + update.bytes_sent;
+                batch.total_bytes = batch.total_bytes - last.total_bytes + update.total_bytes;
+            });
+            *last = update;
+        }))
+    }
+}
+
+/// Run `future` unless `cancel` is cancelled first, reporting the analyses
+/// in `request_ids` as already submitted
+pub(crate) async fn cancellable<T>(
+    cancel: Option<&CancellationToken>,
+    request_ids: &[&str],
+    future: impl Future<Output = Result<T>>,
+) -> Result<T> {
+    let cancel = match cancel {
+        Some(cancel) => cancel,
+        None => return future.await,
+    };
+
+    tokio::select! {
+        biased;
+        _ = cancel.cancelled() => Err(Error::Cancelled {
+            request_ids: request_ids.iter().map(|id| id.to_string()).collect(),
+        }),
+        result = future => result,
     }
 }
 
+/// Result options used when waiting without explicit polling settings
+fn default_get_options() -> GetResultOptions {
+    GetResultOptions {
+        max_attempts: Some(150),
+        polling_interval: Some(2000),
+        ..Default::default()
+    }
+}
+
+/// Result options for a batch, or `None` if results should not be awaited
+fn batch_get_options(options: &BatchOptions) -> Option<GetResultOptions> {
+    let strategy = select_strategy(
+        &options.poll_strategy,
+        options.max_attempts,
+        options.polling_interval,
+        options.deadline,
+    );
+
+    strategy.is_some().then(|| GetResultOptions {
+        max_attempts: options.max_attempts,
+        polling_interval: options.polling_interval,
+        poll_strategy: options.poll_strategy.clone(),
+        deadline: options.deadline,
+        ..Default::default()
+    })
+}
+
 #[cfg(test)]
 mod tests {
-    use crate::{BatchOptions, Client, Config, Error, GetResultOptions, UploadOptions};
+    use super::{batch_get_options, BatchLimits};
+    use crate::{
+        AnalysisStatus, BatchOptions, BatchProgress, Client, Config, DedupeCache, DirectoryOptions,
+        Error, ExponentialBackoff, FixedInterval, GetResultOptions, GetResultsOptions,
+        MemoryDedupeStore, SkipReason, UploadOptions,
+    };
+    use futures::StreamExt;
+    use mockito::Matcher;
     use serde_json::json;
+    use std::collections::HashMap;
     use std::fs::File;
     use std::io::Write;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::time::Duration;
     use tempfile::tempdir;
+    use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+    use tokio::net::TcpListener;
+    use tokio::sync::watch;
+    use tokio::time::sleep;
+    use tokio_util::sync::CancellationToken;
 
     #[tokio::test]
     async fn test_client_new() {
@@ -438,17 +1160,17 @@ mod tests {
         let result = client.get_result(request_id, None).await.unwrap();
 
         assert_eq!(result.request_id, request_id);
-        assert_eq!(result.status, "COMPLETED");
+        assert_eq!(result.status.as_str(), "COMPLETED");
         assert_eq!(result.score, Some(0.85));
         assert_eq!(result.models.len(), 2);
 
         assert_eq!(result.models[0].name, "TestModel");
         assert_eq!(result.models[0].score, Some(0.27));
-        assert_eq!(result.models[0].status, "COMPLETED");
+        assert_eq!(result.models[0].status.as_str(), "COMPLETED");
 
         assert_eq!(result.models[1].name, "TestModel2");
         assert_eq!(result.models[1].score, None);
-        assert_eq!(result.models[1].status, "COMPLETED");
+        assert_eq!(result.models[1].status.as_str(), "COMPLETED");
 
         mock.assert_async().await;
     }
@@ -649,6 +1371,7 @@ mod tests {
                 Some(GetResultOptions {
                     max_attempts: Some(5),
                     polling_interval: Some(1000), // Short timeout for test
+                    ..Default::default()
                 }),
             )
             .await;
@@ -656,7 +1379,7 @@ mod tests {
         assert!(result.is_ok());
         let analysis_result = result.unwrap();
         assert_eq!(analysis_result.request_id, request_id);
-        assert_eq!(analysis_result.status, "COMPLETED");
+        assert_eq!(analysis_result.status.as_str(), "COMPLETED");
         assert_eq!(analysis_result.score, Some(0.75));
 
         mock1.assert_async().await;
@@ -664,47 +1387,313 @@ mod tests {
     }
 
     #[tokio::test]
-    async fn test_process_batch_empty() {
+    async fn test_wait_for_result_with_poll_strategy() {
+        let mut server = mockito::Server::new_async().await;
+        let request_id = "test-strategy-request";
+
+        let mock = server
+            .mock("GET", format!("/api/media/users/{}", request_id).as_str())
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(
+                json!({
+                    "requestId": request_id,
+                    "overallStatus": "ANALYZING",
+                    "models": []
+                })
+                .to_string(),
+            )
+            .expect(3)
+            .create_async()
+            .await;
+
         let client = Client::new(Config {
             api_key: "test_api_key".to_string(),
+            base_url: Some(server.url()),
             ..Default::default()
         })
         .unwrap();
 
-        // Test with empty file paths
+        // The strategy alone enables waiting, and decides when to give up
         let result = client
-            .process_batch(
-                vec![],
-                BatchOptions {
-                    max_concurrency: Some(2),
-                    max_attempts: Some(10),
-                    polling_interval: Some(1000),
-                },
+            .get_result(
+                request_id,
+                Some(GetResultOptions {
+                    poll_strategy: Some(Arc::new(FixedInterval {
+                        interval: Duration::from_millis(10),
+                        max_attempts: 3,
+                    })),
+                    ..Default::default()
+                }),
             )
             .await;
 
-        assert!(result.is_ok());
-        let batch_results = result.unwrap();
-        assert!(batch_results.is_empty());
+        match result {
+            Err(Error::Timeout {
+                request_id: timed_out,
+                last: Some(last),
+                ..
+            }) => {
+                assert_eq!(timed_out, request_id);
+                assert_eq!(last.status.as_str(), "ANALYZING");
+            }
+            other => panic!("Expected Timeout error, got: {:?}", other),
+        }
+        mock.assert_async().await;
     }
 
     #[tokio::test]
-    async fn test_process_batch_without_waiting() {
+    async fn test_wait_for_results_times_out() {
         let mut server = mockito::Server::new_async().await;
 
-        // Create temporary test files
-        let dir = tempdir().unwrap();
-        let file_path1 = dir.path().join("test1.jpg");
-        let mut file1 = File::create(&file_path1).unwrap();
-        file1.write_all(b"test image data 1").unwrap();
-
-        let file_path2 = dir.path().join("test2.jpg");
+        let mock = server
+            .mock("GET", "/api/v2/media/users/pages/0")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(
+                json!({
+                    "totalItems": 2,
+                    "totalPages": 1,
+                    "currentPage": 0,
+                    "currentPageItemsCount": 2,
+                    "mediaList": [
+                        {"requestId": "a", "overallStatus": "AUTHENTIC", "finalScore": 10, "models": []},
+                        {"requestId": "b", "overallStatus": "ANALYZING", "models": []},
+                    ],
+                })
+                .to_string(),
+            )
+            .expect(2)
+            .create_async()
+            .await;
+
+        let client = Client::new(Config {
+            api_key: "test_api_key".to_string(),
+            base_url: Some(server.url()),
+            ..Default::default()
+        })
+        .unwrap();
+
+        let result = client
+            .get_results(Some(GetResultsOptions {
+                poll_strategy: Some(Arc::new(FixedInterval {
+                    interval: Duration::from_millis(10),
+                    max_attempts: 2,
+                })),
+                ..Default::default()
+            }))
+            .await;
+
+        match result {
+            Err(Error::ResultsTimeout { elapsed, last }) => {
+                assert!(elapsed >= Duration::from_millis(10));
+                assert_eq!(last.items.len(), 2);
+                assert_eq!(last.items[1].request_id, "b");
+                assert_eq!(last.items[1].status.as_str(), "ANALYZING");
+            }
+            other => panic!("Expected ResultsTimeout error, got: {:?}", other),
+        }
+        mock.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_wait_for_result_until_deadline() {
+        let mut server = mockito::Server::new_async().await;
+        let request_id = "test-deadline-request";
+
+        let mock = server
+            .mock("GET", format!("/api/media/users/{}", request_id).as_str())
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(
+                json!({
+                    "requestId": request_id,
+                    "overallStatus": "ANALYZING",
+                    "models": []
+                })
+                .to_string(),
+            )
+            .expect_at_least(2)
+            .create_async()
+            .await;
+
+        let client = Client::new(Config {
+            api_key: "test_api_key".to_string(),
+            base_url: Some(server.url()),
+            ..Default::default()
+        })
+        .unwrap();
+
+        // The deadline alone enables waiting, with a last poll at the deadline
+        let result = client
+            .get_result(
+                request_id,
+                Some(GetResultOptions {
+                    deadline: Some(Duration::from_millis(50)),
+                    ..Default::default()
+                }),
+            )
+            .await;
+
+        match result {
+            Err(Error::Timeout { elapsed, last, .. }) => {
+                assert!(elapsed >= Duration::from_millis(50), "{elapsed:?}");
+                assert!(elapsed < Duration::from_secs(2), "{elapsed:?}");
+                assert_eq!(last.unwrap().request_id, request_id);
+            }
+            other => panic!("Expected Timeout error, got: {:?}", other),
+        }
+        mock.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_watch_result_yields_changes() {
+        let mut server = mockito::Server::new_async().await;
+        let request_id = "test-watch-request";
+
+        // DOWNLOADING twice, ANALYZING twice, then a score update and a result
+        let responses = [
+            json!({"requestId": request_id, "overallStatus": "DOWNLOADING", "models": []}),
+            json!({"requestId": request_id, "overallStatus": "DOWNLOADING", "models": []}),
+            json!({"requestId": request_id, "overallStatus": "ANALYZING", "models": []}),
+            json!({"requestId": request_id, "overallStatus": "ANALYZING", "models": []}),
+            json!({"requestId": request_id, "overallStatus": "ANALYZING", "finalScore": 40, "models": []}),
+            json!({"requestId": request_id, "overallStatus": "MANIPULATED", "finalScore": 95, "models": []}),
+        ];
+        let polls = Arc::new(AtomicUsize::new(0));
+        let counter = polls.clone();
+        server
+            .mock("GET", format!("/api/media/users/{}", request_id).as_str())
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body_from_request(move |_| {
+                let poll = counter
+                    .fetch_add(1, Ordering::SeqCst)
+                    .min(responses.len() - 1);
+                responses[poll].to_string().into_bytes()
+            })
+            .create_async()
+            .await;
+
+        let client = Client::new(Config {
+            api_key: "test_api_key".to_string(),
+            base_url: Some(server.url()),
+            ..Default::default()
+        })
+        .unwrap();
+
+        let strategy = Arc::new(FixedInterval {
+            interval: Duration::from_millis(5),
+            max_attempts: 10,
+        });
+        let updates: Vec<_> = client
+            .watch_result(request_id, strategy)
+            .map(|result| {
+                let result = result.unwrap();
+                (result.status.as_str().to_string(), result.score)
+            })
+            .collect()
+            .await;
+
+        assert_eq!(
+            updates,
+            [
+                ("DOWNLOADING".to_string(), None),
+                ("ANALYZING".to_string(), None),
+                ("ANALYZING".to_string(), Some(0.4)),
+                ("MANIPULATED".to_string(), Some(0.95)),
+            ]
+        );
+        assert_eq!(polls.load(Ordering::SeqCst), 6);
+    }
+
+    #[tokio::test]
+    async fn test_watch_result_times_out() {
+        let mut server = mockito::Server::new_async().await;
+        let request_id = "test-watch-timeout";
+        let mock = server
+            .mock("GET", format!("/api/media/users/{}", request_id).as_str())
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(
+                json!({"requestId": request_id, "overallStatus": "ANALYZING", "models": []})
+                    .to_string(),
+            )
+            .expect(3)
+            .create_async()
+            .await;
+
+        let client = Client::new(Config {
+            api_key: "test_api_key".to_string(),
+            base_url: Some(server.url()),
+            ..Default::default()
+        })
+        .unwrap();
+
+        let strategy = Arc::new(FixedInterval {
+            interval: Duration::from_millis(5),
+            max_attempts: 3,
+        });
+        let updates: Vec<_> = client.watch_result(request_id, strategy).collect().await;
+
+        assert_eq!(updates.len(), 2);
+        assert!(updates[0].is_ok());
+        match &updates[1] {
+            Err(Error::Timeout {
+                last: Some(last), ..
+            }) => {
+                assert_eq!(last.status.as_str(), "ANALYZING")
+            }
+            other => panic!("Expected Timeout error, got: {:?}", other),
+        }
+        mock.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_process_batch_empty() {
+        let client = Client::new(Config {
+            api_key: "test_api_key".to_string(),
+            ..Default::default()
+        })
+        .unwrap();
+
+        // Test with empty file paths
+        let result = client
+            .process_batch(
+                vec![],
+                BatchOptions {
+                    max_concurrency: Some(2),
+                    max_attempts: Some(10),
+                    polling_interval: Some(1000),
+                    ..Default::default()
+                },
+            )
+            .await;
+
+        assert!(result.is_ok());
+        let report = result.unwrap();
+        assert!(report.is_empty());
+        assert_eq!(report.succeeded + report.failed + report.pending, 0);
+    }
+
+    #[tokio::test]
+    async fn test_process_batch_without_waiting() {
+        let mut server = mockito::Server::new_async().await;
+
+        // Create temporary test files
+        let dir = tempdir().unwrap();
+        let file_path1 = dir.path().join("test1.jpg");
+        let mut file1 = File::create(&file_path1).unwrap();
+        file1.write_all(b"test image data 1").unwrap();
+
+        let file_path2 = dir.path().join("test2.jpg");
         let mut file2 = File::create(&file_path2).unwrap();
         file2.write_all(b"test image data 2").unwrap();
 
         // Mock the presigned URL requests
         let mock1 = server
             .mock("POST", "/api/files/aws-presigned")
+            .match_body(Matcher::Json(json!({"fileName": "test1.jpg"})))
             .with_status(200)
             .with_header("content-type", "application/json")
             .with_body(
@@ -724,6 +1713,7 @@ mod tests {
 
         let mock2 = server
             .mock("POST", "/api/files/aws-presigned")
+            .match_body(Matcher::Json(json!({"fileName": "test2.jpg"})))
             .with_status(200)
             .with_header("content-type", "application/json")
             .with_body(
@@ -771,19 +1761,25 @@ mod tests {
                     max_concurrency: Some(2),
                     max_attempts: None,
                     polling_interval: None,
+                    ..Default::default()
                 },
             )
             .await;
 
         assert!(result.is_ok());
-        let batch_results = result.unwrap();
-        assert_eq!(batch_results.len(), 2);
-
-        // Check that results have request IDs but are in PROCESSING state
-        assert_eq!(batch_results[0].request_id, "test-request-id-1");
-        assert_eq!(batch_results[0].status, "PROCESSING");
-        assert_eq!(batch_results[1].request_id, "test-request-id-2");
-        assert_eq!(batch_results[1].status, "PROCESSING");
+        let report = result.unwrap();
+        assert_eq!(report.len(), 2);
+        assert_eq!(report.pending, 2);
+        assert_eq!(report.succeeded, 0);
+        assert_eq!(report.failed, 0);
+
+        // Check that entries have request IDs but no awaited result
+        assert_eq!(report.entries[0].path, file_path1);
+        assert_eq!(report.entries[0].request_id(), Some("test-request-id-1"));
+        assert!(report.entries[0].is_pending());
+        assert_eq!(report.entries[1].path, file_path2);
+        assert_eq!(report.entries[1].request_id(), Some("test-request-id-2"));
+        assert!(report.entries[1].is_pending());
 
         mock1.assert_async().await;
         mock2.assert_async().await;
@@ -792,17 +1788,136 @@ mod tests {
     }
 
     #[tokio::test]
-    async fn test_detect_file() {
+    async fn test_process_batch_reports_failures_in_order() {
+        let mut server = mockito::Server::new_async().await;
+
+        let dir = tempdir().unwrap();
+        let file_path1 = dir.path().join("test1.jpg");
+        let mut file1 = File::create(&file_path1).unwrap();
+        file1.write_all(b"test image data 1").unwrap();
+
+        // Second file does not exist and fails before upload
+        let missing_path = dir.path().join("missing.jpg");
+
+        let file_path3 = dir.path().join("test3.xyz");
+        let mut file3 = File::create(&file_path3).unwrap();
+        file3.write_all(b"unsupported data").unwrap();
+
+        let mock_presigned = server
+            .mock("POST", "/api/files/aws-presigned")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(
+                json!({
+                    "code": "success",
+                    "errno": 0,
+                    "requestId": "test-request-id-1",
+                    "mediaId": "test-media-id-1",
+                    "response": {
+                        "signedUrl": format!("{}/upload1", server.url())
+                    }
+                })
+                .to_string(),
+            )
+            .expect(1)
+            .create_async()
+            .await;
+
+        let mock_upload = server
+            .mock("PUT", "/upload1")
+            .with_status(200)
+            .create_async()
+            .await;
+
+        let mock_result = server
+            .mock("GET", "/api/media/users/test-request-id-1")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(
+                json!({
+                    "requestId": "test-request-id-1",
+                    "overallStatus": "MANIPULATED",
+                    "finalScore": 95,
+                    "models": []
+                })
+                .to_string(),
+            )
+            .create_async()
+            .await;
+
+        let client = Client::new(Config {
+            api_key: "test_api_key".to_string(),
+            base_url: Some(server.url()),
+            ..Default::default()
+        })
+        .unwrap();
+
+        let report = client
+            .process_batch(
+                vec![
+                    file_path1.to_str().unwrap(),
+                    missing_path.to_str().unwrap(),
+                    file_path3.to_str().unwrap(),
+                ],
+                BatchOptions {
+                    max_concurrency: Some(2),
+                    max_attempts: Some(3),
+                    polling_interval: Some(10),
+                    ..Default::default()
+                },
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(report.len(), 3);
+        assert_eq!(report.succeeded, 1);
+        assert_eq!(report.failed, 2);
+        assert_eq!(report.pending, 0);
+
+        assert_eq!(report.entries[0].path, file_path1);
+        match &report.entries[0].result {
+            Some(Ok(result)) => {
+                assert_eq!(result.request_id, "test-request-id-1");
+                assert_eq!(result.score, Some(0.95));
+            }
+            other => panic!("Expected a detection result, got: {:?}", other),
+        }
+
+        assert_eq!(report.entries[1].path, missing_path);
+        assert!(report.entries[1].upload.is_none());
+        assert!(matches!(
+            report.entries[1].result,
+            Some(Err(Error::InvalidFile(_)))
+        ));
+
+        assert_eq!(report.entries[2].path, file_path3);
+        assert!(matches!(
+            report.entries[2].result,
+            Some(Err(Error::InvalidFile(_)))
+        ));
+
+        let failed_paths: Vec<_> = report.failures().map(|(path, _)| path).collect();
+        assert_eq!(
+            failed_paths,
+            vec![missing_path.as_path(), file_path3.as_path()]
+        );
+
+        mock_presigned.assert_async().await;
+        mock_upload.assert_async().await;
+        mock_result.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_process_stream() {
         let mut server = mockito::Server::new_async().await;
 
-        // Create a temporary file for testing
         let dir = tempdir().unwrap();
         let file_path = dir.path().join("test.jpg");
         let mut file = File::create(&file_path).unwrap();
         file.write_all(b"test image data").unwrap();
+        let missing_path = dir.path().join("missing.jpg");
 
-        // Mock the presigned URL request
-        let mock1 = server
+        let mock_presigned = server
             .mock("POST", "/api/files/aws-presigned")
             .with_status(200)
             .with_header("content-type", "application/json")
@@ -810,8 +1925,8 @@ mod tests {
                 json!({
                     "code": "success",
                     "errno": 0,
-                    "requestId": "test-request-id",
-                    "mediaId": "test-media-id",
+                    "requestId": "test-stream-id",
+                    "mediaId": "test-stream-media",
                     "response": {
                         "signedUrl": format!("{}/upload", server.url())
                     }
@@ -821,23 +1936,21 @@ mod tests {
             .create_async()
             .await;
 
-        // Mock the upload endpoint
-        let mock2 = server
+        let mock_upload = server
             .mock("PUT", "/upload")
             .with_status(200)
             .create_async()
             .await;
 
-        // Mock the result endpoint
-        let mock3 = server
-            .mock("GET", "/api/media/users/test-request-id")
+        let mock_result = server
+            .mock("GET", "/api/media/users/test-stream-id")
             .with_status(200)
             .with_header("content-type", "application/json")
             .with_body(
                 json!({
-                    "requestId": "test-request-id",
-                    "overallStatus": "COMPLETED",
-                    "finalScore": 75,
+                    "requestId": "test-stream-id",
+                    "overallStatus": "AUTHENTIC",
+                    "finalScore": 5,
                     "models": []
                 })
                 .to_string(),
@@ -852,17 +1965,1150 @@ mod tests {
         })
         .unwrap();
 
-        // Test detect_file method
-        let result = client.detect_file(file_path.to_str().unwrap()).await;
+        let paths = futures::stream::iter(vec![file_path.clone(), missing_path.clone()]);
+        let outcomes: HashMap<_, _> = client
+            .process_stream(paths, BatchOptions::default())
+            .collect()
+            .await;
 
-        assert!(result.is_ok());
-        let analysis_result = result.unwrap();
-        assert_eq!(analysis_result.request_id, "test-request-id");
-        assert_eq!(analysis_result.status, "COMPLETED");
-        assert_eq!(analysis_result.score, Some(0.75));
+        assert_eq!(outcomes.len(), 2);
+        match &outcomes[&file_path] {
+            Ok(result) => {
+                assert_eq!(result.request_id, "test-stream-id");
+                assert_eq!(result.score, Some(0.05));
+            }
+            Err(err) => panic!("Unexpected error: {:?}", err),
+        }
+        assert!(matches!(
+            outcomes[&missing_path],
+            Err(Error::InvalidFile(_))
+        ));
+
+        mock_presigned.assert_async().await;
+        mock_upload.assert_async().await;
+        mock_result.assert_async().await;
+    }
 
-        mock1.assert_async().await;
-        mock2.assert_async().await;
-        mock3.assert_async().await;
+    #[tokio::test]
+    async fn test_process_stream_resumes_from_journal() {
+        let mut server = mockito::Server::new_async().await;
+
+        let dir = tempdir().unwrap();
+        let mut paths = Vec::new();
+        for name in ["done.jpg", "new.jpg"] {
+            let path = dir.path().join(name);
+            let mut file = File::create(&path).unwrap();
+            file.write_all(b"test image data").unwrap();
+            paths.push(path);
+        }
+
+        // Simulate a previous run that finished one file
+        let journal_path = dir.path().join("stream.jsonl");
+        let previous_run = json!({"path": paths[0], "state": "completed",
+            "upload": {"request_id": "done-id"},
+            "result": {"requestId": "done-id", "status": "AUTHENTIC", "score": 0.1, "models": []}});
+        std::fs::write(&journal_path, format!("{}\n", previous_run)).unwrap();
+
+        // Only the new file is uploaded, and only once across both runs
+        let mock_presigned = server
+            .mock("POST", "/api/files/aws-presigned")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .match_body(mockito::Matcher::Json(json!({"fileName": "new.jpg"})))
+            .with_body(
+                json!({
+                    "code": "success",
+                    "errno": 0,
+                    "requestId": "new-id",
+                    "mediaId": "new-media-id",
+                    "response": {
+                        "signedUrl": format!("{}/upload", server.url())
+                    }
+                })
+                .to_string(),
+            )
+            .expect(1)
+            .create_async()
+            .await;
+
+        let mock_upload = server
+            .mock("PUT", "/upload")
+            .with_status(200)
+            .expect(1)
+            .create_async()
+            .await;
+
+        let mock_result = server
+            .mock("GET", "/api/media/users/new-id")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(
+                json!({
+                    "requestId": "new-id",
+                    "overallStatus": "MANIPULATED",
+                    "finalScore": 90,
+                    "models": []
+                })
+                .to_string(),
+            )
+            .expect(1)
+            .create_async()
+            .await;
+
+        let client = Client::new(Config {
+            api_key: "test_api_key".to_string(),
+            base_url: Some(server.url()),
+            ..Default::default()
+        })
+        .unwrap();
+
+        for _ in 0..2 {
+            let outcomes: HashMap<_, _> = client
+                .process_stream(
+                    futures::stream::iter(paths.clone()),
+                    BatchOptions {
+                        max_attempts: Some(3),
+                        polling_interval: Some(10),
+                        journal_path: Some(journal_path.clone()),
+                        ..Default::default()
+                    },
+                )
+                .collect()
+                .await;
+
+            assert_eq!(outcomes[&paths[0]].as_ref().unwrap().request_id, "done-id");
+            assert_eq!(outcomes[&paths[1]].as_ref().unwrap().request_id, "new-id");
+        }
+
+        mock_presigned.assert_async().await;
+        mock_upload.assert_async().await;
+        mock_result.assert_async().await;
+
+        // A journal that cannot be opened fails every file
+        let outcomes: Vec<_> = client
+            .process_stream(
+                futures::stream::iter(paths.clone()),
+                BatchOptions {
+                    journal_path: Some(dir.path().join("missing").join("stream.jsonl")),
+                    ..Default::default()
+                },
+            )
+            .collect()
+            .await;
+        assert_eq!(outcomes.len(), 2);
+        assert!(outcomes.iter().all(|(_, result)| result.is_err()));
+    }
+
+    #[tokio::test]
+    async fn test_get_result_cancelled() {
+        let mut server = mockito::Server::new_async().await;
+        let mock = server
+            .mock("GET", "/api/media/users/test-cancel-request")
+            .expect(0)
+            .create_async()
+            .await;
+
+        let client = Client::new(Config {
+            api_key: "test_api_key".to_string(),
+            base_url: Some(server.url()),
+            ..Default::default()
+        })
+        .unwrap();
+
+        let cancel = CancellationToken::new();
+        cancel.cancel();
+        let result = client
+            .get_result(
+                "test-cancel-request",
+                Some(GetResultOptions {
+                    max_attempts: Some(5),
+                    polling_interval: Some(10),
+                    cancel: Some(cancel.clone()),
+                    ..Default::default()
+                }),
+            )
+            .await;
+
+        match result {
+            Err(Error::Cancelled { request_ids }) => {
+                assert_eq!(request_ids, ["test-cancel-request"])
+            }
+            other => panic!("Expected Cancelled error, got: {:?}", other),
+        }
+
+        // Nothing is uploaded once cancelled
+        let result = client
+            .detect_file_with_options(
+                "missing.jpg",
+                GetResultOptions {
+                    cancel: Some(cancel),
+                    ..Default::default()
+                },
+            )
+            .await;
+        assert!(matches!(result, Err(Error::Cancelled { request_ids }) if request_ids.is_empty()));
+        mock.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_process_batch_cancelled() {
+        let mut server = mockito::Server::new_async().await;
+
+        let dir = tempdir().unwrap();
+        let file_paths: Vec<_> = (1..=3)
+            .map(|index| {
+                let path = dir.path().join(format!("test{index}.jpg"));
+                std::fs::write(&path, format!("test image data {index}")).unwrap();
+                path
+            })
+            .collect();
+
+        let mock_presigned = server
+            .mock("POST", "/api/files/aws-presigned")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(
+                json!({
+                    "code": "success",
+                    "errno": 0,
+                    "requestId": "test-request-id",
+                    "mediaId": "test-media-id",
+                    "response": {
+                        "signedUrl": format!("{}/upload", server.url())
+                    }
+                })
+                .to_string(),
+            )
+            .expect_at_most(2)
+            .create_async()
+            .await;
+
+        server
+            .mock("PUT", "/upload")
+            .with_status(200)
+            .create_async()
+            .await;
+
+        // The shutdown arrives while the first result is being polled
+        let cancel = CancellationToken::new();
+        let trigger = cancel.clone();
+        server
+            .mock("GET", "/api/media/users/test-request-id")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body_from_request(move |_| {
+                trigger.cancel();
+                json!({
+                    "requestId": "test-request-id",
+                    "overallStatus": "ANALYZING",
+                    "models": []
+                })
+                .to_string()
+                .into_bytes()
+            })
+            .create_async()
+            .await;
+
+        let client = Client::new(Config {
+            api_key: "test_api_key".to_string(),
+            base_url: Some(server.url()),
+            ..Default::default()
+        })
+        .unwrap();
+
+        let (progress, progress_rx) = watch::channel(BatchProgress::default());
+        let report = client
+            .process_batch(
+                file_paths
+                    .iter()
+                    .map(|path| path.to_str().unwrap())
+                    .collect(),
+                BatchOptions {
+                    max_concurrency: Some(1),
+                    max_attempts: Some(100),
+                    polling_interval: Some(10),
+                    cancel: Some(cancel),
+                    progress: Some(progress),
+                    ..Default::default()
+                },
+            )
+            .await
+            .unwrap();
+
+        // The final progress counts files like the report
+        let progress = *progress_rx.borrow();
+        assert_eq!(progress.files_done, report.succeeded);
+        assert_eq!(progress.files_failed, report.failed);
+        assert_eq!(progress.files_pending, report.pending);
+        assert_eq!(progress.files_cancelled, report.cancelled);
+        assert_eq!(progress.files_in_flight, 0);
+
+        // Uploaded files stay pending with their request IDs, the rest were
+        // never uploaded
+        assert_eq!(report.failed, 0);
+        assert_eq!(report.succeeded, 0);
+        assert_eq!(report.pending + report.cancelled, 3);
+        assert!(report.entries[0].is_pending());
+        assert_eq!(report.entries[0].request_id(), Some("test-request-id"));
+        assert!(report.entries[2].upload.is_none());
+        assert!(matches!(
+            report.entries[2].result,
+            Some(Err(Error::Cancelled { .. }))
+        ));
+        assert_eq!(report.pending_request_ids().count(), report.pending);
+        assert_eq!(report.failures().count(), 0);
+        mock_presigned.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_process_batch_reports_progress() {
+        let mut server = mockito::Server::new_async().await;
+
+        let dir = tempdir().unwrap();
+        let file_path = dir.path().join("large.jpg");
+        std::fs::write(&file_path, vec![0u8; 200_000]).unwrap();
+        let missing_path = dir.path().join("missing.jpg");
+
+        let _m1 = server
+            .mock("POST", "/api/files/aws-presigned")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(
+                json!({
+                    "code": "success",
+                    "errno": 0,
+                    "requestId": "test-progress-id",
+                    "mediaId": "test-progress-media",
+                    "response": {
+                        "signedUrl": format!("{}/upload", server.url())
+                    }
+                })
+                .to_string(),
+            )
+            .create_async()
+            .await;
+
+        let _m2 = server
+            .mock("PUT", "/upload")
+            .with_status(200)
+            .create_async()
+            .await;
+
+        let client = Client::new(Config {
+            api_key: "test_api_key".to_string(),
+            base_url: Some(server.url()),
+            ..Default::default()
+        })
+        .unwrap();
+
+        let (progress, progress_rx) = watch::channel(BatchProgress::default());
+        let report = client
+            .process_batch(
+                vec![file_path.to_str().unwrap(), missing_path.to_str().unwrap()],
+                BatchOptions {
+                    progress: Some(progress),
+                    ..Default::default()
+                },
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(report.pending, 1);
+        assert_eq!(report.failed, 1);
+        assert_eq!(
+            *progress_rx.borrow(),
+            BatchProgress {
+                total_files: Some(2),
+                files_done: 0,
+                files_failed: 1,
+                files_pending: 1,
+                files_cancelled: 0,
+                files_in_flight: 0,
+                bytes_sent: 200_000,
+                total_bytes: 200_000,
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn test_process_batch_resumes_from_journal() {
+        let mut server = mockito::Server::new_async().await;
+
+        let dir = tempdir().unwrap();
+        let mut paths = Vec::new();
+        for name in ["done.jpg", "uploaded.jpg", "new.jpg"] {
+            let path = dir.path().join(name);
+            let mut file = File::create(&path).unwrap();
+            file.write_all(b"test image data").unwrap();
+            paths.push(path);
+        }
+
+        // Simulate a previous run that finished one file and uploaded another
+        let journal_path = dir.path().join("batch.jsonl");
+        let previous_run = [
+            json!({"path": paths[0], "state": "queued"}),
+            json!({"path": paths[1], "state": "queued"}),
+            json!({"path": paths[2], "state": "queued"}),
+            json!({"path": paths[0], "state": "completed",
+                "upload": {"request_id": "done-id"},
+                "result": {"requestId": "done-id", "status": "AUTHENTIC", "score": 0.1, "models": []}}),
+            json!({"path": paths[1], "state": "uploaded", "upload": {"request_id": "uploaded-id"}}),
+        ];
+        let journal_contents: String = previous_run
+            .iter()
+            .map(|record| format!("{}\n", record))
+            .collect();
+        std::fs::write(&journal_path, journal_contents).unwrap();
+
+        // Only the new file is uploaded
+        let mock_presigned = server
+            .mock("POST", "/api/files/aws-presigned")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .match_body(mockito::Matcher::Json(json!({"fileName": "new.jpg"})))
+            .with_body(
+                json!({
+                    "code": "success",
+                    "errno": 0,
+                    "requestId": "new-id",
+                    "mediaId": "new-media-id",
+                    "response": {
+                        "signedUrl": format!("{}/upload", server.url())
+                    }
+                })
+                .to_string(),
+            )
+            .expect(1)
+            .create_async()
+            .await;
+
+        let mock_upload = server
+            .mock("PUT", "/upload")
+            .with_status(200)
+            .expect(1)
+            .create_async()
+            .await;
+
+        let mut result_mocks = Vec::new();
+        for request_id in ["uploaded-id", "new-id"] {
+            let mock = server
+                .mock("GET", format!("/api/media/users/{}", request_id).as_str())
+                .with_status(200)
+                .with_header("content-type", "application/json")
+                .with_body(
+                    json!({
+                        "requestId": request_id,
+                        "overallStatus": "MANIPULATED",
+                        "finalScore": 90,
+                        "models": []
+                    })
+                    .to_string(),
+                )
+                .expect(1)
+                .create_async()
+                .await;
+            result_mocks.push(mock);
+        }
+
+        let client = Client::new(Config {
+            api_key: "test_api_key".to_string(),
+            base_url: Some(server.url()),
+            ..Default::default()
+        })
+        .unwrap();
+
+        let report = client
+            .process_batch(
+                paths.iter().map(|path| path.to_str().unwrap()).collect(),
+                BatchOptions {
+                    max_attempts: Some(3),
+                    polling_interval: Some(10),
+                    journal_path: Some(journal_path.clone()),
+                    ..Default::default()
+                },
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(report.succeeded, 3);
+        let request_ids: Vec<_> = report
+            .entries
+            .iter()
+            .map(|entry| entry.request_id().unwrap())
+            .collect();
+        assert_eq!(request_ids, vec!["done-id", "uploaded-id", "new-id"]);
+
+        mock_presigned.assert_async().await;
+        mock_upload.assert_async().await;
+        for mock in result_mocks {
+            mock.assert_async().await;
+        }
+
+        // A second run finds every file completed and makes no requests
+        let report = client
+            .process_batch(
+                paths.iter().map(|path| path.to_str().unwrap()).collect(),
+                BatchOptions {
+                    max_attempts: Some(3),
+                    polling_interval: Some(10),
+                    journal_path: Some(journal_path),
+                    ..Default::default()
+                },
+            )
+            .await
+            .unwrap();
+        assert_eq!(report.succeeded, 3);
+        mock_presigned.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_process_directory() {
+        let mut server = mockito::Server::new_async().await;
+
+        let dir = tempdir().unwrap();
+        let mut file = File::create(dir.path().join("image.jpg")).unwrap();
+        file.write_all(b"test image data").unwrap();
+        let mut file = File::create(dir.path().join("notes.xyz")).unwrap();
+        file.write_all(b"unsupported data").unwrap();
+
+        let mock_presigned = server
+            .mock("POST", "/api/files/aws-presigned")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(
+                json!({
+                    "code": "success",
+                    "errno": 0,
+                    "requestId": "test-dir-id",
+                    "mediaId": "test-dir-media",
+                    "response": {
+                        "signedUrl": format!("{}/upload", server.url())
+                    }
+                })
+                .to_string(),
+            )
+            .expect(1)
+            .create_async()
+            .await;
+
+        let mock_upload = server
+            .mock("PUT", "/upload")
+            .with_status(200)
+            .create_async()
+            .await;
+
+        let client = Client::new(Config {
+            api_key: "test_api_key".to_string(),
+            base_url: Some(server.url()),
+            ..Default::default()
+        })
+        .unwrap();
+
+        let report = client
+            .process_directory(dir.path(), DirectoryOptions::default())
+            .await
+            .unwrap();
+
+        assert_eq!(report.batch.len(), 1);
+        assert_eq!(report.batch.entries[0].path, dir.path().join("image.jpg"));
+        assert_eq!(report.batch.entries[0].request_id(), Some("test-dir-id"));
+        assert_eq!(report.skipped.len(), 1);
+        assert_eq!(report.skipped[0].path, dir.path().join("notes.xyz"));
+        assert_eq!(report.skipped[0].reason, SkipReason::UnsupportedType);
+
+        mock_presigned.assert_async().await;
+        mock_upload.assert_async().await;
+    }
+
+    /// Requests being served, the most seen at the same time, and the number
+    /// served so far
+    #[derive(Default)]
+    struct InFlight {
+        current: AtomicUsize,
+        max: AtomicUsize,
+        served: AtomicUsize,
+    }
+
+    impl InFlight {
+        async fn hold(&self, delay: Duration) {
+            let current = self.current.fetch_add(1, Ordering::SeqCst) + 1;
+            self.max.fetch_max(current, Ordering::SeqCst);
+            sleep(delay).await;
+            self.current.fetch_sub(1, Ordering::SeqCst);
+            self.served.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    /// Serve the upload and result endpoints, holding each presigned PUT and
+    /// each result poll for a while so overlapping requests can be counted.
+    /// Unlike mockito, requests are served concurrently.
+    async fn spawn_batch_server(
+        uploads: Arc<InFlight>,
+        polls: Arc<InFlight>,
+        upload_delay: Duration,
+        poll_delay: Duration,
+    ) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+        let url = base_url.clone();
+
+        tokio::spawn(async move {
+            while let Ok((socket, _)) = listener.accept().await {
+                let (uploads, polls, url) = (uploads.clone(), polls.clone(), url.clone());
+                tokio::spawn(async move {
+                    let mut reader = BufReader::new(socket);
+                    loop {
+                        let mut request_line = String::new();
+                        if reader.read_line(&mut request_line).await.unwrap_or(0) == 0 {
+                            return;
+                        }
+                        let mut content_length = 0;
+                        loop {
+                            let mut header = String::new();
+                            reader.read_line(&mut header).await.unwrap();
+                            if header.trim().is_empty() {
+                                break;
+                            }
+                            if let Some((name, value)) = header.split_once(':') {
+                                if name.eq_ignore_ascii_case("content-length") {
+                                    content_length = value.trim().parse().unwrap();
+                                }
+                            }
+                        }
+                        let mut body = vec![0; content_length];
+                        reader.read_exact(&mut body).await.unwrap();
+
+                        let mut parts = request_line.split_whitespace();
+                        let method = parts.next().unwrap().to_string();
+                        let path = parts.next().unwrap().to_string();
+                        let response = match method.as_str() {
+                            "POST" => {
+                                let body: serde_json::Value =
+                                    serde_json::from_slice(&body).unwrap();
+                                let request_id = body["fileName"].as_str().unwrap();
+                                json!({
+                                    "code": "success",
+                                    "errno": 0,
+                                    "requestId": request_id,
+                                    "mediaId": request_id,
+                                    "response": {"signedUrl": format!("{}/upload/{}", url, request_id)}
+                                })
+                                .to_string()
+                            }
+                            "PUT" => {
+                                uploads.hold(upload_delay).await;
+                                String::new()
+                            }
+                            _ => {
+                                polls.hold(poll_delay).await;
+                                let request_id = path.rsplit('/').next().unwrap();
+                                json!({
+                                    "requestId": request_id,
+                                    "overallStatus": "AUTHENTIC",
+                                    "models": []
+                                })
+                                .to_string()
+                            }
+                        };
+
+                        let head = format!(
+                            "HTTP/1.1 200 OK\r\ncontent-type: application/json\r\ncontent-length: {}\r\n\r\n",
+                            response.len()
+                        );
+                        let socket = reader.get_mut();
+                        if socket.write_all(head.as_bytes()).await.is_err()
+                            || socket.write_all(response.as_bytes()).await.is_err()
+                        {
+                            return;
+                        }
+                    }
+                });
+            }
+        });
+
+        base_url
+    }
+
+    #[tokio::test]
+    async fn test_process_batch_respects_concurrency_limits() {
+        let uploads = Arc::new(InFlight::default());
+        let polls = Arc::new(InFlight::default());
+        // Polls outlast uploads, so they pile up unless the limit holds them
+        let base_url = spawn_batch_server(
+            uploads.clone(),
+            polls.clone(),
+            Duration::from_millis(20),
+            Duration::from_millis(200),
+        )
+        .await;
+
+        let dir = tempdir().unwrap();
+        let paths: Vec<_> = (0..10)
+            .map(|index| {
+                let path = dir.path().join(format!("file{}.jpg", index));
+                File::create(&path)
+                    .unwrap()
+                    .write_all(b"test image data")
+                    .unwrap();
+                path
+            })
+            .collect();
+
+        let client = Client::new(Config {
+            api_key: "test_api_key".to_string(),
+            base_url: Some(base_url),
+            ..Default::default()
+        })
+        .unwrap();
+
+        let report = client
+            .process_batch(
+                paths.iter().map(|path| path.to_str().unwrap()).collect(),
+                BatchOptions {
+                    max_concurrency: Some(2),
+                    max_polling_concurrency: Some(3),
+                    max_attempts: Some(3),
+                    polling_interval: Some(10),
+                    ..Default::default()
+                },
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(report.succeeded, 10);
+        let max_uploads = uploads.max.load(Ordering::SeqCst);
+        let max_polls = polls.max.load(Ordering::SeqCst);
+        assert_eq!(max_uploads, 2, "{} uploads in flight", max_uploads);
+        assert_eq!(max_polls, 3, "{} polls in flight", max_polls);
+    }
+
+    #[tokio::test]
+    async fn test_process_batch_uploads_while_polls_queue() {
+        let uploads = Arc::new(InFlight::default());
+        let polls = Arc::new(InFlight::default());
+        let base_url = spawn_batch_server(
+            uploads.clone(),
+            polls.clone(),
+            Duration::from_millis(20),
+            Duration::from_millis(150),
+        )
+        .await;
+
+        let dir = tempdir().unwrap();
+        let paths: Vec<_> = (0..10)
+            .map(|index| {
+                let path = dir.path().join(format!("file{}.jpg", index));
+                File::create(&path)
+                    .unwrap()
+                    .write_all(b"test image data")
+                    .unwrap();
+                path
+            })
+            .collect();
+
+        let client = Client::new(Config {
+            api_key: "test_api_key".to_string(),
+            base_url: Some(base_url),
+            ..Default::default()
+        })
+        .unwrap();
+
+        // A single slow poll at a time must not stall the remaining uploads
+        let (report, uploaded_early) = tokio::join!(
+            client.process_batch(
+                paths.iter().map(|path| path.to_str().unwrap()).collect(),
+                BatchOptions {
+                    max_concurrency: Some(5),
+                    max_polling_concurrency: Some(1),
+                    max_attempts: Some(3),
+                    polling_interval: Some(10),
+                    ..Default::default()
+                },
+            ),
+            async {
+                sleep(Duration::from_millis(250)).await;
+                uploads.served.load(Ordering::SeqCst)
+            }
+        );
+
+        assert_eq!(report.unwrap().succeeded, 10);
+        assert_eq!(uploaded_early, 10);
+        assert_eq!(polls.max.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn test_batch_limits() {
+        let limits = BatchLimits::new(&BatchOptions::default());
+        assert_eq!(limits.uploads.available_permits(), 5);
+        assert_eq!(limits.polls.available_permits(), 5);
+        assert_eq!(limits.max_uploads, 5);
+
+        let limits = BatchLimits::new(&BatchOptions {
+            max_concurrency: Some(3),
+            max_polling_concurrency: Some(20),
+            ..Default::default()
+        });
+        assert_eq!(limits.uploads.available_permits(), 3);
+        assert_eq!(limits.polls.available_permits(), 20);
+        assert_eq!(limits.max_uploads, 3);
+
+        // A zero limit would never make progress, so it is raised to one
+        let limits = BatchLimits::new(&BatchOptions {
+            max_concurrency: Some(0),
+            max_polling_concurrency: Some(0),
+            ..Default::default()
+        });
+        assert_eq!(limits.uploads.available_permits(), 1);
+        assert_eq!(limits.polls.available_permits(), 1);
+    }
+
+    #[test]
+    fn test_batch_get_options() {
+        assert!(batch_get_options(&BatchOptions::default()).is_none());
+
+        let get_options = batch_get_options(&BatchOptions {
+            max_attempts: Some(10),
+            polling_interval: Some(500),
+            ..Default::default()
+        })
+        .unwrap();
+        assert_eq!(get_options.max_attempts, Some(10));
+        assert_eq!(get_options.polling_interval, Some(500));
+
+        let get_options = batch_get_options(&BatchOptions {
+            poll_strategy: Some(Arc::new(ExponentialBackoff::default())),
+            ..Default::default()
+        })
+        .unwrap();
+        assert!(get_options.poll_strategy.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_detect_file() {
+        let mut server = mockito::Server::new_async().await;
+
+        // Create a temporary file for testing
+        let dir = tempdir().unwrap();
+        let file_path = dir.path().join("test.jpg");
+        let mut file = File::create(&file_path).unwrap();
+        file.write_all(b"test image data").unwrap();
+
+        // Mock the presigned URL request
+        let mock1 = server
+            .mock("POST", "/api/files/aws-presigned")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(
+                json!({
+                    "code": "success",
+                    "errno": 0,
+                    "requestId": "test-request-id",
+                    "mediaId": "test-media-id",
+                    "response": {
+                        "signedUrl": format!("{}/upload", server.url())
+                    }
+                })
+                .to_string(),
+            )
+            .create_async()
+            .await;
+
+        // Mock the upload endpoint
+        let mock2 = server
+            .mock("PUT", "/upload")
+            .with_status(200)
+            .create_async()
+            .await;
+
+        // Mock the result endpoint
+        let mock3 = server
+            .mock("GET", "/api/media/users/test-request-id")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(
+                json!({
+                    "requestId": "test-request-id",
+                    "overallStatus": "COMPLETED",
+                    "finalScore": 75,
+                    "models": []
+                })
+                .to_string(),
+            )
+            .create_async()
+            .await;
+
+        let client = Client::new(Config {
+            api_key: "test_api_key".to_string(),
+            base_url: Some(server.url()),
+            ..Default::default()
+        })
+        .unwrap();
+
+        // Test detect_file method
+        let result = client.detect_file(file_path.to_str().unwrap()).await;
+
+        assert!(result.is_ok());
+        let analysis_result = result.unwrap();
+        assert_eq!(analysis_result.request_id, "test-request-id");
+        assert_eq!(analysis_result.status.as_str(), "COMPLETED");
+        assert_eq!(analysis_result.score, Some(0.75));
+
+        mock1.assert_async().await;
+        mock2.assert_async().await;
+        mock3.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_detect_file_reuses_cached_result() {
+        let mut server = mockito::Server::new_async().await;
+
+        // Identical content under different names is only uploaded once
+        let dir = tempdir().unwrap();
+        let original = dir.path().join("original.jpg");
+        let copy = dir.path().join("copy.jpg");
+        std::fs::write(&original, "test image data").unwrap();
+        std::fs::write(&copy, "test image data").unwrap();
+
+        let mock_presigned = server
+            .mock("POST", "/api/files/aws-presigned")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(
+                json!({
+                    "code": "success",
+                    "errno": 0,
+                    "requestId": "test-request-id",
+                    "mediaId": "test-media-id",
+                    "response": {
+                        "signedUrl": format!("{}/upload", server.url())
+                    }
+                })
+                .to_string(),
+            )
+            .expect(2)
+            .create_async()
+            .await;
+        let mock_put = server
+            .mock("PUT", "/upload")
+            .with_status(200)
+            .expect(2)
+            .create_async()
+            .await;
+        let mock_result = server
+            .mock("GET", "/api/media/users/test-request-id")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(
+                json!({
+                    "requestId": "test-request-id",
+                    "overallStatus": "AUTHENTIC",
+                    "finalScore": 10,
+                    "models": []
+                })
+                .to_string(),
+            )
+            .expect(2)
+            .create_async()
+            .await;
+
+        let client = Client::new(Config {
+            api_key: "test_api_key".to_string(),
+            base_url: Some(server.url()),
+            dedupe: Some(DedupeCache {
+                store: Arc::new(MemoryDedupeStore::new()),
+                ttl: None,
+            }),
+            ..Default::default()
+        })
+        .unwrap();
+
+        let result = client
+            .detect_file(original.to_str().unwrap())
+            .await
+            .unwrap();
+        assert_eq!(result.score, Some(0.1));
+
+        let cached = client
+            .upload_cached(
+                UploadOptions {
+                    file_path: copy.to_str().unwrap().to_string(),
+                },
+                false,
+            )
+            .await
+            .unwrap();
+        assert!(cached.cached);
+        assert_eq!(cached.upload.request_id, "test-request-id");
+        assert_eq!(cached.result.unwrap().score, Some(0.1));
+
+        let result = client.detect_file(copy.to_str().unwrap()).await.unwrap();
+        assert_eq!(result.score, Some(0.1));
+
+        // A forced refresh uploads and polls again
+        let result = client
+            .detect_file_cached(copy.to_str().unwrap(), true)
+            .await
+            .unwrap();
+        assert_eq!(result.request_id, "test-request-id");
+
+        mock_presigned.assert_async().await;
+        mock_put.assert_async().await;
+        mock_result.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_detect_file_does_not_reuse_failed_result() {
+        let mut server = mockito::Server::new_async().await;
+
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("image.jpg");
+        std::fs::write(&path, "test image data").unwrap();
+        let path = path.to_str().unwrap();
+
+        let mock_presigned = server
+            .mock("POST", "/api/files/aws-presigned")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(
+                json!({
+                    "code": "success",
+                    "errno": 0,
+                    "requestId": "test-request-id",
+                    "mediaId": "test-media-id",
+                    "response": {
+                        "signedUrl": format!("{}/upload", server.url())
+                    }
+                })
+                .to_string(),
+            )
+            .expect(3)
+            .create_async()
+            .await;
+        let mock_put = server
+            .mock("PUT", "/upload")
+            .with_status(200)
+            .expect(3)
+            .create_async()
+            .await;
+        let mock_result = server
+            .mock("GET", "/api/media/users/test-request-id")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(
+                json!({
+                    "requestId": "test-request-id",
+                    "overallStatus": "ERROR",
+                    "models": []
+                })
+                .to_string(),
+            )
+            .expect(2)
+            .create_async()
+            .await;
+
+        let client = Client::new(Config {
+            api_key: "test_api_key".to_string(),
+            base_url: Some(server.url()),
+            dedupe: Some(DedupeCache {
+                store: Arc::new(MemoryDedupeStore::new()),
+                ttl: None,
+            }),
+            ..Default::default()
+        })
+        .unwrap();
+
+        // Each detection uploads and polls again instead of reusing the error
+        for _ in 0..2 {
+            let result = client.detect_file(path).await.unwrap();
+            assert_eq!(result.status, AnalysisStatus::Error);
+        }
+        let cached = client
+            .upload_cached(
+                UploadOptions {
+                    file_path: path.to_string(),
+                },
+                false,
+            )
+            .await
+            .unwrap();
+        assert!(!cached.cached);
+        assert!(cached.result.is_none());
+
+        mock_presigned.assert_async().await;
+        mock_put.assert_async().await;
+        mock_result.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_process_batch_uploads_duplicates_once() {
+        let mut server = mockito::Server::new_async().await;
+
+        let dir = tempdir().unwrap();
+        let original = dir.path().join("original.jpg");
+        let copy = dir.path().join("copy.jpg");
+        std::fs::write(&original, "test image data").unwrap();
+        std::fs::write(&copy, "test image data").unwrap();
+
+        let mock_presigned = server
+            .mock("POST", "/api/files/aws-presigned")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(
+                json!({
+                    "code": "success",
+                    "errno": 0,
+                    "requestId": "test-request-id",
+                    "mediaId": "test-media-id",
+                    "response": {
+                        "signedUrl": format!("{}/upload", server.url())
+                    }
+                })
+                .to_string(),
+            )
+            .expect(1)
+            .create_async()
+            .await;
+        let mock_put = server
+            .mock("PUT", "/upload")
+            .with_status(200)
+            .expect(1)
+            .create_async()
+            .await;
+
+        let client = Client::new(Config {
+            api_key: "test_api_key".to_string(),
+            base_url: Some(server.url()),
+            dedupe: Some(DedupeCache {
+                store: Arc::new(MemoryDedupeStore::new()),
+                ttl: None,
+            }),
+            ..Default::default()
+        })
+        .unwrap();
+
+        // Both files are uploaded concurrently, yet only one is sent
+        let report = client
+            .process_batch(
+                vec![original.to_str().unwrap(), copy.to_str().unwrap()],
+                BatchOptions {
+                    max_concurrency: Some(2),
+                    ..Default::default()
+                },
+            )
+            .await
+            .unwrap();
+        assert_eq!(report.pending, 2);
+        assert_eq!(report.entries[0].request_id(), Some("test-request-id"));
+        assert_eq!(report.entries[1].request_id(), Some("test-request-id"));
+
+        // In-memory content goes through the same cache
+        let upload = client
+            .upload_bytes("photo.jpg", bytes::Bytes::from_static(b"test image data"))
+            .await
+            .unwrap();
+        assert_eq!(upload.request_id, "test-request-id");
+
+        mock_presigned.assert_async().await;
+        mock_put.assert_async().await;
     }
 }
@@ -1,8 +1,155 @@
+use crate::dedupe::DedupeCache;
 use crate::error::{Error, Result};
+use crate::normalize::ImageNormalization;
+use crate::utils::random_fraction;
+use std::time::Duration;
 
 /// Default API base URL
 pub const DEFAULT_BASE_URL: &str = "https://api.prd.realitydefender.xyz";
 
+/// Kinds of transport errors that can be retried
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RetryableError {
+    /// The request timed out
+    Timeout,
+
+    /// The connection could not be established
+    Connect,
+
+    /// The request failed while being sent, e.g. the connection was reset
+    Request,
+}
+
+/// Policy for retrying failed requests with exponential backoff.
+///
+/// Only idempotent calls are retried: result lookups and the upload to the
+/// presigned URL. Requests that create a new analysis are never retried.
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    /// Maximum number of retries after the first attempt
+    pub max_retries: u32,
+
+    /// Delay before the first retry, doubled for each following retry
+    pub base_delay: Duration,
+
+    /// Upper bound for the delay between retries
+    pub max_delay: Duration,
+
+    /// Whether to randomize delays so concurrent clients do not retry in lockstep
+    pub jitter: bool,
+
+    /// HTTP status codes that are retried. A 429 waits for the delay given in
+    /// its `Retry-After` header when there is one, and is returned as
+    /// `Error::RateLimited` when that delay exceeds `max_delay`.
+    pub retry_statuses: Vec<u16>,
+
+    /// Transport errors that are retried
+    pub retry_errors: Vec<RetryableError>,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: 3,
+            base_delay: Duration::from_millis(500),
+            max_delay: Duration::from_secs(30),
+            jitter: true,
+            retry_statuses: vec![429, 500, 502, 503, 504],
+            retry_errors: vec![
+                RetryableError::Timeout,
+                RetryableError::Connect,
+                RetryableError::Request,
+            ],
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Delay before the given retry, starting at 0 for the first retry
+    pub fn delay(&self, retry: u32) -> Duration {
+        let backoff = self
+            .base_delay
+            .saturating_mul(2u32.saturating_pow(retry))
+            .min(self.max_delay);
+
+        if self.jitter {
+            // Keep at least half of the backoff and randomize the rest
+            backoff / 2 + backoff.mul_f64(random_fraction() / 2.0)
+        } else {
+            backoff
+        }
+    }
+
+    /// Whether a response with the given status should be retried
+    pub fn retries_status(&self, status: u16) -> bool {
+        self.retry_statuses.contains(&status)
+    }
+
+    /// Whether a transport error should be retried
+    pub fn retries_error(&self, error: &reqwest::Error) -> bool {
+        self.retry_errors.iter().any(|kind| match kind {
+            RetryableError::Timeout => error.is_timeout(),
+            RetryableError::Connect => error.is_connect(),
+            RetryableError::Request => error.is_request(),
+        })
+    }
+}
+
+/// Client-side limit on the request rate, shared by every call made through
+/// a client, including result polling
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RateLimit {
+    /// Sustained number of requests allowed per second
+    pub requests_per_second: f64,
+
+    /// Number of requests that can be sent at once after an idle period
+    pub burst: u32,
+}
+
+/// How the content type of an upload is determined
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ContentTypePolicy {
+    /// Use the file extension and ignore the content
+    #[default]
+    TrustExtension,
+
+    /// Use the type recognised from the file signature, falling back to the
+    /// extension when the content is not recognised
+    TrustContent,
+
+    /// Reject uploads whose content is not recognised as the type of their
+    /// extension
+    RejectMismatch,
+}
+
+/// Settings for uploading large files in parts. Each part is retried on its
+/// own, so a failure near the end of an upload only resends the failed part.
+///
+/// Experimental: the `fileSize` and `partSize` fields of the presigned URL
+/// request and the `multipart` details of its response are not part of the
+/// documented API and may change. Takes effect only with the
+/// `experimental-multipart` feature, and fails validation without it.
+///
+/// Parts are only resumed within one upload call. Nothing is saved, so an
+/// upload interrupted by a process restart starts over.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MultipartUpload {
+    /// Uploads of at least this many bytes are sent in parts
+    pub threshold: u64,
+
+    /// Preferred size of each part in bytes. The API may choose another size.
+    pub part_size: u64,
+}
+
+impl Default for MultipartUpload {
+    fn default() -> Self {
+        Self {
+            threshold: 64 * 1024 * 1024,
+            part_size: 16 * 1024 * 1024,
+        }
+    }
+}
+
 /// Configuration for the Reality Defender client
 #[derive(Debug, Clone, Default)]
 pub struct Config {
@@ -14,6 +161,34 @@ pub struct Config {
 
     /// Timeout in seconds for HTTP requests
     pub timeout_seconds: Option<u64>,
+
+    /// Retry policy for idempotent requests. Requests are not retried when unset.
+    pub retry_policy: Option<RetryPolicy>,
+
+    /// Client-side request rate limit. Requests are not throttled when unset.
+    pub rate_limit: Option<RateLimit>,
+
+    /// How the content type of uploads is determined
+    pub content_type_policy: ContentTypePolicy,
+
+    /// Multipart upload settings for large files. Every upload is sent with a
+    /// single request when unset. Experimental, and requires the
+    /// `experimental-multipart` feature.
+    pub multipart_upload: Option<MultipartUpload>,
+
+    /// Cache reusing the uploads of identical content. Every upload is sent
+    /// when unset.
+    pub dedupe: Option<DedupeCache>,
+
+    /// Whether the structure of uploads is checked locally before they are
+    /// sent, rejecting truncated media, media with broken headers and images
+    /// that do not decode without spending quota. Compressed audio and video
+    /// data is not decoded, so damage inside it is left to the server.
+    pub check_upload_structure: bool,
+
+    /// Conversion of images that are too large or in formats the API does
+    /// not accept into PNG or JPEG. Such images are rejected when unset.
+    pub image_normalization: Option<ImageNormalization>,
 }
 
 impl Config {
@@ -29,6 +204,40 @@ impl Config {
             }
         }
 
+        if let Some(policy) = &self.retry_policy {
+            if policy.base_delay > policy.max_delay {
+                return Err(Error::InvalidConfig(
+                    "Retry base delay cannot exceed the max delay".to_string(),
+                ));
+            }
+        }
+
+        if let Some(limit) = &self.rate_limit {
+            if !limit.requests_per_second.is_finite() || limit.requests_per_second <= 0.0 {
+                return Err(Error::InvalidConfig(
+                    "Rate limit must allow a positive number of requests per second".to_string(),
+                ));
+            }
+            if limit.burst == 0 {
+                return Err(Error::InvalidConfig(
+                    "Rate limit burst must be at least 1".to_string(),
+                ));
+            }
+        }
+
+        if let Some(multipart) = &self.multipart_upload {
+            if cfg!(not(feature = "experimental-multipart")) {
+                return Err(Error::InvalidConfig(
+                    "Multipart uploads require the experimental-multipart feature".to_string(),
+                ));
+            }
+            if multipart.part_size == 0 {
+                return Err(Error::InvalidConfig(
+                    "Multipart part size must be at least 1 byte".to_string(),
+                ));
+            }
+        }
+
         Ok(())
     }
 
@@ -55,6 +264,16 @@ mod tests {
         assert_eq!(config.api_key, "".to_string());
         assert_eq!(config.base_url, None);
         assert_eq!(config.timeout_seconds, None);
+        assert!(config.retry_policy.is_none());
+        assert!(config.rate_limit.is_none());
+        assert!(config.multipart_upload.is_none());
+        assert!(config.dedupe.is_none());
+        assert!(!config.check_upload_structure);
+        assert!(config.image_normalization.is_none());
+        assert_eq!(
+            config.content_type_policy,
+            ContentTypePolicy::TrustExtension
+        );
     }
 
     #[test]
@@ -63,6 +282,7 @@ mod tests {
             api_key: "test_api_key".to_string(),
             base_url: Some("https://custom-api.example.com".to_string()),
             timeout_seconds: None,
+            ..Default::default()
         };
 
         assert_eq!(config.api_key, "test_api_key");
@@ -79,6 +299,7 @@ mod tests {
             api_key: "test_api_key".to_string(),
             base_url: None,
             timeout_seconds: Some(120),
+            ..Default::default()
         };
 
         assert_eq!(config.api_key, "test_api_key");
@@ -92,6 +313,7 @@ mod tests {
             api_key: "test_api_key".to_string(),
             base_url: Some("https://custom-api.example.com".to_string()),
             timeout_seconds: Some(120),
+            ..Default::default()
         };
 
         assert_eq!(config.api_key, "test_api_key");
@@ -108,6 +330,7 @@ mod tests {
             api_key: "test_api_key".to_string(),
             base_url: None,
             timeout_seconds: None,
+            ..Default::default()
         };
 
         let result = config.validate();
@@ -134,6 +357,7 @@ mod tests {
             api_key: "test_api_key".to_string(),
             base_url: Some("".to_string()),
             timeout_seconds: None,
+            ..Default::default()
         };
 
         let result = config.validate();
@@ -153,6 +377,7 @@ mod tests {
             api_key: "test_api_key".to_string(),
             base_url: None,
             timeout_seconds: None,
+            ..Default::default()
         };
 
         assert_eq!(config.get_base_url(), "https://api.prd.realitydefender.xyz");
@@ -164,6 +389,7 @@ mod tests {
             api_key: "test_api_key".to_string(),
             base_url: Some("https://custom-api.example.com".to_string()),
             timeout_seconds: None,
+            ..Default::default()
         };
 
         assert_eq!(config.get_base_url(), "https://custom-api.example.com");
@@ -175,6 +401,7 @@ mod tests {
             api_key: "test_api_key".to_string(),
             base_url: None,
             timeout_seconds: None,
+            ..Default::default()
         };
 
         assert_eq!(config.get_timeout_seconds(), 30); // Default is 30 seconds
@@ -186,8 +413,129 @@ mod tests {
             api_key: "test_api_key".to_string(),
             base_url: None,
             timeout_seconds: Some(120),
+            ..Default::default()
         };
 
         assert_eq!(config.get_timeout_seconds(), 120);
     }
+
+    #[test]
+    fn test_validate_retry_delays() {
+        let config = Config {
+            api_key: "test_api_key".to_string(),
+            retry_policy: Some(RetryPolicy {
+                base_delay: Duration::from_secs(10),
+                max_delay: Duration::from_secs(1),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+
+        match config.validate() {
+            Err(Error::InvalidConfig(msg)) => assert!(msg.contains("Retry base delay")),
+            other => panic!("Expected InvalidConfig error, got: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_validate_rate_limit() {
+        let config = |requests_per_second, burst| Config {
+            api_key: "test_api_key".to_string(),
+            rate_limit: Some(RateLimit {
+                requests_per_second,
+                burst,
+            }),
+            ..Default::default()
+        };
+
+        assert!(config(5.0, 10).validate().is_ok());
+        assert!(config(0.5, 1).validate().is_ok());
+
+        for invalid in [
+            config(0.0, 1),
+            config(-1.0, 1),
+            config(f64::NAN, 1),
+            config(1.0, 0),
+        ] {
+            match invalid.validate() {
+                Err(Error::InvalidConfig(msg)) => assert!(msg.contains("Rate limit")),
+                other => panic!("Expected InvalidConfig error, got: {:?}", other),
+            }
+        }
+    }
+
+    #[cfg(feature = "experimental-multipart")]
+    #[test]
+    fn test_validate_multipart_part_size() {
+        let config = Config {
+            api_key: "test_api_key".to_string(),
+            multipart_upload: Some(MultipartUpload {
+                part_size: 0,
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+
+        match config.validate() {
+            Err(Error::InvalidConfig(msg)) => assert!(msg.contains("part size")),
+            other => panic!("Expected InvalidConfig error, got: {:?}", other),
+        }
+    }
+
+    #[cfg(not(feature = "experimental-multipart"))]
+    #[test]
+    fn test_validate_multipart_without_feature() {
+        let config = Config {
+            api_key: "test_api_key".to_string(),
+            multipart_upload: Some(MultipartUpload::default()),
+            ..Default::default()
+        };
+
+        match config.validate() {
+            Err(Error::InvalidConfig(msg)) => {
+                assert!(msg.contains("experimental-multipart"), "{msg}")
+            }
+            other => panic!("Expected InvalidConfig error, got: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_retry_policy_delay_without_jitter() {
+        let policy = RetryPolicy {
+            base_delay: Duration::from_millis(100),
+            max_delay: Duration::from_millis(500),
+            jitter: false,
+            ..Default::default()
+        };
+
+        assert_eq!(policy.delay(0), Duration::from_millis(100));
+        assert_eq!(policy.delay(1), Duration::from_millis(200));
+        assert_eq!(policy.delay(2), Duration::from_millis(400));
+        assert_eq!(policy.delay(3), Duration::from_millis(500));
+        assert_eq!(policy.delay(40), Duration::from_millis(500));
+    }
+
+    #[test]
+    fn test_retry_policy_delay_with_jitter() {
+        let policy = RetryPolicy {
+            base_delay: Duration::from_millis(100),
+            max_delay: Duration::from_secs(1),
+            jitter: true,
+            ..Default::default()
+        };
+
+        for retry in 0..5 {
+            let backoff = Duration::from_millis(100 * 2u64.pow(retry)).min(policy.max_delay);
+            let delay = policy.delay(retry);
+            assert!(delay >= backoff / 2 && delay <= backoff, "{:?}", delay);
+        }
+    }
+
+    #[test]
+    fn test_retry_policy_statuses() {
+        let policy = RetryPolicy::default();
+        assert!(policy.retries_status(503));
+        assert!(!policy.retries_status(400));
+        assert!(!policy.retries_status(404));
+    }
 }
@@ -0,0 +1,266 @@
+use crate::error::{Error, Result};
+use crate::jsonl::JsonlLog;
+use crate::models::{DetectionResult, UploadResult};
+use crate::utils::lock;
+use ring::digest::{Context, SHA256};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt::{Debug, Write as _};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
+use tokio::io::AsyncReadExt;
+
+/// Size of the buffer used to hash files
+const HASH_BUFFER_SIZE: usize = 64 * 1024;
+
+/// Upload recorded for content with a given hash
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct DedupeEntry {
+    /// Upload of the content
+    pub upload: UploadResult,
+
+    /// Terminal result of the upload, once it is known. An entry whose
+    /// analysis ended without a verdict, e.g. with `ERROR`, is not reused.
+    pub result: Option<DetectionResult>,
+
+    /// When the upload was recorded
+    pub stored_at: SystemTime,
+}
+
+/// Store mapping the SHA-256 of uploaded content, as lowercase hex, to its
+/// upload. Implement it to keep the cache in a database such as SQLite.
+pub trait DedupeStore: Debug + Send + Sync {
+    /// Entry recorded for a hash, if any
+    fn get(&self, hash: &str) -> Result<Option<DedupeEntry>>;
+
+    /// Record the entry for a hash, replacing any previous one
+    fn put(&self, hash: &str, entry: &DedupeEntry) -> Result<()>;
+}
+
+/// Upload deduplication settings
+#[derive(Debug, Clone)]
+pub struct DedupeCache {
+    /// Store holding previous uploads
+    pub store: Arc<dyn DedupeStore>,
+
+    /// How long an upload can be reused. Entries never expire when unset.
+    pub ttl: Option<Duration>,
+}
+
+impl DedupeCache {
+    /// Entry recorded for a hash, unless it expired or its analysis ended
+    /// without a verdict, so the content is uploaded again
+    pub(crate) fn lookup(&self, hash: &str) -> Result<Option<DedupeEntry>> {
+        let entry = match self.store.get(hash)? {
+            Some(entry) => entry,
+            None => return Ok(None),
+        };
+
+        let expired = self
+            .ttl
+            .is_some_and(|ttl| entry.stored_at.elapsed().is_ok_and(|elapsed| elapsed > ttl));
+        let failed = entry
+            .result
+            .as_ref()
+            .is_some_and(|result| !result.status.is_verdict());
+        Ok((!expired && !failed).then_some(entry))
+    }
+}
+
+/// Dedupe store kept in memory for the lifetime of the process
+#[derive(Debug, Default)]
+pub struct MemoryDedupeStore {
+    entries: Mutex<HashMap<String, DedupeEntry>>,
+}
+
+impl MemoryDedupeStore {
+    /// Create an empty store
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl DedupeStore for MemoryDedupeStore {
+    fn get(&self, hash: &str) -> Result<Option<DedupeEntry>> {
+        Ok(lock(&self.entries, "dedupe store")?.get(hash).cloned())
+    }
+
+    fn put(&self, hash: &str, entry: &DedupeEntry) -> Result<()> {
+        lock(&self.entries, "dedupe store")?.insert(hash.to_string(), entry.clone());
+        Ok(())
+    }
+}
+
+/// A single line of a file-backed store
+#[derive(Debug, Deserialize, Serialize)]
+struct DedupeRecord {
+    hash: String,
+    #[serde(flatten)]
+    entry: DedupeEntry,
+}
+
+/// Dedupe store persisted to an append-only JSONL file. When the file is
+/// reopened, the last record for each hash wins.
+#[derive(Debug)]
+pub struct FileDedupeStore {
+    log: JsonlLog,
+    entries: Mutex<HashMap<String, DedupeEntry>>,
+}
+
+impl FileDedupeStore {
+    /// Open a store, loading the records left by previous runs
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        let (log, records) = JsonlLog::open::<DedupeRecord>(path.as_ref(), "dedupe store")?;
+        let entries = records
+            .into_iter()
+            .map(|record| (record.hash, record.entry))
+            .collect();
+        Ok(Self {
+            log,
+            entries: Mutex::new(entries),
+        })
+    }
+}
+
+impl DedupeStore for FileDedupeStore {
+    fn get(&self, hash: &str) -> Result<Option<DedupeEntry>> {
+        Ok(lock(&self.entries, "dedupe store")?.get(hash).cloned())
+    }
+
+    fn put(&self, hash: &str, entry: &DedupeEntry) -> Result<()> {
+        self.log.append(&[DedupeRecord {
+            hash: hash.to_string(),
+            entry: entry.clone(),
+        }])?;
+        lock(&self.entries, "dedupe store")?.insert(hash.to_string(), entry.clone());
+        Ok(())
+    }
+}
+
+/// SHA-256 of a file's content as lowercase hex
+pub(crate) async fn hash_file(path: &Path) -> Result<String> {
+    let mut file = match tokio::fs::File::open(path).await {
+        Ok(file) => file,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+            return Err(Error::InvalidFile(format!(
+                "File not found: {}",
+                path.display()
+            )))
+        }
+        Err(err) => return Err(err.into()),
+    };
+
+    let mut context = Context::new(&SHA256);
+    let mut buffer = vec![0; HASH_BUFFER_SIZE];
+    loop {
+        let read = file.read(&mut buffer).await?;
+        if read == 0 {
+            break;
+        }
+        context.update(&buffer[..read]);
+    }
+
+    Ok(to_hex(context.finish().as_ref()))
+}
+
+/// SHA-256 of in-memory content as lowercase hex
+pub(crate) fn hash_bytes(content: &[u8]) -> String {
+    to_hex(ring::digest::digest(&SHA256, content).as_ref())
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().fold(String::new(), |mut hex, byte| {
+        let _ = write!(hex, "{byte:02x}");
+        hex
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::AnalysisStatus;
+    use tempfile::tempdir;
+
+    fn entry(request_id: &str, stored_at: SystemTime) -> DedupeEntry {
+        DedupeEntry {
+            upload: UploadResult {
+                request_id: request_id.to_string(),
+                media_id: None,
+                result_url: None,
+                normalization: None,
+            },
+            result: None,
+            stored_at,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_hash_file() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("test.txt");
+        std::fs::write(&path, "abc").unwrap();
+
+        assert_eq!(
+            hash_file(&path).await.unwrap(),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+        assert_eq!(hash_bytes(b"abc"), hash_file(&path).await.unwrap());
+        assert!(matches!(
+            hash_file(&dir.path().join("missing.txt")).await,
+            Err(Error::InvalidFile(_))
+        ));
+    }
+
+    #[test]
+    fn test_file_store_roundtrip() {
+        let dir = tempdir().unwrap();
+        let store_path = dir.path().join("dedupe.jsonl");
+
+        let store = FileDedupeStore::open(&store_path).unwrap();
+        store
+            .put("a", &entry("first-id", SystemTime::now()))
+            .unwrap();
+        let mut completed = entry("second-id", SystemTime::now());
+        completed.result = Some(DetectionResult {
+            request_id: "second-id".to_string(),
+            status: AnalysisStatus::Authentic,
+            score: Some(0.1),
+            models: vec![],
+        });
+        store.put("a", &completed).unwrap();
+        drop(store);
+
+        // The last record for a hash wins
+        let store = FileDedupeStore::open(&store_path).unwrap();
+        let loaded = store.get("a").unwrap().unwrap();
+        assert_eq!(loaded.upload.request_id, "second-id");
+        assert_eq!(loaded.result.unwrap().score, Some(0.1));
+        assert!(store.get("b").unwrap().is_none());
+    }
+
+    #[test]
+    fn test_cache_ttl() {
+        let store = Arc::new(MemoryDedupeStore::new());
+        store
+            .put("fresh", &entry("fresh-id", SystemTime::now()))
+            .unwrap();
+        store
+            .put(
+                "stale",
+                &entry("stale-id", SystemTime::now() - Duration::from_secs(120)),
+            )
+            .unwrap();
+
+        let cache = DedupeCache {
+            store: store.clone(),
+            ttl: Some(Duration::from_secs(60)),
+        };
+        assert!(cache.lookup("fresh").unwrap().is_some());
+        assert!(cache.lookup("stale").unwrap().is_none());
+        assert!(cache.lookup("missing").unwrap().is_none());
+
+        let cache = DedupeCache { store, ttl: None };
+        assert!(cache.lookup("stale").unwrap().is_some());
+    }
+}
@@ -0,0 +1,312 @@
+use crate::error::{Error, Result};
+use crate::file::find_media_type;
+use crate::models::{DirectoryOptions, SkipReason, SkippedFile};
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use std::path::{Path, PathBuf};
+use walkdir::{DirEntry, WalkDir};
+
+/// Files selected from a directory tree, and the entries that were skipped
+#[derive(Debug, Default)]
+pub struct DirectoryFiles {
+    pub selected: Vec<PathBuf>,
+    pub skipped: Vec<SkippedFile>,
+}
+
+/// Walk a directory tree and select the files that pass the filters and the
+/// media type registry. Entries are visited in file name order.
+pub fn collect_files(root: &Path, options: &DirectoryOptions) -> Result<DirectoryFiles> {
+    if !root.is_dir() {
+        return Err(Error::InvalidRequest(format!(
+            "Not a directory: {}",
+            root.display()
+        )));
+    }
+
+    let include = build_glob_set(&options.include)?;
+    let exclude = build_glob_set(&options.exclude)?;
+
+    let mut walker = WalkDir::new(root)
+        .min_depth(1)
+        .follow_links(options.follow_symlinks)
+        .sort_by_file_name();
+    if let Some(max_depth) = options.max_depth {
+        walker = walker.max_depth(max_depth);
+    }
+
+    let mut files = DirectoryFiles::default();
+    let mut entries = walker.into_iter();
+
+    while let Some(entry) = entries.next() {
+        let entry = match entry {
+            Ok(entry) => entry,
+            Err(err) => {
+                files.skipped.push(SkippedFile {
+                    path: err.path().unwrap_or(root).to_path_buf(),
+                    reason: SkipReason::Unreadable(err.to_string()),
+                });
+                continue;
+            }
+        };
+
+        let reason = if entry.file_type().is_dir() {
+            let reason = dir_skip_reason(root, &entry, options, &exclude);
+            if reason.is_none() {
+                continue;
+            }
+            // Report a pruned directory once rather than every file inside it
+            entries.skip_current_dir();
+            reason
+        } else {
+            file_skip_reason(root, &entry, options, &include, &exclude)
+        };
+
+        match reason {
+            Some(reason) => files.skipped.push(SkippedFile {
+                path: entry.into_path(),
+                reason,
+            }),
+            None => files.selected.push(entry.into_path()),
+        }
+    }
+
+    Ok(files)
+}
+
+fn build_glob_set(patterns: &[String]) -> Result<GlobSet> {
+    let mut builder = GlobSetBuilder::new();
+    for pattern in patterns {
+        let glob = Glob::new(pattern).map_err(|err| {
+            Error::InvalidRequest(format!("Invalid glob pattern '{pattern}': {err}"))
+        })?;
+        builder.add(glob);
+    }
+
+    builder
+        .build()
+        .map_err(|err| Error::InvalidRequest(format!("Invalid glob patterns: {err}")))
+}
+
+fn is_hidden(path: &Path) -> bool {
+    path.file_name()
+        .and_then(|name| name.to_str())
+        .is_some_and(|name| name.starts_with('.'))
+}
+
+fn relative_path<'a>(root: &Path, path: &'a Path) -> &'a Path {
+    path.strip_prefix(root).unwrap_or(path)
+}
+
+fn dir_skip_reason(
+    root: &Path,
+    entry: &DirEntry,
+    options: &DirectoryOptions,
+    exclude: &GlobSet,
+) -> Option<SkipReason> {
+    if !options.include_hidden && is_hidden(entry.path()) {
+        return Some(SkipReason::Hidden);
+    }
+    if exclude.is_match(relative_path(root, entry.path())) {
+        return Some(SkipReason::Excluded);
+    }
+    None
+}
+
+fn file_skip_reason(
+    root: &Path,
+    entry: &DirEntry,
+    options: &DirectoryOptions,
+    include: &GlobSet,
+    exclude: &GlobSet,
+) -> Option<SkipReason> {
+    let relative = relative_path(root, entry.path());
+
+    if !options.include_hidden && is_hidden(entry.path()) {
+        return Some(SkipReason::Hidden);
+    }
+    // Without follow_links, walkdir reports links as entries of their own
+    if entry.path_is_symlink() && !options.follow_symlinks {
+        return Some(SkipReason::Symlink);
+    }
+    if exclude.is_match(relative) {
+        return Some(SkipReason::Excluded);
+    }
+    if !include.is_empty() && !include.is_match(relative) {
+        return Some(SkipReason::NotIncluded);
+    }
+
+    let media_type = match find_media_type(entry.path()) {
+        Some(media_type) => media_type,
+        None => return Some(SkipReason::UnsupportedType),
+    };
+
+    match entry.metadata() {
+        Ok(metadata) if metadata.len() > media_type.size_limit => Some(SkipReason::TooLarge {
+            size: metadata.len(),
+            limit: media_type.size_limit,
+        }),
+        Ok(_) => None,
+        Err(err) => Some(SkipReason::Unreadable(err.to_string())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::{tempdir, TempDir};
+
+    fn create_tree() -> TempDir {
+        let dir = tempdir().unwrap();
+        let root = dir.path();
+
+        fs::create_dir_all(root.join("sub/deep")).unwrap();
+        fs::create_dir_all(root.join(".git")).unwrap();
+        fs::create_dir_all(root.join("skip")).unwrap();
+
+        fs::write(root.join("a.jpg"), b"image").unwrap();
+        fs::write(root.join("b.xyz"), b"unknown").unwrap();
+        fs::write(root.join(".hidden.jpg"), b"image").unwrap();
+        fs::write(root.join(".git/c.jpg"), b"image").unwrap();
+        fs::write(root.join("sub/d.png"), b"image").unwrap();
+        fs::write(root.join("sub/deep/e.png"), b"image").unwrap();
+        fs::write(root.join("skip/f.jpg"), b"image").unwrap();
+        fs::write(root.join("big.txt"), vec![b'a'; 5242881]).unwrap();
+        std::os::unix::fs::symlink(root.join("a.jpg"), root.join("link.jpg")).unwrap();
+
+        dir
+    }
+
+    fn relative(root: &Path, paths: &[PathBuf]) -> Vec<String> {
+        paths
+            .iter()
+            .map(|path| relative_path(root, path).to_string_lossy().into_owned())
+            .collect()
+    }
+
+    fn skip_reason(files: &DirectoryFiles, root: &Path, path: &str) -> Option<SkipReason> {
+        files
+            .skipped
+            .iter()
+            .find(|skipped| skipped.path == root.join(path))
+            .map(|skipped| skipped.reason.clone())
+    }
+
+    #[test]
+    fn test_collect_files_defaults() {
+        let dir = create_tree();
+        let root = dir.path();
+
+        let files = collect_files(
+            root,
+            &DirectoryOptions {
+                exclude: vec!["skip".to_string()],
+                ..Default::default()
+            },
+        )
+        .unwrap();
+
+        assert_eq!(
+            relative(root, &files.selected),
+            vec!["a.jpg", "sub/d.png", "sub/deep/e.png"]
+        );
+        assert_eq!(
+            skip_reason(&files, root, "b.xyz"),
+            Some(SkipReason::UnsupportedType)
+        );
+        assert_eq!(
+            skip_reason(&files, root, ".hidden.jpg"),
+            Some(SkipReason::Hidden)
+        );
+        assert_eq!(skip_reason(&files, root, ".git"), Some(SkipReason::Hidden));
+        assert_eq!(skip_reason(&files, root, ".git/c.jpg"), None);
+        assert_eq!(
+            skip_reason(&files, root, "skip"),
+            Some(SkipReason::Excluded)
+        );
+        assert_eq!(
+            skip_reason(&files, root, "big.txt"),
+            Some(SkipReason::TooLarge {
+                size: 5242881,
+                limit: 5242880
+            })
+        );
+        assert_eq!(
+            skip_reason(&files, root, "link.jpg"),
+            Some(SkipReason::Symlink)
+        );
+    }
+
+    #[test]
+    fn test_collect_files_include_and_depth() {
+        let dir = create_tree();
+        let root = dir.path();
+
+        let files = collect_files(
+            root,
+            &DirectoryOptions {
+                include: vec!["**/*.png".to_string()],
+                max_depth: Some(2),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+
+        assert_eq!(relative(root, &files.selected), vec!["sub/d.png"]);
+        assert_eq!(
+            skip_reason(&files, root, "a.jpg"),
+            Some(SkipReason::NotIncluded)
+        );
+        assert_eq!(skip_reason(&files, root, "sub/deep/e.png"), None);
+    }
+
+    #[test]
+    fn test_collect_files_hidden_and_symlinks() {
+        let dir = create_tree();
+        let root = dir.path();
+
+        let files = collect_files(
+            root,
+            &DirectoryOptions {
+                include: vec!["*.jpg".to_string()],
+                include_hidden: true,
+                follow_symlinks: true,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+
+        assert_eq!(
+            relative(root, &files.selected),
+            vec![
+                ".git/c.jpg",
+                ".hidden.jpg",
+                "a.jpg",
+                "link.jpg",
+                "skip/f.jpg"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_collect_files_invalid_input() {
+        let dir = create_tree();
+
+        let result = collect_files(
+            dir.path(),
+            &DirectoryOptions {
+                include: vec!["[".to_string()],
+                ..Default::default()
+            },
+        );
+        match result {
+            Err(Error::InvalidRequest(msg)) => assert!(msg.contains("Invalid glob pattern")),
+            other => panic!("Expected InvalidRequest error, got: {:?}", other),
+        }
+
+        let result = collect_files(&dir.path().join("a.jpg"), &DirectoryOptions::default());
+        match result {
+            Err(Error::InvalidRequest(msg)) => assert!(msg.contains("Not a directory")),
+            other => panic!("Expected InvalidRequest error, got: {:?}", other),
+        }
+    }
+}
@@ -1,9 +1,37 @@
+use crate::models::{DetectionResult, FormattedDetectionResultList};
+use std::fmt;
 use std::io;
+use std::time::Duration;
 use thiserror::Error;
 
 /// Custom result type for the SDK
 pub type Result<T> = std::result::Result<T, Error>;
 
+/// Error details returned by the API
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ApiError {
+    /// HTTP status code of the response
+    pub http_status: u16,
+
+    /// Error code from the API, e.g. `upload-limit-reached`
+    pub code: String,
+
+    /// Error number from the API
+    pub errno: i32,
+
+    /// Error message from the API
+    pub message: String,
+
+    /// Request ID the API attached to the error, if any
+    pub request_id: Option<String>,
+}
+
+impl fmt::Display for ApiError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
 /// Error types for the Reality Defender SDK
 #[derive(Error, Debug)]
 pub enum Error {
@@ -13,15 +41,58 @@ pub enum Error {
 
     /// Authentication failure
     #[error("{0}")]
-    Unauthorized(String),
+    Unauthorized(ApiError),
+
+    /// The API key is not allowed to access the resource
+    #[error("Forbidden: {0}")]
+    Forbidden(ApiError),
+
+    /// The account's upload quota is used up
+    #[error("Quota exceeded: {0}")]
+    QuotaExceeded(ApiError),
+
+    /// The feature is not available on the free tier
+    #[error("Not available on the free tier: {0}")]
+    FreeTierNotAllowed(ApiError),
 
     /// Resource not found
     #[error("Resource not found")]
-    NotFound,
+    NotFound(ApiError),
 
-    /// Server error from the API
+    /// The API rejected the request as invalid
+    #[error("Invalid request: {0}")]
+    BadRequest(ApiError),
+
+    /// Too many requests. `retry_after` is the delay advertised by the API
+    /// in the `Retry-After` header, if any.
+    #[error("Rate limited{}", retry_after.map(|delay| format!(", retry after {delay:?}")).unwrap_or_default())]
+    RateLimited { retry_after: Option<Duration> },
+
+    /// Server error or any other unexpected response from the API
     #[error("API error: {0}")]
-    ServerError(String),
+    ServerError(ApiError),
+
+    /// No terminal result arrived while waiting. `last` holds the last
+    /// result fetched, so the caller can decide whether to keep waiting.
+    #[error("Timed out waiting for result {request_id} after {elapsed:?}")]
+    Timeout {
+        request_id: String,
+        elapsed: Duration,
+        last: Option<Box<DetectionResult>>,
+    },
+
+    /// Results were still being analyzed when waiting for a list of results
+    /// gave up. `last` holds the last list fetched.
+    #[error("Timed out waiting for results after {elapsed:?}")]
+    ResultsTimeout {
+        elapsed: Duration,
+        last: Box<FormattedDetectionResultList>,
+    },
+
+    /// The operation was cancelled. `request_ids` lists the analyses already
+    /// submitted, whose results can still be fetched later.
+    #[error("Cancelled")]
+    Cancelled { request_ids: Vec<String> },
 
     /// Invalid file
     #[error("Invalid file: {0}")]
@@ -31,7 +102,7 @@ pub enum Error {
     #[error("Upload failed: {0}")]
     UploadFailed(String),
 
-    /// Invalid request
+    /// Invalid request, rejected before it was sent
     #[error("Invalid request: {0}")]
     InvalidRequest(String),
 
@@ -56,10 +127,92 @@ pub enum Error {
     UnknownError(String),
 }
 
+impl Error {
+    /// Error details returned by the API, if the error came from an API response
+    pub fn api_error(&self) -> Option<&ApiError> {
+        match self {
+            Self::Unauthorized(error)
+            | Self::Forbidden(error)
+            | Self::QuotaExceeded(error)
+            | Self::FreeTierNotAllowed(error)
+            | Self::NotFound(error)
+            | Self::BadRequest(error)
+            | Self::ServerError(error) => Some(error),
+            _ => None,
+        }
+    }
+
+    /// Copy of the error, for reporting one failure to several callers.
+    /// Transport and JSON errors cannot be copied, so they become an
+    /// `UnknownError` with the same message.
+    pub(crate) fn duplicate(&self) -> Self {
+        match self {
+            Self::InvalidConfig(message) => Self::InvalidConfig(message.clone()),
+            Self::Unauthorized(error) => Self::Unauthorized(error.clone()),
+            Self::Forbidden(error) => Self::Forbidden(error.clone()),
+            Self::QuotaExceeded(error) => Self::QuotaExceeded(error.clone()),
+            Self::FreeTierNotAllowed(error) => Self::FreeTierNotAllowed(error.clone()),
+            Self::NotFound(error) => Self::NotFound(error.clone()),
+            Self::BadRequest(error) => Self::BadRequest(error.clone()),
+            Self::RateLimited { retry_after } => Self::RateLimited {
+                retry_after: *retry_after,
+            },
+            Self::ServerError(error) => Self::ServerError(error.clone()),
+            Self::Timeout {
+                request_id,
+                elapsed,
+                last,
+            } => Self::Timeout {
+                request_id: request_id.clone(),
+                elapsed: *elapsed,
+                last: last.clone(),
+            },
+            Self::ResultsTimeout { elapsed, last } => Self::ResultsTimeout {
+                elapsed: *elapsed,
+                last: last.clone(),
+            },
+            Self::Cancelled { request_ids } => Self::Cancelled {
+                request_ids: request_ids.clone(),
+            },
+            Self::InvalidFile(message) => Self::InvalidFile(message.clone()),
+            Self::UploadFailed(message) => Self::UploadFailed(message.clone()),
+            Self::InvalidRequest(message) => Self::InvalidRequest(message.clone()),
+            Self::InvalidData(message) => Self::InvalidData(message.clone()),
+            Self::IOError(error) => Self::IOError(io::Error::new(error.kind(), error.to_string())),
+            Self::RequestError(_) | Self::JsonError(_) => Self::UnknownError(self.to_string()),
+            Self::UnknownError(message) => Self::UnknownError(message.clone()),
+        }
+    }
+
+    /// Whether the failure is transient, so the same request may succeed later
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            Self::RateLimited { .. } => true,
+            Self::ServerError(error) => error.http_status >= 500,
+            Self::RequestError(error) => {
+                error.is_timeout() || error.is_connect() || error.is_request()
+            }
+            _ => false,
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use crate::Error;
+    use crate::error::ApiError;
+    use crate::{Error, FormattedDetectionResultList};
     use std::io;
+    use std::time::Duration;
+
+    fn api_error(http_status: u16, code: &str, message: &str) -> ApiError {
+        ApiError {
+            http_status,
+            code: code.to_string(),
+            errno: -1,
+            message: message.to_string(),
+            request_id: None,
+        }
+    }
 
     #[test]
     fn test_error_display() {
@@ -70,14 +223,67 @@ mod tests {
                 "Invalid configuration: missing api key",
             ),
             (
-                Error::Unauthorized("Authentication failed: Invalid API key".to_string()),
-                "Authentication failed: Invalid API key",
+                Error::Unauthorized(api_error(401, "unauthorized", "Invalid API key")),
+                "Invalid API key",
             ),
-            (Error::NotFound, "Resource not found"),
             (
-                Error::ServerError("internal error".to_string()),
+                Error::Forbidden(api_error(403, "forbidden", "Access denied")),
+                "Forbidden: Access denied",
+            ),
+            (
+                Error::QuotaExceeded(api_error(400, "upload-limit-reached", "Limit reached")),
+                "Quota exceeded: Limit reached",
+            ),
+            (
+                Error::FreeTierNotAllowed(api_error(400, "free-tier-not-allowed", "Upgrade")),
+                "Not available on the free tier: Upgrade",
+            ),
+            (
+                Error::NotFound(api_error(404, "not-found", "No such media")),
+                "Resource not found",
+            ),
+            (
+                Error::BadRequest(api_error(400, "error", "Invalid page size")),
+                "Invalid request: Invalid page size",
+            ),
+            (Error::RateLimited { retry_after: None }, "Rate limited"),
+            (
+                Error::RateLimited {
+                    retry_after: Some(Duration::from_secs(30)),
+                },
+                "Rate limited, retry after 30s",
+            ),
+            (
+                Error::ServerError(api_error(500, "error", "internal error")),
                 "API error: internal error",
             ),
+            (
+                Error::Timeout {
+                    request_id: "request-id".to_string(),
+                    elapsed: Duration::from_secs(90),
+                    last: None,
+                },
+                "Timed out waiting for result request-id after 90s",
+            ),
+            (
+                Error::ResultsTimeout {
+                    elapsed: Duration::from_secs(90),
+                    last: Box::new(FormattedDetectionResultList {
+                        total_items: 0,
+                        total_pages: 0,
+                        current_page: 0,
+                        current_page_items_count: 0,
+                        items: vec![],
+                    }),
+                },
+                "Timed out waiting for results after 90s",
+            ),
+            (
+                Error::Cancelled {
+                    request_ids: vec!["request-id".to_string()],
+                },
+                "Cancelled",
+            ),
             (
                 Error::InvalidFile("file not found".to_string()),
                 "Invalid file: file not found",
@@ -149,4 +355,47 @@ mod tests {
             _ => panic!("Wrong error type"),
         }
     }
+
+    #[test]
+    fn test_error_api_error() {
+        let error = Error::QuotaExceeded(api_error(400, "upload-limit-reached", "Limit reached"));
+        assert_eq!(error.api_error().unwrap().code, "upload-limit-reached");
+        assert!(Error::InvalidRequest("bad".to_string())
+            .api_error()
+            .is_none());
+    }
+
+    #[test]
+    fn test_error_is_retryable() {
+        assert!(Error::RateLimited { retry_after: None }.is_retryable());
+        assert!(Error::ServerError(api_error(503, "error", "unavailable")).is_retryable());
+        assert!(!Error::ServerError(api_error(422, "error", "unprocessable")).is_retryable());
+        assert!(!Error::Unauthorized(api_error(401, "error", "Invalid API key")).is_retryable());
+        assert!(!Error::QuotaExceeded(api_error(400, "error", "Limit reached")).is_retryable());
+        assert!(!Error::NotFound(api_error(404, "error", "Not found")).is_retryable());
+        assert!(!Error::InvalidFile("too large".to_string()).is_retryable());
+    }
+
+    #[test]
+    fn test_error_duplicate() {
+        let error = Error::Unauthorized(api_error(401, "unauthorized", "Invalid API key"));
+        let duplicate = error.duplicate();
+        assert!(matches!(&duplicate, Error::Unauthorized(api) if api.http_status == 401));
+        assert_eq!(duplicate.to_string(), error.to_string());
+
+        let error = Error::IOError(io::Error::new(io::ErrorKind::NotFound, "missing"));
+        assert!(matches!(
+            error.duplicate(),
+            Error::IOError(err) if err.kind() == io::ErrorKind::NotFound
+        ));
+
+        // JSON errors cannot be copied, so only their message is kept
+        let error: Error = serde_json::from_str::<serde_json::Value>("invalid")
+            .unwrap_err()
+            .into();
+        match error.duplicate() {
+            Error::UnknownError(message) => assert_eq!(message, error.to_string()),
+            other => panic!("Expected UnknownError, got: {:?}", other),
+        }
+    }
 }
@@ -0,0 +1,415 @@
+use crate::error::{Error, Result};
+use crate::file::{find_media_type, find_media_type_by_mime, MediaType};
+use crate::http::USER_AGENT;
+use crate::models::FetchOptions;
+use bytes::{Bytes, BytesMut};
+use futures::StreamExt;
+use reqwest::header::{CONTENT_TYPE, LOCATION};
+use reqwest::{redirect, Client as ReqwestClient, ClientBuilder, Response};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::path::Path;
+use std::time::Duration;
+use url::{Host, Url};
+
+/// Redirects followed unless the options say otherwise
+const DEFAULT_MAX_REDIRECTS: usize = 5;
+
+/// Download media from a direct URL, returning the name to upload it under
+/// and its content. The whole download, redirects included, must finish
+/// within `timeout`.
+pub(crate) async fn fetch_media(
+    url: &str,
+    options: &FetchOptions,
+    timeout: Duration,
+) -> Result<(String, Bytes)> {
+    tokio::time::timeout(timeout, download(url, options))
+        .await
+        .map_err(|_| {
+            Error::UploadFailed(format!("Downloading {url} timed out after {timeout:?}"))
+        })?
+}
+
+async fn download(url: &str, options: &FetchOptions) -> Result<(String, Bytes)> {
+    let mut url = parse_url(url)?;
+    let max_redirects = options.max_redirects.unwrap_or(DEFAULT_MAX_REDIRECTS);
+
+    // Redirects are followed by hand so every hop is checked like the first
+    let mut redirects = 0;
+    let response = loop {
+        let client = connect(&url, options.allow_private_addresses).await?;
+        let response = client.get(url.clone()).send().await?;
+        if !response.status().is_redirection() {
+            break response;
+        }
+
+        let location = response
+            .headers()
+            .get(LOCATION)
+            .and_then(|location| location.to_str().ok())
+            .ok_or_else(|| {
+                Error::UploadFailed(format!("Redirect from {url} has no valid location"))
+            })?;
+        if redirects == max_redirects {
+            return Err(Error::UploadFailed(format!(
+                "Too many redirects downloading {url}, at most {max_redirects} are followed"
+            )));
+        }
+        url = url
+            .join(location)
+            .map_err(|_| Error::InvalidRequest(format!("Invalid URL: {location}")))
+            .and_then(check_scheme)?;
+        redirects += 1;
+    };
+
+    if !response.status().is_success() {
+        return Err(Error::UploadFailed(format!(
+            "Downloading {url} failed with status {}",
+            response.status()
+        )));
+    }
+
+    let file_name = file_name(&url, &response, options.file_name.as_deref())?;
+    let media_type = find_media_type(Path::new(&file_name))
+        .ok_or_else(|| Error::InvalidFile(format!("Unsupported file type: {file_name}")))?;
+    let limit = options.max_size.map_or(media_type.size_limit, |max_size| {
+        max_size.min(media_type.size_limit)
+    });
+    let too_large = || Error::InvalidFile(format!("File too large: {file_name} from {url}"));
+
+    // The announced length is checked first, but servers can lie about it
+    if response.content_length().is_some_and(|len| len > limit) {
+        return Err(too_large());
+    }
+    let mut content = BytesMut::new();
+    let mut stream = response.bytes_stream();
+    while let Some(chunk) = stream.next().await {
+        let chunk = chunk?;
+        if content.len() as u64 + chunk.len() as u64 > limit {
+            return Err(too_large());
+        }
+        content.extend_from_slice(&chunk);
+    }
+
+    Ok((file_name, content.freeze()))
+}
+
+fn parse_url(url: &str) -> Result<Url> {
+    Url::parse(url)
+        .map_err(|_| Error::InvalidRequest(format!("Invalid URL: {url}")))
+        .and_then(check_scheme)
+}
+
+fn check_scheme(url: Url) -> Result<Url> {
+    match url.scheme() {
+        "http" | "https" => Ok(url),
+        _ => Err(Error::InvalidRequest(
+            "URL must use http or https scheme".to_string(),
+        )),
+    }
+}
+
+/// Client for a single request to `url`. Unless private addresses are
+/// allowed, the host is resolved and checked up front, and the client is
+/// pinned to the checked addresses so a second lookup cannot change them.
+async fn connect(url: &Url, allow_private_addresses: bool) -> Result<ReqwestClient> {
+    let mut builder = ClientBuilder::new()
+        .user_agent(USER_AGENT)
+        .redirect(redirect::Policy::none());
+
+    let host = url
+        .host()
+        .ok_or_else(|| Error::InvalidRequest("URL must have a valid host".to_string()))?;
+    if !allow_private_addresses {
+        let addresses: Vec<IpAddr> = match &host {
+            Host::Ipv4(address) => vec![IpAddr::V4(*address)],
+            Host::Ipv6(address) => vec![IpAddr::V6(*address)],
+            Host::Domain(domain) => {
+                let port = url.port_or_known_default().unwrap_or(80);
+                let addresses: Vec<SocketAddr> =
+                    tokio::net::lookup_host((*domain, port)).await?.collect();
+                builder = builder.resolve_to_addrs(domain, &addresses);
+                addresses.iter().map(SocketAddr::ip).collect()
+            }
+        };
+
+        if let Some(address) = addresses.into_iter().find(|address| is_private(*address)) {
+            return Err(Error::InvalidRequest(format!(
+                "{host} resolves to the private address {address}"
+            )));
+        }
+    }
+
+    Ok(builder.build()?)
+}
+
+/// Whether an address is not a publicly routable unicast address. IPv6
+/// addresses embedding an IPv4 address are judged by that address.
+fn is_private(address: IpAddr) -> bool {
+    match address {
+        IpAddr::V4(address) => {
+            let octets = address.octets();
+            address.is_private()
+                || address.is_loopback()
+                || address.is_link_local()
+                || address.is_unspecified()
+                || address.is_broadcast()
+                || address.is_multicast()
+                || octets[0] == 0
+                // Shared address space used by carrier-grade NAT, 100.64.0.0/10
+                || (octets[0] == 100 && octets[1] & 0xC0 == 64)
+        }
+        IpAddr::V6(address) => {
+            let segments = address.segments();
+            address.is_loopback()
+                || address.is_unspecified()
+                || address.is_multicast()
+                // Unique local fc00::/7 and link-local fe80::/10 addresses
+                || segments[0] & 0xFE00 == 0xFC00
+                || segments[0] & 0xFFC0 == 0xFE80
+                || embedded_ipv4(address).is_some_and(|address| is_private(IpAddr::V4(address)))
+        }
+    }
+}
+
+/// IPv4 address carried by an IPv4-mapped, NAT64 64:ff9b::/96 or 6to4
+/// 2002::/16 address
+fn embedded_ipv4(address: Ipv6Addr) -> Option<Ipv4Addr> {
+    let octets = address.octets();
+    let segments = address.segments();
+    if segments[..6] == [0x64, 0xFF9B, 0, 0, 0, 0] {
+        Some(Ipv4Addr::new(
+            octets[12], octets[13], octets[14], octets[15],
+        ))
+    } else if segments[0] == 0x2002 {
+        Some(Ipv4Addr::new(octets[2], octets[3], octets[4], octets[5]))
+    } else {
+        address.to_ipv4_mapped()
+    }
+}
+
+/// Name to upload a download under: the given name, the last segment of the
+/// URL when it has a supported extension, or that segment with the extension
+/// of the content type. Content types that are not media, such as the HTML
+/// of a landing page, are rejected.
+fn file_name(url: &Url, response: &Response, file_name: Option<&str>) -> Result<String> {
+    let content_type = response
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|content_type| content_type.to_str().ok())
+        .and_then(|content_type| content_type.split(';').next())
+        .map(|content_type| content_type.trim().to_ascii_lowercase());
+
+    let media_type: Option<&MediaType> = match content_type.as_deref() {
+        None | Some("application/octet-stream" | "binary/octet-stream") => None,
+        Some(content_type) => match find_media_type_by_mime(content_type) {
+            Some(media_type) => Some(media_type),
+            None if ["image/", "audio/", "video/"]
+                .iter()
+                .any(|prefix| content_type.starts_with(prefix)) =>
+            {
+                None
+            }
+            None => {
+                return Err(Error::InvalidFile(format!(
+                    "Unsupported content type {content_type} from {url}"
+                )))
+            }
+        },
+    };
+
+    if let Some(file_name) = file_name {
+        return Ok(file_name.to_string());
+    }
+    let segment = url
+        .path_segments()
+        .and_then(|mut segments| segments.next_back())
+        .filter(|segment| !segment.is_empty())
+        .unwrap_or("download");
+    if find_media_type(Path::new(segment)).is_some() {
+        return Ok(segment.to_string());
+    }
+
+    let stem = Path::new(segment)
+        .file_stem()
+        .and_then(|stem| stem.to_str())
+        .unwrap_or("download");
+    media_type
+        .map(|media_type| format!("{stem}.{}", media_type.extension))
+        .ok_or_else(|| Error::InvalidFile(format!("Cannot determine the file type of {url}")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn local() -> FetchOptions {
+        FetchOptions {
+            allow_private_addresses: true,
+            ..Default::default()
+        }
+    }
+
+    async fn fetch(url: &str, options: &FetchOptions) -> Result<(String, Bytes)> {
+        fetch_media(url, options, Duration::from_secs(5)).await
+    }
+
+    #[tokio::test]
+    async fn test_fetch_follows_redirects() {
+        let mut server = mockito::Server::new_async().await;
+        let mock_start = server
+            .mock("GET", "/share/abc")
+            .with_status(302)
+            .with_header("location", "/media/frame?token=1")
+            .expect(2)
+            .create_async()
+            .await;
+        let mock_media = server
+            .mock("GET", "/media/frame?token=1")
+            .with_status(200)
+            .with_header("content-type", "image/png; charset=binary")
+            .with_body("png data")
+            .expect(1)
+            .create_async()
+            .await;
+
+        let url = format!("{}/share/abc", server.url());
+        let (file_name, content) = fetch(&url, &local()).await.unwrap();
+        assert_eq!(file_name, "frame.png");
+        assert_eq!(content, Bytes::from_static(b"png data"));
+
+        let options = FetchOptions {
+            max_redirects: Some(0),
+            ..local()
+        };
+        match fetch(&url, &options).await {
+            Err(Error::UploadFailed(msg)) => assert!(msg.contains("Too many redirects"), "{msg}"),
+            other => panic!("Expected UploadFailed error, got: {:?}", other),
+        }
+        mock_start.assert_async().await;
+        mock_media.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_fetch_blocks_private_addresses() {
+        let mut server = mockito::Server::new_async().await;
+        let mock = server
+            .mock("GET", "/video.mp4")
+            .expect(0)
+            .create_async()
+            .await;
+
+        let url = format!("{}/video.mp4", server.url());
+        match fetch(&url, &FetchOptions::default()).await {
+            Err(Error::InvalidRequest(msg)) => {
+                assert_eq!(msg, "127.0.0.1 resolves to the private address 127.0.0.1")
+            }
+            other => panic!("Expected InvalidRequest error, got: {:?}", other),
+        }
+        match fetch("file:///etc/passwd", &local()).await {
+            Err(Error::InvalidRequest(msg)) => assert_eq!(msg, "URL must use http or https scheme"),
+            other => panic!("Expected InvalidRequest error, got: {:?}", other),
+        }
+        mock.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_fetch_checks_size_and_content_type() {
+        let mut server = mockito::Server::new_async().await;
+        server
+            .mock("GET", "/clip.mp3")
+            .with_status(200)
+            .with_header("content-type", "audio/mpeg")
+            .with_body("0123456789")
+            .create_async()
+            .await;
+        server
+            .mock("GET", "/post")
+            .with_status(200)
+            .with_header("content-type", "text/html")
+            .with_body("<html></html>")
+            .create_async()
+            .await;
+        server
+            .mock("GET", "/missing.mp3")
+            .with_status(404)
+            .create_async()
+            .await;
+
+        let options = FetchOptions {
+            max_size: Some(4),
+            ..local()
+        };
+        match fetch(&format!("{}/clip.mp3", server.url()), &options).await {
+            Err(Error::InvalidFile(msg)) => {
+                assert!(msg.starts_with("File too large: clip.mp3"), "{msg}")
+            }
+            other => panic!("Expected InvalidFile error, got: {:?}", other),
+        }
+        match fetch(&format!("{}/post", server.url()), &local()).await {
+            Err(Error::InvalidFile(msg)) => assert!(
+                msg.starts_with("Unsupported content type text/html"),
+                "{msg}"
+            ),
+            other => panic!("Expected InvalidFile error, got: {:?}", other),
+        }
+        match fetch(&format!("{}/missing.mp3", server.url()), &local()).await {
+            Err(Error::UploadFailed(msg)) => assert!(msg.contains("404"), "{msg}"),
+            other => panic!("Expected UploadFailed error, got: {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_fetch_times_out() {
+        let mut server = mockito::Server::new_async().await;
+        server
+            .mock("GET", "/slow.wav")
+            .with_status(200)
+            .with_chunked_body(|writer| {
+                std::thread::sleep(Duration::from_millis(500));
+                writer.write_all(b"late")
+            })
+            .create_async()
+            .await;
+
+        let url = format!("{}/slow.wav", server.url());
+        match fetch_media(&url, &local(), Duration::from_millis(100)).await {
+            Err(Error::UploadFailed(msg)) => assert!(msg.contains("timed out"), "{msg}"),
+            other => panic!("Expected UploadFailed error, got: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_is_private() {
+        for address in [
+            "10.1.2.3",
+            "172.16.0.1",
+            "192.168.1.1",
+            "127.0.0.1",
+            "169.254.169.254",
+            "100.64.0.1",
+            "0.0.0.0",
+            "::1",
+            "fd00::1",
+            "fe80::1",
+            "::ffff:10.0.0.1",
+            "224.0.0.1",
+            "239.255.255.250",
+            "ff02::1",
+            "64:ff9b::7f00:1",
+            "64:ff9b::a9fe:a9fe",
+            "2002:c0a8:101::1",
+            "2002:a00:1::",
+        ] {
+            assert!(is_private(address.parse().unwrap()), "{address}");
+        }
+        for address in [
+            "8.8.8.8",
+            "100.128.0.1",
+            "2606:4700::1111",
+            "64:ff9b::808:808",
+            "2002:808:808::1",
+        ] {
+            assert!(!is_private(address.parse().unwrap()), "{address}");
+        }
+    }
+}
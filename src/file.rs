@@ -1,24 +1,280 @@
-#[derive(Debug)]
-pub struct FileTypeConfig {
-    pub extensions: &'static [&'static str],
+use std::path::Path;
+
+/// Size limit for video files, 250 MB
+const VIDEO_SIZE_LIMIT: u64 = 262144000;
+/// Size limit for image files, 50 MB
+const IMAGE_SIZE_LIMIT: u64 = 52428800;
+/// Size limit for audio files, 20 MB
+const AUDIO_SIZE_LIMIT: u64 = 20971520;
+/// Size limit for text files, 5 MB
+const TEXT_SIZE_LIMIT: u64 = 5242880;
+
+/// Category of supported media
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MediaCategory {
+    Image,
+    Video,
+    Audio,
+    Text,
+}
+
+/// A supported media type, identified by its extension
+#[derive(Debug, PartialEq, Eq)]
+pub struct MediaType {
+    /// File extension, in lowercase
+    pub extension: &'static str,
+
+    /// MIME type sent as the Content-Type of uploads
+    pub mime_type: &'static str,
+
+    /// Category of the media
+    pub category: MediaCategory,
+
+    /// Maximum size of an upload, in bytes
     pub size_limit: u64,
 }
 
-pub const SUPPORTED_FILE_TYPES: &[FileTypeConfig] = &[
-    FileTypeConfig {
-        extensions: &["mp4", "mov"],
-        size_limit: 262144000, // 250 MB
-    },
-    FileTypeConfig {
-        extensions: &["jpg", "png", "jpeg", "gif", "webp"],
-        size_limit: 52428800, // 50 MB
-    },
-    FileTypeConfig {
-        extensions: &["flac", "wav", "mp3", "m4a", "aac", "alac", "ogg"],
-        size_limit: 20971520, // 20 MB
-    },
-    FileTypeConfig {
-        extensions: &["txt"],
-        size_limit: 5242880, // 5 MB
-    },
+const fn media_type(
+    extension: &'static str,
+    mime_type: &'static str,
+    category: MediaCategory,
+) -> MediaType {
+    let size_limit = match category {
+        MediaCategory::Video => VIDEO_SIZE_LIMIT,
+        MediaCategory::Image => IMAGE_SIZE_LIMIT,
+        MediaCategory::Audio => AUDIO_SIZE_LIMIT,
+        MediaCategory::Text => TEXT_SIZE_LIMIT,
+    };
+    MediaType {
+        extension,
+        mime_type,
+        category,
+        size_limit,
+    }
+}
+
+/// Every media type accepted for upload. An extension listed more than once
+/// can hold content of each of its MIME types, and the first one is used
+/// when the content is not inspected.
+pub const MEDIA_TYPES: &[MediaType] = &[
+    media_type("mp4", "video/mp4", MediaCategory::Video),
+    media_type("mov", "video/quicktime", MediaCategory::Video),
+    media_type("jpg", "image/jpeg", MediaCategory::Image),
+    media_type("jpeg", "image/jpeg", MediaCategory::Image),
+    media_type("png", "image/png", MediaCategory::Image),
+    media_type("gif", "image/gif", MediaCategory::Image),
+    media_type("webp", "image/webp", MediaCategory::Image),
+    media_type("flac", "audio/flac", MediaCategory::Audio),
+    media_type("wav", "audio/wav", MediaCategory::Audio),
+    media_type("mp3", "audio/mpeg", MediaCategory::Audio),
+    media_type("m4a", "audio/mp4", MediaCategory::Audio),
+    media_type("aac", "audio/aac", MediaCategory::Audio),
+    media_type("alac", "audio/mp4", MediaCategory::Audio),
+    media_type("alac", "audio/x-caf", MediaCategory::Audio),
+    media_type("ogg", "audio/ogg", MediaCategory::Audio),
+    media_type("txt", "text/plain", MediaCategory::Text),
 ];
+
+/// Find the media type of a path based on its extension, ignoring case
+pub fn find_media_type(path: &Path) -> Option<&'static MediaType> {
+    let extension = path.extension()?.to_str()?;
+    MEDIA_TYPES
+        .iter()
+        .find(|media_type| media_type.extension.eq_ignore_ascii_case(extension))
+}
+
+/// Find the media type of content recognised as the given MIME type
+pub fn find_media_type_by_mime(mime_type: &str) -> Option<&'static MediaType> {
+    MEDIA_TYPES
+        .iter()
+        .find(|media_type| media_type.mime_type == mime_type)
+}
+
+/// Whether a path's extension, ignoring case, can hold content of the given
+/// MIME type
+pub fn extension_matches(path: &Path, mime_type: &str) -> bool {
+    let extension = match path.extension().and_then(|extension| extension.to_str()) {
+        Some(extension) => extension,
+        None => return false,
+    };
+    MEDIA_TYPES.iter().any(|media_type| {
+        media_type.extension.eq_ignore_ascii_case(extension) && media_type.mime_type == mime_type
+    })
+}
+
+/// Number of leading bytes inspected to recognise the content of a file
+pub const SNIFF_LEN: usize = 512;
+
+/// Recognise the MIME type of media from the first bytes of its content.
+/// Text is recognised as UTF-8 without NUL bytes.
+pub fn sniff_content(head: &[u8]) -> Option<&'static str> {
+    let at = |offset: usize, magic: &[u8]| head.get(offset..offset + magic.len()) == Some(magic);
+
+    if at(0, b"\xFF\xD8\xFF") {
+        return Some("image/jpeg");
+    }
+    if at(0, b"\x89PNG\r\n\x1A\n") {
+        return Some("image/png");
+    }
+    if at(0, b"GIF87a") || at(0, b"GIF89a") {
+        return Some("image/gif");
+    }
+    if at(0, b"RIFF") && at(8, b"WEBP") {
+        return Some("image/webp");
+    }
+    if at(0, b"RIFF") && at(8, b"WAVE") {
+        return Some("audio/wav");
+    }
+
+    // ISO base media files start with a box, whose type follows its size
+    if at(4, b"ftyp") {
+        return match head.get(8..12) {
+            Some(b"qt  ") => Some("video/quicktime"),
+            Some(b"M4A ") | Some(b"M4B ") | Some(b"M4P ") => Some("audio/mp4"),
+            Some(_) => Some("video/mp4"),
+            None => None,
+        };
+    }
+    if at(4, b"moov") || at(4, b"mdat") || at(4, b"wide") || at(4, b"pnot") {
+        return Some("video/quicktime");
+    }
+
+    if at(0, b"fLaC") {
+        return Some("audio/flac");
+    }
+    if at(0, b"OggS") {
+        return Some("audio/ogg");
+    }
+    if at(0, b"caff") {
+        return Some("audio/x-caf");
+    }
+    if at(0, b"ID3") {
+        return Some("audio/mpeg");
+    }
+    if at(0, b"ADIF") {
+        return Some("audio/aac");
+    }
+
+    // MPEG audio frames start with a sync word, followed by the layer bits:
+    // zero for ADTS AAC and one for MP3
+    if let [0xFF, flags, ..] = head {
+        if flags & 0xF6 == 0xF0 {
+            return Some("audio/aac");
+        }
+        if flags & 0xE6 == 0xE2 {
+            return Some("audio/mpeg");
+        }
+    }
+
+    if is_text(head) {
+        return Some("text/plain");
+    }
+
+    None
+}
+
+/// Whether content is UTF-8 text, allowing a character cut off at the end
+fn is_text(head: &[u8]) -> bool {
+    if head.is_empty() || head.contains(&0) {
+        return false;
+    }
+
+    match std::str::from_utf8(head) {
+        Ok(_) => true,
+        Err(err) => err.error_len().is_none(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_media_type_ignores_case() {
+        let media_type = find_media_type(Path::new("IMAGE.JPG")).unwrap();
+        assert_eq!(media_type.mime_type, "image/jpeg");
+        assert_eq!(media_type.category, MediaCategory::Image);
+        assert_eq!(media_type.size_limit, 52428800);
+
+        let media_type = find_media_type(Path::new("clip.Mp4")).unwrap();
+        assert_eq!(media_type.category, MediaCategory::Video);
+        assert_eq!(media_type.size_limit, 262144000);
+
+        assert!(find_media_type(Path::new("archive.ZIP")).is_none());
+        assert!(find_media_type(Path::new("no_extension")).is_none());
+    }
+
+    #[test]
+    fn test_media_type_registry() {
+        let limits = [
+            (MediaCategory::Video, 262144000),
+            (MediaCategory::Image, 52428800),
+            (MediaCategory::Audio, 20971520),
+            (MediaCategory::Text, 5242880),
+        ];
+        for media_type in MEDIA_TYPES {
+            assert_eq!(media_type.extension, media_type.extension.to_lowercase());
+            assert_ne!(media_type.mime_type, "application/octet-stream");
+            assert!(limits.contains(&(media_type.category, media_type.size_limit)));
+        }
+
+        // Formats the upload path rejects are not part of the registry
+        assert!(find_media_type(Path::new("clip.avi")).is_none());
+        assert!(find_media_type(Path::new("clip.webm")).is_none());
+    }
+
+    #[test]
+    fn test_extension_matches() {
+        assert!(extension_matches(Path::new("photo.JPEG"), "image/jpeg"));
+        assert!(extension_matches(Path::new("song.alac"), "audio/mp4"));
+        assert!(extension_matches(Path::new("song.alac"), "audio/x-caf"));
+        assert!(!extension_matches(Path::new("clip.mp4"), "image/png"));
+        assert!(!extension_matches(Path::new("no_extension"), "image/png"));
+    }
+
+    #[test]
+    fn test_sniff_content() {
+        let cases: [(&[u8], &str); 18] = [
+            (b"\xFF\xD8\xFF\xE0\x00\x10JFIF", "image/jpeg"),
+            (b"\x89PNG\r\n\x1A\n\x00\x00\x00\x0DIHDR", "image/png"),
+            (b"GIF89a\x01\x00", "image/gif"),
+            (b"RIFF\x24\x00\x00\x00WEBPVP8 ", "image/webp"),
+            (b"\x00\x00\x00\x20ftypisom\x00\x00\x02\x00", "video/mp4"),
+            (
+                b"\x00\x00\x00\x14ftypqt  \x00\x00\x02\x00",
+                "video/quicktime",
+            ),
+            (
+                b"\x00\x00\x00\x08wide\x00\x00\x00\x00mdat",
+                "video/quicktime",
+            ),
+            (b"\x00\x00\x00\x20ftypM4A \x00\x00\x02\x00", "audio/mp4"),
+            (b"caff\x00\x01\x00\x00", "audio/x-caf"),
+            (b"fLaC\x00\x00\x00\x22", "audio/flac"),
+            (b"RIFF\x24\x00\x00\x00WAVEfmt ", "audio/wav"),
+            (b"ID3\x04\x00\x00\x00\x00", "audio/mpeg"),
+            (b"\xFF\xFB\x90\x64", "audio/mpeg"),
+            (b"\xFF\xF1\x50\x80", "audio/aac"),
+            (b"ADIF\x00\x00", "audio/aac"),
+            (b"OggS\x00\x02\x00\x00", "audio/ogg"),
+            (b"plain text content", "text/plain"),
+            // A multi-byte character cut off by the sniffing window
+            (b"caf\xC3\xA9 \xC3", "text/plain"),
+        ];
+
+        for (head, mime_type) in cases {
+            assert_eq!(sniff_content(head), Some(mime_type), "{head:?}");
+            // Every recognised format is a supported media type
+            assert!(find_media_type_by_mime(mime_type).is_some(), "{mime_type}");
+        }
+    }
+
+    #[test]
+    fn test_sniff_content_unrecognised() {
+        assert!(sniff_content(b"").is_none());
+        assert!(sniff_content(b"\x00\x01\x02\x03binary").is_none());
+        assert!(sniff_content(b"\xC3\x28 invalid utf-8").is_none());
+        // Too short to read the brand of an ISO base media file
+        assert!(sniff_content(b"\x00\x00\x00\x20ftyp").is_none());
+    }
+}
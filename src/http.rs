@@ -1,15 +1,37 @@
-use crate::config::Config;
-use crate::error::{Error, Result};
-use crate::file::SUPPORTED_FILE_TYPES;
+use crate::config::{Config, ContentTypePolicy, RetryPolicy};
+use crate::error::{ApiError, Error, Result};
+use crate::fetch::fetch_media;
+use crate::file::{
+    extension_matches, find_media_type, find_media_type_by_mime, sniff_content, SNIFF_LEN,
+};
 use crate::http::api_paths::SOCIAL_MEDIA;
-use crate::models::{BaseResponse, UploadSocialMediaOptions};
-use crate::utils::{determine_content_type, is_valid_url};
+use crate::inspect::{inspect_media, inspect_path, MediaInfo};
+use crate::models::{
+    BaseResponse, FetchOptions, MultipartDetails, SignedUrlResponse, UploadProgress,
+    UploadSocialMediaOptions,
+};
+use crate::normalize::{normalize_image, ImageNormalization};
+use crate::rate_limit::RateLimiter;
+use crate::utils::{
+    determine_content_type, is_valid_url, parse_retry_after, presigned_url_expired,
+};
 use crate::UploadResult;
-use reqwest::{Client as ReqwestClient, ClientBuilder, Response, StatusCode};
+use bytes::Bytes;
+use futures::{stream, StreamExt, TryStreamExt};
+use reqwest::{Client as ReqwestClient, ClientBuilder, Request, Response, StatusCode};
 use serde::de::DeserializeOwned;
 use serde::Serialize;
-use std::path::Path;
+use std::io::{Cursor, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, PoisonError};
 use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncReadExt};
+use tokio::sync::mpsc;
+use tokio::time::sleep;
+use tokio_util::io::ReaderStream;
+
+/// User agent sent with every request
+pub(crate) const USER_AGENT: &str = "realitydefender-go-sdk/1.0";
 
 /// Constants for API paths
 pub mod api_paths {
@@ -23,10 +45,144 @@ pub mod api_paths {
     pub const SOCIAL_MEDIA: &str = "/api/files/social";
 }
 
+/// Outcome of an upload to a presigned URL
+#[derive(Debug, PartialEq, Eq)]
+pub enum PresignedPut {
+    /// The content was uploaded, and storage identified it with the ETag
+    Uploaded { etag: Option<String> },
+
+    /// The presigned URL expired and a new one must be requested
+    Expired,
+}
+
+/// Size of the chunks whose progress is reported while uploading
+const PROGRESS_CHUNK_SIZE: usize = 64 * 1024;
+
+/// Chunks read ahead of a streamed upload
+const STREAMED_CHUNKS: usize = 4;
+
+/// Callback receiving the progress of an upload to a presigned URL
+pub type ProgressCallback = Arc<dyn Fn(UploadProgress) + Send + Sync>;
+
+/// Content uploaded to a presigned URL
+#[derive(Debug, Clone)]
+pub enum UploadBody {
+    /// Content already held in memory
+    Bytes(Bytes),
+
+    /// `len` bytes of a file streamed from disk, starting at `offset`. The
+    /// file is opened again for every attempt, so retries never need the
+    /// whole file in memory.
+    File {
+        path: PathBuf,
+        offset: u64,
+        len: u64,
+    },
+
+    /// `len` bytes received from a reader while they are read. The content
+    /// can only be sent once, so it is never retried or split into parts.
+    Stream {
+        chunks: Arc<Mutex<Option<mpsc::Receiver<std::io::Result<Bytes>>>>>,
+        len: u64,
+    },
+}
+
+impl UploadBody {
+    /// Number of bytes sent as the Content-Length
+    pub fn len(&self) -> u64 {
+        match self {
+            Self::Bytes(data) => data.len() as u64,
+            Self::File { len, .. } | Self::Stream { len, .. } => *len,
+        }
+    }
+
+    /// Whether the content can be sent more than once
+    fn is_replayable(&self) -> bool {
+        !matches!(self, Self::Stream { .. })
+    }
+
+    /// The `len` bytes of this content starting at `offset`
+    fn part(&self, offset: u64, len: u64) -> Self {
+        match self {
+            Self::Bytes(data) => Self::Bytes(data.slice(offset as usize..(offset + len) as usize)),
+            Self::File {
+                path,
+                offset: start,
+                ..
+            } => Self::File {
+                path: path.clone(),
+                offset: start + offset,
+                len,
+            },
+            Self::Stream { .. } => unreachable!("Streamed content is never split into parts"),
+        }
+    }
+
+    /// Request body for a single upload attempt, reporting the bytes sent to
+    /// `progress` as chunks are handed to the connection
+    fn to_body(&self, progress: Option<&ProgressCallback>) -> std::io::Result<reqwest::Body> {
+        let chunks = match self {
+            Self::Bytes(data) if progress.is_none() => {
+                return Ok(reqwest::Body::from(data.clone()))
+            }
+            Self::Bytes(data) => {
+                let data = data.clone();
+                stream::iter((0..data.len()).step_by(PROGRESS_CHUNK_SIZE))
+                    .map(move |start| {
+                        Ok(data.slice(start..data.len().min(start + PROGRESS_CHUNK_SIZE)))
+                    })
+                    .boxed()
+            }
+            Self::File { path, offset, len } => {
+                let mut file = std::fs::File::open(path)?;
+                file.seek(SeekFrom::Start(*offset))?;
+                let file = tokio::fs::File::from_std(file).take(*len);
+                ReaderStream::with_capacity(file, PROGRESS_CHUNK_SIZE).boxed()
+            }
+            Self::Stream { chunks, .. } => {
+                let receiver = chunks
+                    .lock()
+                    .unwrap_or_else(PoisonError::into_inner)
+                    .take()
+                    .ok_or_else(|| {
+                        std::io::Error::other("Content from a reader can only be sent once")
+                    })?;
+                stream::unfold(receiver, |mut receiver| async move {
+                    let chunk = receiver.recv().await?;
+                    Some((chunk, receiver))
+                })
+                .boxed()
+            }
+        };
+
+        let progress = match progress {
+            Some(progress) => progress.clone(),
+            None => return Ok(reqwest::Body::wrap_stream(chunks)),
+        };
+
+        let total_bytes = self.len();
+        let mut bytes_sent = 0;
+        progress(UploadProgress {
+            bytes_sent,
+            total_bytes,
+        });
+
+        let chunks = chunks.inspect_ok(move |chunk| {
+            bytes_sent += chunk.len() as u64;
+            progress(UploadProgress {
+                bytes_sent,
+                total_bytes,
+            });
+        });
+        Ok(reqwest::Body::wrap_stream(chunks))
+    }
+}
+
 /// HTTP client for making API requests
 pub struct HttpClient {
     client: ReqwestClient,
     config: Config,
+    rate_limiter: Option<RateLimiter>,
 }
 
 impl HttpClient {
@@ -36,26 +192,33 @@ impl HttpClient {
 
         // Use the same User-Agent as Go SDK might be using
         let client = ClientBuilder::new()
-            .user_agent("realitydefender-go-sdk/1.0")
+            .user_agent(USER_AGENT)
             .timeout(Duration::from_secs(config.get_timeout_seconds()))
             .build()?;
 
-        Ok(Self { client, config })
+        let rate_limiter = config.rate_limit.as_ref().map(RateLimiter::new);
+
+        Ok(Self {
+            client,
+            config,
+            rate_limiter,
+        })
     }
 
     /// Make a GET request to the specified endpoint
     pub async fn get<T: DeserializeOwned>(&self, endpoint: &str) -> Result<T> {
         let url = format!("{}{}", self.config.get_base_url(), endpoint);
 
-        let request = self
-            .client
-            .get(&url)
-            .header("X-API-KEY", &self.config.api_key)
-            .header("Accept", "application/json")
-            .header("Accept-Encoding", "gzip")
-            .build()?;
-
-        let response = self.client.execute(request).await?;
+        let response = self
+            .execute_with_retry(|| {
+                self.client
+                    .get(&url)
+                    .header("X-API-KEY", &self.config.api_key)
+                    .header("Accept", "application/json")
+                    .header("Accept-Encoding", "gzip")
+                    .build()
+            })
+            .await?;
         self.handle_response(response).await
     }
 
@@ -67,20 +230,22 @@ impl HttpClient {
     ) -> Result<T> {
         let url = format!("{}{}", self.config.get_base_url(), endpoint);
 
-        let request = self
-            .client
-            .get(&url)
-            .query(params)
-            .header("X-API-KEY", &self.config.api_key)
-            .header("Accept", "application/json")
-            .header("Accept-Encoding", "gzip")
-            .build()?;
-
-        let response = self.client.execute(request).await?;
+        let response = self
+            .execute_with_retry(|| {
+                self.client
+                    .get(&url)
+                    .query(params)
+                    .header("X-API-KEY", &self.config.api_key)
+                    .header("Accept", "application/json")
+                    .header("Accept-Encoding", "gzip")
+                    .build()
+            })
+            .await?;
         self.handle_response(response).await
     }
 
-    /// Make a POST request with JSON data to the specified endpoint
+    /// Make a POST request with JSON data to the specified endpoint.
+    /// POST requests create resources and are never retried.
     pub async fn post<T: DeserializeOwned, D: Serialize>(
         &self,
         endpoint: &str,
@@ -98,144 +263,588 @@ impl HttpClient {
             .json(data)
             .build()?;
 
-        let response = self.client.execute(request).await?;
+        let response = self.execute(request).await?;
         self.handle_response(response).await
     }
 
-    /// Make a PUT request to upload data to a URL (used for presigned URLs)
-    pub async fn put(&self, url: &str, data: Vec<u8>, content_type: &str) -> Result<()> {
-        let request = self
-            .client
-            .put(url)
-            .header("Content-Type", content_type)
-            .header("Content-Length", data.len().to_string())
-            // Do not include X-API-KEY for presigned URL uploads
-            .body(data.clone())
-            .build()?;
+    /// Make a PUT request to upload data to a URL (used for presigned URLs),
+    /// retrying it according to `policy`
+    pub async fn put(
+        &self,
+        url: &str,
+        data: &UploadBody,
+        content_type: &str,
+        progress: Option<&ProgressCallback>,
+        policy: Option<&RetryPolicy>,
+    ) -> Result<PresignedPut> {
+        // Retrying with an expired URL is pointless, a new one is needed
+        if presigned_url_expired(url) {
+            return Ok(PresignedPut::Expired);
+        }
 
-        let response = self.client.execute(request).await?;
+        let build = || -> Result<Request> {
+            Ok(self
+                .client
+                .put(url)
+                .header("Content-Type", content_type)
+                .header("Content-Length", data.len().to_string())
+                // Do not include X-API-KEY for presigned URL uploads
+                .body(data.to_body(progress)?)
+                .build()?)
+        };
+        let response = if data.is_replayable() {
+            self.execute_with_policy(policy, build).await?
+        } else {
+            self.execute(build()?).await?
+        };
         let status = response.status();
 
         // Check if the upload was successful
+        if status == StatusCode::TOO_MANY_REQUESTS {
+            return Err(Error::RateLimited {
+                retry_after: retry_after(&response),
+            });
+        }
+
         if !status.is_success() {
             let body = response.text().await?;
+
+            // Storage rejects expired presigned URLs with "Request has expired"
+            if status == StatusCode::FORBIDDEN && body.contains("expired") {
+                return Ok(PresignedPut::Expired);
+            }
+
             return Err(Error::UploadFailed(format!(
                 "Failed to upload to presigned URL. Status: {status} Body: {body}"
             )));
         }
 
-        Ok(())
+        let etag = response
+            .headers()
+            .get("ETag")
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_string);
+        Ok(PresignedPut::Uploaded { etag })
+    }
+
+    /// Execute a single request once the rate limiter allows it
+    async fn execute(&self, request: Request) -> reqwest::Result<Response> {
+        if let Some(rate_limiter) = &self.rate_limiter {
+            rate_limiter.acquire().await;
+        }
+        self.client.execute(request).await
+    }
+
+    /// Execute a request, retrying it according to the configured retry policy.
+    /// `build` is called for every attempt so each one sends a fresh request.
+    async fn execute_with_retry<F, E>(&self, build: F) -> Result<Response>
+    where
+        F: Fn() -> std::result::Result<Request, E>,
+        Error: From<E>,
+    {
+        self.execute_with_policy(self.config.retry_policy.as_ref(), build)
+            .await
     }
 
-    /// Upload a file using the presigned URL flow
-    pub async fn upload_file<T: DeserializeOwned>(&self, file_path: &str) -> Result<T> {
-        // 1. Get file name
+    /// Execute a request, retrying it according to `policy`, or only once
+    /// without a policy
+    async fn execute_with_policy<F, E>(
+        &self,
+        policy: Option<&RetryPolicy>,
+        build: F,
+    ) -> Result<Response>
+    where
+        F: Fn() -> std::result::Result<Request, E>,
+        Error: From<E>,
+    {
+        let policy = match policy {
+            Some(policy) => policy,
+            None => return Ok(self.execute(build()?).await?),
+        };
+
+        let mut retry = 0;
+        loop {
+            let outcome = self.execute(build()?).await;
+
+            let should_retry = retry < policy.max_retries
+                && match &outcome {
+                    Ok(response) => policy.retries_status(response.status().as_u16()),
+                    Err(err) => policy.retries_error(err),
+                };
+            if !should_retry {
+                return Ok(outcome?);
+            }
+
+            // Throttled requests wait as long as the API asks them to, unless
+            // that is longer than the policy allows, in which case the caller
+            // gets `Error::RateLimited` with the advertised delay
+            let retry_after = outcome
+                .as_ref()
+                .ok()
+                .filter(|response| response.status() == StatusCode::TOO_MANY_REQUESTS)
+                .and_then(retry_after);
+            let delay = match retry_after {
+                Some(retry_after) if retry_after > policy.max_delay => return Ok(outcome?),
+                Some(retry_after) => retry_after,
+                None => policy.delay(retry),
+            };
+            sleep(delay).await;
+            retry += 1;
+        }
+    }
+
+    /// Upload a file using the presigned URL flow, reporting the progress of
+    /// the upload to `progress`
+    pub async fn upload_file<T: DeserializeOwned>(
+        &self,
+        file_path: &str,
+        progress: Option<ProgressCallback>,
+    ) -> Result<T> {
+        // 1. Validate the file, converting images the API would not accept
         let path = Path::new(file_path);
-        if !path.exists() {
-            return Err(Error::InvalidFile(format!("File not found: {file_path}")));
+        if let Some(normalization) = &self.config.image_normalization {
+            let file_size = tokio::fs::metadata(path)
+                .await
+                .map(|metadata| metadata.len());
+            if let Some(size) = file_size
+                .ok()
+                .filter(|&size| normalization.applies_to(file_path, size))
+            {
+                normalization.check_size(file_path, size)?;
+                let content = Bytes::from(tokio::fs::read(path).await?);
+                let upload_result = self
+                    .upload_normalized(normalization, file_path, content, progress)
+                    .await?;
+                return Ok(serde_json::from_value(serde_json::to_value(
+                    upload_result,
+                )?)?);
+            }
+        }
+        let (file_size, content_type, head) = self.validate_file(file_path).await?;
+        if self.config.check_upload_structure {
+            let mime_type = inspected_type(file_path, &head, content_type);
+            inspect_path(
+                path.to_path_buf(),
+                file_size,
+                mime_type,
+                file_path.to_string(),
+            )
+            .await?;
         }
 
-        let file_extension = path
-            .extension()
+        let file_name = path
+            .file_name()
+            .and_then(|n| n.to_str())
             .ok_or_else(|| Error::InvalidFile("Invalid file name".to_string()))?;
-        let supported_file_type = SUPPORTED_FILE_TYPES
-            .iter()
-            .find(|x| x.extensions.contains(&file_extension.to_str().unwrap()));
 
-        if supported_file_type.is_none() {
-            return Err(Error::InvalidFile(format!(
-                "Unsupported file type: {file_path}"
-            )));
+        // 2. Upload through a presigned URL, streaming the file from disk
+        let file_content = UploadBody::File {
+            path: path.to_path_buf(),
+            offset: 0,
+            len: file_size,
+        };
+        let upload_result = self
+            .upload_to_signed_url(file_name, file_content, content_type, progress)
+            .await?;
+
+        // 3. Convert to the requested type
+        Ok(serde_json::from_value(serde_json::to_value(
+            upload_result,
+        )?)?)
+    }
+
+    /// Validate a file like an upload and inspect its content locally
+    pub async fn inspect_file(&self, file_path: &str) -> Result<MediaInfo> {
+        let (file_size, content_type, head) = self.validate_file(file_path).await?;
+        let mime_type = inspected_type(file_path, &head, content_type);
+        inspect_path(
+            PathBuf::from(file_path),
+            file_size,
+            mime_type,
+            file_path.to_string(),
+        )
+        .await
+    }
+
+    /// Check a file on disk against the supported file types and the content
+    /// type policy, returning its size, its content type and its first bytes
+    async fn validate_file(&self, file_path: &str) -> Result<(u64, &'static str, Vec<u8>)> {
+        let path = Path::new(file_path);
+        if !path.exists() {
+            return Err(Error::InvalidFile(format!("File not found: {file_path}")));
         }
 
+        // Only the first bytes are read to recognise the content
+        let mut head = Vec::with_capacity(SNIFF_LEN);
+        tokio::fs::File::open(path)
+            .await?
+            .take(SNIFF_LEN as u64)
+            .read_to_end(&mut head)
+            .await?;
+
         let file_size = path.metadata()?.len();
-        if file_size > supported_file_type.unwrap().size_limit {
-            return Err(Error::InvalidFile(format!("File too large: {file_path}")));
+        let content_type =
+            validate_upload(file_path, file_size, &head, self.config.content_type_policy)?;
+        Ok((file_size, content_type, head))
+    }
+
+    /// Upload in-memory content using the presigned URL flow. The file name
+    /// determines the file type, exactly like the extension of a file on disk.
+    pub async fn upload_bytes(&self, file_name: &str, content: Bytes) -> Result<UploadResult> {
+        if let Some(normalization) = &self.config.image_normalization {
+            if normalization.applies_to(file_name, content.len() as u64) {
+                return self
+                    .upload_normalized(normalization, file_name, content, None)
+                    .await;
+            }
+        }
+
+        let content_type = validate_upload(
+            file_name,
+            content.len() as u64,
+            &content[..content.len().min(SNIFF_LEN)],
+            self.config.content_type_policy,
+        )?;
+        if self.config.check_upload_structure {
+            let head = &content[..content.len().min(SNIFF_LEN)];
+            let mime_type = inspected_type(file_name, head, content_type);
+            inspect_media(
+                Cursor::new(&content[..]),
+                content.len() as u64,
+                mime_type,
+                file_name,
+            )?;
         }
 
+        let path = Path::new(file_name);
         let file_name = path
             .file_name()
             .and_then(|n| n.to_str())
             .ok_or_else(|| Error::InvalidFile("Invalid file name".to_string()))?;
 
-        let payload = serde_json::json!({ "fileName": file_name });
+        self.upload_to_signed_url(file_name, UploadBody::Bytes(content), content_type, None)
+            .await
+    }
 
-        // 2. Request a presigned URL
-        let signed_url_response = self
-            .post::<crate::models::SignedUrlResponse, _>(api_paths::SIGNED_URL, &payload)
+    /// Convert an image to PNG and upload it, recording the conversion on the
+    /// upload result
+    async fn upload_normalized(
+        &self,
+        normalization: &ImageNormalization,
+        file_name: &str,
+        content: Bytes,
+        progress: Option<ProgressCallback>,
+    ) -> Result<UploadResult> {
+        let normalization = normalization.clone();
+        let original_name = file_name.to_string();
+        let content_type = normalization.format.mime_type();
+        let (file_name, image, record) = tokio::task::spawn_blocking(move || {
+            normalize_image(&normalization, &original_name, &content)
+        })
+        .await
+        .map_err(|err| Error::UnknownError(format!("Image normalization failed: {err}")))??;
+
+        let mut upload_result = self
+            .upload_to_signed_url(
+                &file_name,
+                UploadBody::Bytes(Bytes::from(image)),
+                content_type,
+                progress,
+            )
             .await?;
+        upload_result.normalization = Some(record);
+        Ok(upload_result)
+    }
 
-        // 3. Read the file content
-        let file_content = tokio::fs::read(path).await?;
+    /// Download media from a direct URL, returning its file name and content
+    /// to upload exactly like in-memory content
+    pub(crate) async fn fetch_from_url(
+        &self,
+        url: &str,
+        options: &FetchOptions,
+    ) -> Result<(String, Bytes)> {
+        let timeout = options
+            .timeout
+            .unwrap_or_else(|| Duration::from_secs(self.config.get_timeout_seconds()));
+        fetch_media(url, options, timeout).await
+    }
 
-        // Check if file is empty or if there's an issue reading it
-        if file_content.is_empty() {
-            // Try with std::fs to see if it reads the file correctly
-            let std_file_content = std::fs::read(path)?;
+    /// Upload `len` bytes read from `reader` using the presigned URL flow.
+    /// The upload is validated before more than its first bytes are read,
+    /// then the rest is streamed to the presigned URL as it is read, with a
+    /// single attempt. Content that is normalized or has its structure
+    /// checked is read into memory first, since both need all of it.
+    pub async fn upload_reader<R>(
+        &self,
+        file_name: &str,
+        mut reader: R,
+        len: u64,
+    ) -> Result<UploadResult>
+    where
+        R: AsyncRead + Unpin,
+    {
+        let (head, content_type) = self.read_head(file_name, &mut reader, len).await?;
+
+        let normalized = self
+            .config
+            .image_normalization
+            .as_ref()
+            .is_some_and(|normalization| normalization.applies_to(file_name, len));
+        if normalized || self.config.check_upload_structure {
+            let content = read_rest(head, reader, len).await?;
+            return self.upload_bytes(file_name, content).await;
+        }
 
-            if std_file_content.is_empty() {
-                return Err(Error::InvalidFile(format!("File is empty: {file_path}")));
-            }
+        let file_name = Path::new(file_name)
+            .file_name()
+            .and_then(|n| n.to_str())
+            .ok_or_else(|| Error::InvalidFile("Invalid file name".to_string()))?;
 
-            // Use the content read by std::fs instead
-            return self
-                .upload_file_with_content(signed_url_response, std_file_content, path)
-                .await;
+        let (sender, receiver) = mpsc::channel(STREAMED_CHUNKS);
+        let content = UploadBody::Stream {
+            chunks: Arc::new(Mutex::new(Some(receiver))),
+            len,
+        };
+        let (sent, uploaded) = tokio::join!(
+            send_reader(head, reader, len, sender),
+            self.upload_to_signed_url(file_name, content, content_type, None)
+        );
+
+        // A reader of the wrong length aborts the upload, so its error is the
+        // one worth reporting
+        sent?;
+        uploaded
+    }
+
+    /// Read all `len` bytes of `reader` into memory, validating the upload
+    /// before more than its first bytes are read
+    pub(crate) async fn read_reader<R>(
+        &self,
+        file_name: &str,
+        mut reader: R,
+        len: u64,
+    ) -> Result<Bytes>
+    where
+        R: AsyncRead + Unpin,
+    {
+        let (head, _) = self.read_head(file_name, &mut reader, len).await?;
+        read_rest(head, reader, len).await
+    }
+
+    /// Read the first bytes of a reader and validate the upload against them,
+    /// returning the bytes read and the content type
+    async fn read_head<R>(
+        &self,
+        file_name: &str,
+        reader: &mut R,
+        len: u64,
+    ) -> Result<(Vec<u8>, &'static str)>
+    where
+        R: AsyncRead + Unpin,
+    {
+        let mut head = Vec::with_capacity(SNIFF_LEN);
+        reader.take(SNIFF_LEN as u64).read_to_end(&mut head).await?;
+        let content_type = validate_upload(file_name, len, &head, self.config.content_type_policy)?;
+
+        // A reader that ended within its first bytes is checked right away
+        let ended = head.len() < SNIFF_LEN;
+        if head.len() as u64 > len || (ended && head.len() as u64 != len) {
+            return Err(reader_length_error(len));
         }
 
-        // 4. Determine content type based on file extension
-        let content_type = determine_content_type(path);
+        Ok((head, content_type))
+    }
 
-        // 5. Upload to the presigned URL
-        self.put(
-            &signed_url_response.response.signed_url,
-            file_content,
-            content_type,
-        )
-        .await?;
+    /// Request a presigned URL and upload content to it. If the URL expires
+    /// before the upload succeeds, a new one is requested once.
+    ///
+    /// Content at least as large as the multipart threshold is uploaded in
+    /// parts when the API offers it, and with a single request otherwise.
+    async fn upload_to_signed_url(
+        &self,
+        file_name: &str,
+        file_content: UploadBody,
+        content_type: &str,
+        progress: Option<ProgressCallback>,
+    ) -> Result<UploadResult> {
+        #[cfg(feature = "experimental-multipart")]
+        let payload = match self.config.multipart_upload {
+            Some(multipart)
+                if file_content.is_replayable() && file_content.len() >= multipart.threshold =>
+            {
+                serde_json::json!({
+                "fileName": file_name,
+                "fileSize": file_content.len(),
+                "partSize": multipart.part_size,
+                })
+            }
+            _ => serde_json::json!({ "fileName": file_name }),
+        };
+        #[cfg(not(feature = "experimental-multipart"))]
+        let payload = serde_json::json!({ "fileName": file_name });
+
+        let mut signed_url_response = self
+            .post::<SignedUrlResponse, _>(api_paths::SIGNED_URL, &payload)
+            .await?;
+
+        if let Some(multipart) = &signed_url_response.response.multipart {
+            self.upload_parts(multipart, &file_content, content_type, progress.as_ref())
+                .await?;
+        } else {
+            let mut refreshed = false;
+
+            while let PresignedPut::Expired = self
+                .put(
+                    &signed_url_response.response.signed_url,
+                    &file_content,
+                    content_type,
+                    progress.as_ref(),
+                    self.config.retry_policy.as_ref(),
+                )
+                .await?
+            {
+                if refreshed {
+                    return Err(Error::UploadFailed(
+                        "Presigned URL expired before the upload completed".to_string(),
+                    ));
+                }
+
+                signed_url_response = self
+                    .post::<SignedUrlResponse, _>(api_paths::SIGNED_URL, &payload)
+                    .await?;
+                refreshed = true;
+            }
+        }
 
-        // 6. Create upload result with request_id and media_id
-        let upload_result = UploadResult {
+        Ok(UploadResult {
             request_id: signed_url_response.request_id,
             media_id: Option::from(signed_url_response.media_id),
             result_url: None,
-        };
+            normalization: None,
+        })
+    }
 
-        // 7. Convert to the requested type
-        Ok(serde_json::from_value(serde_json::to_value(
-            upload_result,
-        )?)?)
+    /// Upload content in parts to the presigned URLs of a multipart upload,
+    /// then complete it. Each part is retried on its own, so the upload
+    /// resumes from the last confirmed part instead of starting over, within
+    /// this call only. Parts
+    /// are retried under the default retry policy when none is configured,
+    /// since a single transient failure would otherwise lose the whole upload.
+    async fn upload_parts(
+        &self,
+        multipart: &MultipartDetails,
+        content: &UploadBody,
+        content_type: &str,
+        progress: Option<&ProgressCallback>,
+    ) -> Result<()> {
+        if !content.is_replayable() {
+            return Err(Error::UploadFailed(format!(
+                "Multipart upload {} cannot split content streamed from a reader",
+                multipart.upload_id
+            )));
+        }
+
+        let total_bytes = content.len();
+        if multipart.part_size == 0
+            || multipart.part_urls.len() as u64 != total_bytes.div_ceil(multipart.part_size)
+        {
+            return Err(Error::UploadFailed(format!(
+                "Multipart upload {} has {} parts of {} bytes for {total_bytes} bytes",
+                multipart.upload_id,
+                multipart.part_urls.len(),
+                multipart.part_size
+            )));
+        }
+
+        let policy = self.config.retry_policy.clone().unwrap_or_default();
+        let mut etags = Vec::with_capacity(multipart.part_urls.len());
+        for (index, url) in multipart.part_urls.iter().enumerate() {
+            let part_number = index + 1;
+            let offset = index as u64 * multipart.part_size;
+            let part = content.part(offset, multipart.part_size.min(total_bytes - offset));
+
+            // Progress covers the whole content, not only the current part
+            let part_progress = progress.map(|progress| {
+                let progress = progress.clone();
+                Arc::new(move |part: UploadProgress| {
+                    progress(UploadProgress {
+                        bytes_sent: offset + part.bytes_sent,
+                        total_bytes,
+                    })
+                }) as ProgressCallback
+            });
+
+            match self
+                .put(
+                    url,
+                    &part,
+                    content_type,
+                    part_progress.as_ref(),
+                    Some(&policy),
+                )
+                .await?
+            {
+                PresignedPut::Uploaded { etag: Some(etag) } => etags.push(etag),
+                PresignedPut::Uploaded { etag: None } => {
+                    return Err(Error::UploadFailed(format!(
+                        "Part {part_number} of multipart upload {} returned no ETag",
+                        multipart.upload_id
+                    )))
+                }
+                PresignedPut::Expired => {
+                    return Err(Error::UploadFailed(format!(
+                        "Presigned URL expired before part {part_number} of multipart upload {} completed",
+                        multipart.upload_id
+                    )))
+                }
+            }
+        }
+
+        self.complete_multipart_upload(multipart, &etags, &policy)
+            .await
     }
 
-    /// Helper method to upload file with provided content
-    async fn upload_file_with_content<T: DeserializeOwned>(
+    /// Complete a multipart upload from the ETags of its parts. Completing an
+    /// upload is idempotent, so unlike API POST requests it is retried.
+    async fn complete_multipart_upload(
         &self,
-        signed_url_response: crate::models::SignedUrlResponse,
-        file_content: Vec<u8>,
-        path: &Path,
-    ) -> Result<T> {
-        // Determine content type based on file extension
-        let content_type = determine_content_type(path);
-
-        // Upload to the presigned URL
-        self.put(
-            &signed_url_response.response.signed_url,
-            file_content,
-            content_type,
-        )
-        .await?;
+        multipart: &MultipartDetails,
+        etags: &[String],
+        policy: &RetryPolicy,
+    ) -> Result<()> {
+        let parts: String = etags
+            .iter()
+            .enumerate()
+            .map(|(index, etag)| {
+                format!(
+                    "<Part><PartNumber>{}</PartNumber><ETag>{etag}</ETag></Part>",
+                    index + 1
+                )
+            })
+            .collect();
+        let body = format!("<CompleteMultipartUpload>{parts}</CompleteMultipartUpload>");
+
+        let response = self
+            .execute_with_policy(Some(policy), || {
+                self.client
+                    .post(&multipart.complete_url)
+                    .header("Content-Type", "application/xml")
+                    .body(body.clone())
+                    .build()
+            })
+            .await?;
+        let status = response.status();
+        let body = response.text().await?;
 
-        // Create upload result with request_id and media_id
-        let upload_result = UploadResult {
-            request_id: signed_url_response.request_id,
-            media_id: Option::from(signed_url_response.media_id),
-            result_url: None,
-        };
+        // Storage can report a failed completion in the body of a 200 response
+        if !status.is_success() || body.contains("<Error>") {
+            return Err(Error::UploadFailed(format!(
+                "Failed to complete multipart upload {}. Status: {status} Body: {body}",
+                multipart.upload_id
+            )));
+        }
 
-        // Convert to the requested type
-        Ok(serde_json::from_value(serde_json::to_value(
-            upload_result,
-        )?)?)
+        Ok(())
     }
 
     pub async fn upload_social_media_link(&self, social_media_link: &str) -> Result<UploadResult> {
@@ -257,64 +866,216 @@ impl HttpClient {
             request_id: response.request_id.unwrap(),
             media_id: None,
             result_url: None,
+            normalization: None,
         })
     }
 
     /// Handle API responses and parse JSON
     async fn handle_response<T: DeserializeOwned>(&self, response: Response) -> Result<T> {
         let status = response.status();
+        let retry_after = retry_after(&response);
         let body = response.bytes().await?;
 
         if status == StatusCode::OK || status == StatusCode::CREATED {
             return Ok(serde_json::from_slice(&body)?);
         }
 
-        let response: BaseResponse =
-            if let Ok(base_response) = serde_json::from_slice::<BaseResponse>(&body) {
-                base_response
-            } else {
-                BaseResponse {
-                    code: "UNKNOWN".to_string(),
-                    response: format!("Unknown error (HTTP {status})"),
-                    errno: -1,
-                    ..Default::default()
-                }
-            };
+        let error = match serde_json::from_slice::<BaseResponse>(&body) {
+            Ok(response) => ApiError {
+                http_status: status.as_u16(),
+                code: response.code,
+                errno: response.errno,
+                message: response.response,
+                request_id: response.request_id,
+            },
+            Err(_) => ApiError {
+                http_status: status.as_u16(),
+                code: "UNKNOWN".to_string(),
+                errno: -1,
+                message: if status == StatusCode::UNAUTHORIZED {
+                    "Invalid API key".to_string()
+                } else {
+                    format!("Unknown error (HTTP {status})")
+                },
+                request_id: None,
+            },
+        };
+
+        // Account restrictions are identified by their code, whatever the status
+        match error.code.as_str() {
+            "free-tier-not-allowed" => return Err(Error::FreeTierNotAllowed(error)),
+            "upload-limit-reached" => return Err(Error::QuotaExceeded(error)),
+            _ => {}
+        }
 
         match status {
-            StatusCode::BAD_REQUEST => {
-                if response.code == "free-tier-not-allowed"
-                    || response.code == "upload-limit-reached"
-                {
-                    Err(Error::Unauthorized(response.response))
-                } else {
-                    Err(Error::InvalidRequest(response.response))
-                }
+            StatusCode::BAD_REQUEST => Err(Error::BadRequest(error)),
+            StatusCode::UNAUTHORIZED => Err(Error::Unauthorized(error)),
+            StatusCode::FORBIDDEN => Err(Error::Forbidden(error)),
+            StatusCode::NOT_FOUND => Err(Error::NotFound(error)),
+            StatusCode::TOO_MANY_REQUESTS => Err(Error::RateLimited { retry_after }),
+            _ => Err(Error::ServerError(error)),
+        }
+    }
+}
+
+/// MIME type to inspect an upload as: its content type, unless the content
+/// is recognised as another type its extension can hold
+fn inspected_type(file_name: &str, head: &[u8], content_type: &'static str) -> &'static str {
+    sniff_content(head)
+        .filter(|mime_type| extension_matches(Path::new(file_name), mime_type))
+        .unwrap_or(content_type)
+}
+
+/// Check an upload against the supported file types and the content type
+/// policy, returning the content type to label it with. `head` holds the
+/// first bytes of the content.
+fn validate_upload(
+    file_name: &str,
+    size: u64,
+    head: &[u8],
+    policy: ContentTypePolicy,
+) -> Result<&'static str> {
+    let path = Path::new(file_name);
+    if path.extension().is_none() {
+        return Err(Error::InvalidFile("Invalid file name".to_string()));
+    }
+
+    let extension_media_type = find_media_type(path)
+        .ok_or_else(|| Error::InvalidFile(format!("Unsupported file type: {file_name}")))?;
+
+    if size == 0 {
+        return Err(Error::InvalidFile(format!("File is empty: {file_name}")));
+    }
+
+    let sniffed_mime_type = match policy {
+        ContentTypePolicy::TrustExtension => None,
+        ContentTypePolicy::TrustContent => sniff_content(head),
+        ContentTypePolicy::RejectMismatch => match sniff_content(head) {
+            Some(mime_type) if extension_matches(path, mime_type) => Some(mime_type),
+            _ => {
+                return Err(Error::InvalidFile(format!(
+                    "Content does not match the file extension: {file_name}"
+                )))
+            }
+        },
+    };
+
+    // Recognised content decides both the size limit and the content type
+    let (size_limit, content_type) = match sniffed_mime_type {
+        Some(mime_type) => (
+            find_media_type_by_mime(mime_type)
+                .unwrap_or(extension_media_type)
+                .size_limit,
+            mime_type,
+        ),
+        None => (
+            extension_media_type.size_limit,
+            determine_content_type(path),
+        ),
+    };
+
+    if size > size_limit {
+        return Err(Error::InvalidFile(format!("File too large: {file_name}")));
+    }
+
+    Ok(content_type)
+}
+
+/// Error for a reader that did not return the length it announced
+fn reader_length_error(len: u64) -> Error {
+    Error::InvalidFile(format!("Reader did not return the expected {len} bytes"))
+}
+
+/// Read the rest of a reader after its first bytes, checking that it holds
+/// exactly `len` bytes
+async fn read_rest<R>(head: Vec<u8>, reader: R, len: u64) -> Result<Bytes>
+where
+    R: AsyncRead + Unpin,
+{
+    let mut content = head;
+    content.reserve(len.saturating_sub(content.len() as u64) as usize);
+    // Read one extra byte to detect readers longer than announced
+    let remaining = (len + 1).saturating_sub(content.len() as u64);
+    reader.take(remaining).read_to_end(&mut content).await?;
+    if content.len() as u64 != len {
+        return Err(reader_length_error(len));
+    }
+    Ok(Bytes::from(content))
+}
+
+/// Send the first bytes of a reader then the rest of its `len` bytes to an
+/// upload in progress. A reader of the wrong length sends an error, which
+/// aborts the upload instead of letting it complete with other content.
+async fn send_reader<R>(
+    head: Vec<u8>,
+    reader: R,
+    len: u64,
+    sender: mpsc::Sender<std::io::Result<Bytes>>,
+) -> Result<()>
+where
+    R: AsyncRead + Unpin,
+{
+    // The upload stops reading once it failed, and its error is reported
+    let mut sent = head.len() as u64;
+    if sender.send(Ok(Bytes::from(head))).await.is_err() {
+        return Ok(());
+    }
+
+    // Read one extra byte to detect readers longer than announced
+    let remaining = (len + 1).saturating_sub(sent);
+    let mut chunks = ReaderStream::with_capacity(reader.take(remaining), PROGRESS_CHUNK_SIZE);
+    while let Some(chunk) = chunks.next().await {
+        let chunk = match chunk {
+            Ok(chunk) => chunk,
+            Err(err) => {
+                let _ = sender
+                    .send(Err(std::io::Error::new(err.kind(), err.to_string())))
+                    .await;
+                return Err(err.into());
             }
-            StatusCode::UNAUTHORIZED => Err(Error::Unauthorized("Invalid API key".to_string())),
-            StatusCode::NOT_FOUND => Err(Error::NotFound),
-            _ => Err(Error::ServerError(response.response)),
+        };
+        sent += chunk.len() as u64;
+        if sent > len {
+            break;
+        }
+        if sender.send(Ok(chunk)).await.is_err() {
+            return Ok(());
         }
     }
+
+    if sent != len {
+        let _ = sender
+            .send(Err(std::io::Error::other("Reader length mismatch")))
+            .await;
+        return Err(reader_length_error(len));
+    }
+    Ok(())
+}
+
+/// Delay advertised by the `Retry-After` header of a response
+fn retry_after(response: &Response) -> Option<Duration> {
+    response
+        .headers()
+        .get(reqwest::header::RETRY_AFTER)
+        .and_then(|value| value.to_str().ok())
+        .and_then(parse_retry_after)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::{Client, UploadOptions};
+    #[cfg(feature = "experimental-multipart")]
+    use crate::config::MultipartUpload;
+    use crate::config::RetryPolicy;
+    use crate::normalize::NormalizedFormat;
+    use crate::{Client, MediaDetails, UploadOptions};
     use mockito::Matcher;
-    use serde::Deserialize;
     use serde_json::json;
     use std::fs::File;
     use std::io::Write;
     use tempfile::tempdir;
 
-    #[derive(Debug, Serialize, Deserialize)]
-    struct TestResponse {
-        status: String,
-        message: String,
-    }
-
     #[tokio::test]
     async fn test_client_new() {
         // Valid configuration
@@ -396,7 +1157,7 @@ mod tests {
         // Verify error
         assert!(result.is_err());
         match result.unwrap_err() {
-            Error::NotFound => {} // Expected error
+            Error::NotFound(error) => assert_eq!(error.http_status, 404),
             err => panic!("Unexpected error: {:?}", err),
         }
     }
@@ -436,11 +1197,15 @@ mod tests {
             .await;
         println!("Mocked presigned URL endpoint");
 
-        // Mock the upload endpoint
+        // Mock the upload endpoint. The file is streamed with a known length,
+        // not with chunked transfer encoding.
         let _m2 = server
             .mock("PUT", "/upload")
             .with_status(200)
             .match_header("content-type", "image/jpeg")
+            .match_header("content-length", "15")
+            .match_header("transfer-encoding", Matcher::Missing)
+            .match_body("test image data")
             .create_async()
             .await;
         println!("Mocked upload endpoint");
@@ -475,25 +1240,89 @@ mod tests {
     }
 
     #[tokio::test]
-    async fn test_http_post_request() {
+    async fn test_upload_reports_progress() {
         let mut server = mockito::Server::new_async().await;
 
-        // Setup mock server for POST request
-        let _m = server
-            .mock("POST", "/api/test-endpoint")
+        let dir = tempdir().unwrap();
+        let file_path = dir.path().join("large.jpg");
+        std::fs::write(&file_path, vec![0u8; 200_000]).unwrap();
+
+        let _m1 = server
+            .mock("POST", "/api/files/aws-presigned")
             .with_status(200)
             .with_header("content-type", "application/json")
-            .match_header("X-API-KEY", "test_api_key")
-            .match_header("Content-Type", "application/json")
-            .match_body(Matcher::Json(json!({"key": "value"})))
-            .with_body(r#"{"status": "success", "message": "Test completed"}"#)
+            .with_body(
+                json!({
+                    "code": "success",
+                    "errno": 0,
+                    "requestId": "test-request-id",
+                    "mediaId": "test-media-id",
+                    "response": {
+                        "signedUrl": format!("{}/upload", server.url())
+                    }
+                })
+                .to_string(),
+            )
             .create_async()
             .await;
 
-        // Create client with mock server URL
-        let config = Config {
-            api_key: "test_api_key".to_string(),
-            base_url: Some(server.url()),
+        // Progress reporting keeps the known length of the upload
+        let mock_put = server
+            .mock("PUT", "/upload")
+            .match_header("content-length", "200000")
+            .with_status(200)
+            .expect(1)
+            .create_async()
+            .await;
+
+        let client = Client::new(Config {
+            api_key: "test_api_key".to_string(),
+            base_url: Some(server.url()),
+            ..Default::default()
+        })
+        .unwrap();
+
+        let (progress, progress_rx) = tokio::sync::watch::channel(UploadProgress::default());
+        let result = client
+            .upload_with_progress(
+                UploadOptions {
+                    file_path: file_path.to_str().unwrap().to_string(),
+                },
+                progress,
+            )
+            .await;
+
+        assert!(result.is_ok(), "Unexpected error: {:?}", result.err());
+        assert_eq!(
+            *progress_rx.borrow(),
+            UploadProgress {
+                bytes_sent: 200_000,
+                total_bytes: 200_000,
+            }
+        );
+        mock_put.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_http_post_request() {
+        let mut server = mockito::Server::new_async().await;
+
+        // Setup mock server for POST request
+        let _m = server
+            .mock("POST", "/api/test-endpoint")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .match_header("X-API-KEY", "test_api_key")
+            .match_header("Content-Type", "application/json")
+            .match_body(Matcher::Json(json!({"key": "value"})))
+            .with_body(r#"{"status": "success", "message": "Test completed"}"#)
+            .create_async()
+            .await;
+
+        // Create client with mock server URL
+        let config = Config {
+            api_key: "test_api_key".to_string(),
+            base_url: Some(server.url()),
             ..Default::default()
         };
         let client = Client::new(config).unwrap();
@@ -606,7 +1435,7 @@ mod tests {
         // Verify error
         assert!(result.is_err());
         match result.unwrap_err() {
-            Error::ServerError(..) => {} // Expected error (403 maps to Unauthorized)
+            Error::Forbidden(error) => assert_eq!(error.http_status, 403),
             err => panic!("Unexpected error: {:?}", err),
         }
     }
@@ -638,8 +1467,9 @@ mod tests {
         // Verify error
         assert!(result.is_err());
         match result.unwrap_err() {
-            Error::InvalidRequest(msg) => {
-                assert_eq!(msg, "Custom error message");
+            Error::BadRequest(error) => {
+                assert_eq!(error.message, "Custom error message");
+                assert_eq!(error.code, "custom-code");
             }
             err => panic!("Unexpected error: {:?}", err),
         }
@@ -659,8 +1489,12 @@ mod tests {
         // Verify error
         assert!(result.is_err());
         match result.unwrap_err() {
-            Error::ServerError(msg) => {
-                assert_eq!(msg, "Unknown error (HTTP 422 Unprocessable Entity)");
+            Error::ServerError(error) => {
+                assert_eq!(
+                    error.message,
+                    "Unknown error (HTTP 422 Unprocessable Entity)"
+                );
+                assert_eq!(error.http_status, 422);
             }
             err => panic!("Unexpected error: {:?}", err),
         }
@@ -1017,216 +1851,690 @@ mod tests {
     }
 
     #[tokio::test]
-    async fn test_upload_social_media_link_success() {
+    async fn test_upload_bytes() {
         let mut server = mockito::Server::new_async().await;
 
-        // Mock the social media upload endpoint
-        let _m = server
-            .mock("POST", "/api/files/social")
+        let mock_presigned = server
+            .mock("POST", "/api/files/aws-presigned")
             .with_status(200)
             .with_header("content-type", "application/json")
-            .match_header("X-API-KEY", "test_api_key")
-            .match_body(Matcher::Json(json!({
-                "socialLink": "https://www.youtube.com/watch?v=dQw4w9WgXcQ"
-            })))
+            .match_body(Matcher::Json(json!({"fileName": "frame.png"})))
             .with_body(
                 json!({
                     "code": "success",
                     "errno": 0,
-                    "response": "",
-                    "requestId": "social-request-id",
+                    "requestId": "bytes-request-id",
+                    "mediaId": "bytes-media-id",
+                    "response": {
+                        "signedUrl": format!("{}/upload", server.url())
+                    }
                 })
                 .to_string(),
             )
+            .expect(1)
             .create_async()
             .await;
 
-        let config = Config {
+        let mock_put = server
+            .mock("PUT", "/upload")
+            .match_header("Content-Type", "image/png")
+            .match_body("in-memory png data")
+            .with_status(200)
+            .expect(1)
+            .create_async()
+            .await;
+
+        let client = Client::new(Config {
             api_key: "test_api_key".to_string(),
             base_url: Some(server.url()),
             ..Default::default()
-        };
-        let client = Client::new(config).unwrap();
+        })
+        .unwrap();
 
         let result = client
-            .upload_social_media("https://www.youtube.com/watch?v=dQw4w9WgXcQ")
-            .await;
+            .upload_bytes("frame.png", Bytes::from_static(b"in-memory png data"))
+            .await
+            .unwrap();
 
-        println!("{:?}", result);
-        assert!(result.is_ok());
-        let upload_result = result.unwrap();
-        assert_eq!(upload_result.request_id, "social-request-id");
+        assert_eq!(result.request_id, "bytes-request-id");
+        mock_presigned.assert_async().await;
+        mock_put.assert_async().await;
     }
 
     #[tokio::test]
-    async fn test_upload_social_media_link_invalid_url() {
-        let config = Config {
-            api_key: "test_api_key".to_string(),
-            ..Default::default()
-        };
-        let client = Client::new(config).unwrap();
+    async fn test_upload_from_url() {
+        let mut server = mockito::Server::new_async().await;
 
-        // Test with invalid URL (no scheme)
-        let result = client.upload_social_media("www.example.com").await;
+        let mock_media = server
+            .mock("GET", "/cdn/photo.jpg")
+            .with_status(200)
+            .with_header("content-type", "image/jpeg")
+            .with_body("downloaded jpeg data")
+            .expect(1)
+            .create_async()
+            .await;
 
-        assert!(result.is_err());
-        match result.unwrap_err() {
-            Error::InvalidRequest(msg) => {
-                assert!(msg.contains("Invalid URL"));
-            }
-            err => panic!("Expected InvalidRequest error, got: {:?}", err),
-        }
-    }
+        let mock_presigned = server
+            .mock("POST", "/api/files/aws-presigned")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .match_body(Matcher::Json(json!({"fileName": "photo.jpg"})))
+            .with_body(
+                json!({
+                    "code": "success",
+                    "errno": 0,
+                    "requestId": "url-request-id",
+                    "mediaId": "url-media-id",
+                    "response": {
+                        "signedUrl": format!("{}/upload", server.url())
+                    }
+                })
+                .to_string(),
+            )
+            .expect(1)
+            .create_async()
+            .await;
 
-    #[tokio::test]
-    async fn test_upload_social_media_link_invalid_scheme() {
-        let config = Config {
+        let mock_put = server
+            .mock("PUT", "/upload")
+            .match_header("Content-Type", "image/jpeg")
+            .match_body("downloaded jpeg data")
+            .with_status(200)
+            .expect(1)
+            .create_async()
+            .await;
+
+        let client = Client::new(Config {
             api_key: "test_api_key".to_string(),
+            base_url: Some(server.url()),
             ..Default::default()
-        };
-        let client = Client::new(config).unwrap();
+        })
+        .unwrap();
 
-        // Test with invalid scheme
-        let result = client.upload_social_media("ftp://example.com/video").await;
+        // The test server listens on a loopback address
+        let result = client
+            .upload_from_url(
+                &format!("{}/cdn/photo.jpg", server.url()),
+                FetchOptions {
+                    allow_private_addresses: true,
+                    ..Default::default()
+                },
+            )
+            .await
+            .unwrap();
 
-        assert!(result.is_err());
-        match result.unwrap_err() {
-            Error::InvalidRequest(msg) => {
-                assert!(msg.contains("http or https scheme"));
-            }
-            err => panic!("Expected InvalidRequest error, got: {:?}", err),
-        }
+        assert_eq!(result.request_id, "url-request-id");
+        mock_media.assert_async().await;
+        mock_presigned.assert_async().await;
+        mock_put.assert_async().await;
     }
 
     #[tokio::test]
-    async fn test_upload_social_media_link_ip_address() {
-        let config = Config {
+    async fn test_upload_bytes_validation() {
+        let client = Client::new(Config {
             api_key: "test_api_key".to_string(),
             ..Default::default()
-        };
-        let client = Client::new(config).unwrap();
-
-        // Test with IP address (not allowed)
-        let result = client
-            .upload_social_media("https://192.168.1.1/video")
-            .await;
-
-        assert!(result.is_err());
-        match result.unwrap_err() {
-            Error::InvalidRequest(msg) => {
-                assert!(msg.contains("valid domain"));
+        })
+        .unwrap();
+
+        let cases = [
+            (
+                "document.xyz",
+                Bytes::from_static(b"data"),
+                "Unsupported file type",
+            ),
+            (
+                "no_extension",
+                Bytes::from_static(b"data"),
+                "Invalid file name",
+            ),
+            ("empty.jpg", Bytes::new(), "File is empty"),
+            (
+                "large.txt",
+                Bytes::from(vec![0u8; 5242881]),
+                "File too large",
+            ),
+        ];
+
+        for (file_name, content, expected) in cases {
+            match client.upload_bytes(file_name, content).await {
+                Err(Error::InvalidFile(msg)) => assert!(msg.contains(expected), "{msg}"),
+                other => panic!("Expected InvalidFile error, got: {:?}", other),
             }
-            err => panic!("Expected InvalidRequest error, got: {:?}", err),
         }
     }
 
     #[tokio::test]
-    async fn test_upload_social_media_link_server_error() {
+    async fn test_upload_reader() {
         let mut server = mockito::Server::new_async().await;
 
-        // Mock server error response
-        let _m = server
-            .mock("POST", "/api/files/social")
-            .with_status(500)
+        let _m1 = server
+            .mock("POST", "/api/files/aws-presigned")
+            .with_status(200)
             .with_header("content-type", "application/json")
-            .with_body(r#"{"response": "Internal server error"}"#)
+            .match_body(Matcher::Json(json!({"fileName": "clip.mp4"})))
+            .with_body(
+                json!({
+                    "code": "success",
+                    "errno": 0,
+                    "requestId": "reader-request-id",
+                    "mediaId": "reader-media-id",
+                    "response": {
+                        "signedUrl": format!("{}/upload", server.url())
+                    }
+                })
+                .to_string(),
+            )
             .create_async()
             .await;
 
-        let config = Config {
+        let mock_put = server
+            .mock("PUT", "/upload")
+            .match_header("Content-Type", "video/mp4")
+            .match_body("streamed video data")
+            .with_status(200)
+            .expect(1)
+            .create_async()
+            .await;
+
+        let client = Client::new(Config {
             api_key: "test_api_key".to_string(),
             base_url: Some(server.url()),
             ..Default::default()
-        };
-        let client = Client::new(config).unwrap();
+        })
+        .unwrap();
 
+        let content = b"streamed video data";
         let result = client
-            .upload_social_media("https://www.instagram.com/p/ABC123/")
-            .await;
+            .upload_reader("clip.mp4", &content[..], content.len() as u64)
+            .await
+            .unwrap();
 
-        assert!(result.is_err());
-        match result.unwrap_err() {
-            Error::ServerError(_) => {} // Expected error
-            err => panic!("Unexpected error: {:?}", err),
-        }
+        assert_eq!(result.request_id, "reader-request-id");
+        mock_put.assert_async().await;
     }
 
     #[tokio::test]
-    async fn test_bad_request_error_handling() {
-        let mut server = mockito::Server::new_async().await;
+    async fn test_upload_reader_length_mismatch() {
+        let client = Client::new(Config {
+            api_key: "test_api_key".to_string(),
+            ..Default::default()
+        })
+        .unwrap();
 
-        let _m = server
-            .mock("GET", "/api/media/users/test-bad-request")
-            .with_status(400)
+        let content = b"streamed video data";
+        for len in [content.len() as u64 - 1, content.len() as u64 + 1] {
+            match client.upload_reader("clip.mp4", &content[..], len).await {
+                Err(Error::InvalidFile(msg)) => assert!(msg.contains("expected")),
+                other => panic!("Expected InvalidFile error, got: {:?}", other),
+            }
+        }
+
+        // The announced length is validated before the content is read
+        match client
+            .upload_reader("clip.mp4", &content[..], 262144001)
+            .await
+        {
+            Err(Error::InvalidFile(msg)) => assert!(msg.contains("File too large")),
+            other => panic!("Expected InvalidFile error, got: {:?}", other),
+        }
+    }
+
+    /// Mock the presigned URL request of an upload of `clip.mp4`
+    async fn mock_reader_presigned(server: &mut mockito::Server) -> mockito::Mock {
+        server
+            .mock("POST", "/api/files/aws-presigned")
+            .with_status(200)
             .with_header("content-type", "application/json")
+            .match_body(Matcher::Json(json!({"fileName": "clip.mp4"})))
             .with_body(
-                r#"{"code": "error", "errno": 400, "response": "Invalid request parameters"}"#,
+                json!({
+                    "code": "success",
+                    "errno": 0,
+                    "requestId": "reader-request-id",
+                    "mediaId": "reader-media-id",
+                    "response": {
+                        "signedUrl": format!("{}/upload", server.url())
+                    }
+                })
+                .to_string(),
             )
             .create_async()
+            .await
+    }
+
+    #[tokio::test]
+    async fn test_upload_reader_streams_content() {
+        let mut server = mockito::Server::new_async().await;
+        let _presigned = mock_reader_presigned(&mut server).await;
+
+        // Larger than the validated head and than a single chunk
+        let content: Vec<u8> = (0..200_000u32).map(|i| (i % 251) as u8).collect();
+        let mock_put = server
+            .mock("PUT", "/upload")
+            .match_header("Content-Length", content.len().to_string().as_str())
+            .match_body(content.clone())
+            .with_status(200)
+            .expect(1)
+            .create_async()
             .await;
 
-        let config = Config {
+        let client = Client::new(Config {
             api_key: "test_api_key".to_string(),
             base_url: Some(server.url()),
             ..Default::default()
-        };
-        let client = Client::new(config).unwrap();
+        })
+        .unwrap();
 
-        let result = client.get_result("test-bad-request", None).await;
+        let result = client
+            .upload_reader("clip.mp4", &content[..], content.len() as u64)
+            .await
+            .unwrap();
 
-        assert!(result.is_err());
-        match result.unwrap_err() {
-            Error::InvalidRequest(_) => {} // Expected error
-            err => panic!("Unexpected error: {:?}", err),
-        }
+        assert_eq!(result.request_id, "reader-request-id");
+        mock_put.assert_async().await;
     }
 
     #[tokio::test]
-    async fn test_upload_bad_request_error() {
+    async fn test_upload_reader_aborts_on_length_mismatch() {
         let mut server = mockito::Server::new_async().await;
-
-        let dir = tempdir().unwrap();
-        let file_path = dir.path().join("test.jpg");
-        let mut file = File::create(&file_path).unwrap();
-        file.write_all(b"test data").unwrap();
-
-        let _m = server
-            .mock("POST", "/api/files/aws-presigned")
-            .with_status(400)
-            .with_header("content-type", "application/json")
-            .with_body(r#"{"code": "error", "errno": 400, "response": "Invalid file format"}"#)
+        let _presigned = mock_reader_presigned(&mut server).await;
+        let _put = server
+            .mock("PUT", "/upload")
+            .with_status(200)
             .create_async()
             .await;
 
-        let config = Config {
+        let client = Client::new(Config {
             api_key: "test_api_key".to_string(),
             base_url: Some(server.url()),
             ..Default::default()
-        };
-        let client = Client::new(config).unwrap();
-
-        let result = client
-            .upload(UploadOptions {
-                file_path: file_path.to_str().unwrap().to_string(),
-            })
-            .await;
-
-        assert!(result.is_err());
-        match result.unwrap_err() {
-            Error::InvalidRequest(_) | Error::UploadFailed(_) => {} // Either is acceptable
-            err => panic!("Unexpected error: {:?}", err),
+        })
+        .unwrap();
+
+        // The mismatch is only found after the upload started
+        let content = vec![7u8; 200_000];
+        for len in [150_000, 250_000] {
+            match client.upload_reader("clip.mp4", &content[..], len).await {
+                Err(Error::InvalidFile(msg)) => assert!(msg.contains("expected"), "{msg}"),
+                other => panic!("Expected InvalidFile error, got: {:?}", other),
+            }
         }
     }
 
     #[tokio::test]
-    async fn test_free_tier_not_allowed_error() {
+    async fn test_upload_reader_is_not_retried() {
         let mut server = mockito::Server::new_async().await;
-
+        let _presigned = mock_reader_presigned(&mut server).await;
+        let mock_put = server
+            .mock("PUT", "/upload")
+            .with_status(503)
+            .expect(1)
+            .create_async()
+            .await;
+
+        let client = Client::new(retry_config(&server)).unwrap();
+
+        let content = b"streamed video data";
+        match client
+            .upload_reader("clip.mp4", &content[..], content.len() as u64)
+            .await
+        {
+            Err(Error::UploadFailed(msg)) => assert!(msg.contains("503"), "{msg}"),
+            other => panic!("Expected UploadFailed error, got: {:?}", other),
+        }
+        mock_put.assert_async().await;
+    }
+
+    const PNG_HEAD: &[u8] = b"\x89PNG\r\n\x1A\n\x00\x00\x00\x0DIHDR";
+
+    #[tokio::test]
+    async fn test_upload_trusts_content_type() {
+        let mut server = mockito::Server::new_async().await;
+
+        let _m1 = server
+            .mock("POST", "/api/files/aws-presigned")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(
+                json!({
+                    "code": "success",
+                    "errno": 0,
+                    "requestId": "sniffed-request-id",
+                    "mediaId": "sniffed-media-id",
+                    "response": {
+                        "signedUrl": format!("{}/upload", server.url())
+                    }
+                })
+                .to_string(),
+            )
+            .create_async()
+            .await;
+
+        // A PNG renamed to .mp4 is labelled with its real type
+        let mock_put = server
+            .mock("PUT", "/upload")
+            .match_header("Content-Type", "image/png")
+            .with_status(200)
+            .expect(1)
+            .create_async()
+            .await;
+
+        let dir = tempdir().unwrap();
+        let file_path = dir.path().join("renamed.MP4");
+        std::fs::write(&file_path, PNG_HEAD).unwrap();
+
+        let client = Client::new(Config {
+            api_key: "test_api_key".to_string(),
+            base_url: Some(server.url()),
+            content_type_policy: ContentTypePolicy::TrustContent,
+            ..Default::default()
+        })
+        .unwrap();
+
+        let result = client
+            .upload(UploadOptions {
+                file_path: file_path.to_str().unwrap().to_string(),
+            })
+            .await;
+
+        assert!(result.is_ok(), "Unexpected error: {:?}", result.err());
+        mock_put.assert_async().await;
+    }
+
+    #[test]
+    fn test_validate_upload_policies() {
+        // Extensions are matched regardless of case
+        assert_eq!(
+            validate_upload("IMAGE.JPG", 4, b"data", ContentTypePolicy::TrustExtension).unwrap(),
+            "image/jpeg"
+        );
+        assert_eq!(
+            validate_upload("clip.mp4", 14, PNG_HEAD, ContentTypePolicy::TrustExtension).unwrap(),
+            "video/mp4"
+        );
+
+        // Unrecognised content falls back to the extension
+        assert_eq!(
+            validate_upload(
+                "clip.mp4",
+                4,
+                b"\x00\x01\x02\x03",
+                ContentTypePolicy::TrustContent
+            )
+            .unwrap(),
+            "video/mp4"
+        );
+
+        // Recognised content decides the size limit
+        match validate_upload(
+            "clip.mp4",
+            52428801,
+            PNG_HEAD,
+            ContentTypePolicy::TrustContent,
+        ) {
+            Err(Error::InvalidFile(msg)) => assert!(msg.contains("File too large")),
+            other => panic!("Expected InvalidFile error, got: {:?}", other),
+        }
+
+        assert_eq!(
+            validate_upload("image.png", 14, PNG_HEAD, ContentTypePolicy::RejectMismatch).unwrap(),
+            "image/png"
+        );
+        for head in [PNG_HEAD, b"\x00\x01\x02\x03"] {
+            match validate_upload("clip.mp4", 14, head, ContentTypePolicy::RejectMismatch) {
+                Err(Error::InvalidFile(msg)) => assert!(msg.contains("does not match")),
+                other => panic!("Expected InvalidFile error, got: {:?}", other),
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_check_upload_structure_rejects_truncated_media() {
+        let mut server = mockito::Server::new_async().await;
+
+        // The mdat box announces more data than the file holds
+        let mut content = b"\x00\x00\x00\x10ftypisom\x00\x00\x02\x00".to_vec();
+        content.extend_from_slice(b"\x00\x00\x03\xE8mdat");
+        content.extend_from_slice(&[0; 100]);
+        let dir = tempdir().unwrap();
+        let file_path = dir.path().join("truncated.mp4");
+        std::fs::write(&file_path, &content).unwrap();
+        let file_path = file_path.to_str().unwrap().to_string();
+
+        let mock_presigned = server
+            .mock("POST", "/api/files/aws-presigned")
+            .expect(0)
+            .create_async()
+            .await;
+
+        let client = Client::new(Config {
+            api_key: "test_api_key".to_string(),
+            base_url: Some(server.url()),
+            check_upload_structure: true,
+            ..Default::default()
+        })
+        .unwrap();
+
+        let expected = format!(
+            "Invalid media in {file_path}: box 'mdat' at offset 16 needs 1000 bytes but the content ends after 124 bytes"
+        );
+        match client.inspect(&file_path).await {
+            Err(Error::InvalidFile(msg)) => assert_eq!(msg, expected),
+            other => panic!("Expected InvalidFile error, got: {:?}", other),
+        }
+        match client
+            .upload(UploadOptions {
+                file_path: file_path.clone(),
+            })
+            .await
+        {
+            Err(Error::InvalidFile(msg)) => assert_eq!(msg, expected),
+            other => panic!("Expected InvalidFile error, got: {:?}", other),
+        }
+        match client
+            .upload_bytes("truncated.mp4", Bytes::from(content))
+            .await
+        {
+            Err(Error::InvalidFile(msg)) => assert!(msg.contains("box 'mdat'"), "{msg}"),
+            other => panic!("Expected InvalidFile error, got: {:?}", other),
+        }
+        mock_presigned.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_inspect_validates_like_an_upload() {
+        let dir = tempdir().unwrap();
+        let text_path = dir.path().join("notes.txt");
+        std::fs::write(&text_path, "plain text").unwrap();
+
+        let client = Client::new(Config {
+            api_key: "test_api_key".to_string(),
+            ..Default::default()
+        })
+        .unwrap();
+
+        let info = client.inspect(&text_path).await.unwrap();
+        assert_eq!(info.mime_type, "text/plain");
+        assert_eq!(info.size, 10);
+        assert_eq!(info.details, MediaDetails::Text);
+
+        match client.inspect(dir.path().join("archive.zip")).await {
+            Err(Error::InvalidFile(msg)) => assert!(msg.contains("File not found"), "{msg}"),
+            other => panic!("Expected InvalidFile error, got: {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_upload_normalizes_unsupported_images() {
+        let mut server = mockito::Server::new_async().await;
+
+        // A 1x1 24-bit BMP holding a single red pixel
+        let mut content =
+            b"BM\x3A\x00\x00\x00\x00\x00\x00\x00\x36\x00\x00\x00\x28\x00\x00\x00".to_vec();
+        content.extend_from_slice(b"\x01\x00\x00\x00\x01\x00\x00\x00\x01\x00\x18\x00");
+        content.extend_from_slice(&[0; 24]);
+        content.extend_from_slice(&[0, 0, 255, 0]);
+        let dir = tempdir().unwrap();
+        let file_path = dir.path().join("scan.bmp");
+        std::fs::write(&file_path, &content).unwrap();
+
+        let mock_presigned = server
+            .mock("POST", "/api/files/aws-presigned")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .match_body(Matcher::Json(json!({"fileName": "scan.png"})))
+            .with_body(
+                json!({
+                    "code": "success",
+                    "errno": 0,
+                    "requestId": "normalized-request-id",
+                    "mediaId": "normalized-media-id",
+                    "response": {
+                        "signedUrl": format!("{}/upload", server.url())
+                    }
+                })
+                .to_string(),
+            )
+            .expect(1)
+            .create_async()
+            .await;
+
+        let mock_put = server
+            .mock("PUT", "/upload")
+            .match_header("Content-Type", "image/png")
+            .with_status(200)
+            .expect(1)
+            .create_async()
+            .await;
+
+        let client = Client::new(Config {
+            api_key: "test_api_key".to_string(),
+            base_url: Some(server.url()),
+            image_normalization: Some(ImageNormalization::default()),
+            ..Default::default()
+        })
+        .unwrap();
+
+        let result = client
+            .upload(UploadOptions {
+                file_path: file_path.to_str().unwrap().to_string(),
+            })
+            .await
+            .unwrap();
+
+        assert_eq!(result.request_id, "normalized-request-id");
+        let normalization = result.normalization.unwrap();
+        assert_eq!(normalization.original_format, "bmp");
+        assert_eq!(normalization.original_size, 58);
+        assert_eq!(normalization.original_dimensions, (1, 1));
+        assert_eq!(normalization.format, "png");
+        assert_eq!(normalization.dimensions, (1, 1));
+        mock_presigned.assert_async().await;
+        mock_put.assert_async().await;
+
+        // Without normalization the format is rejected
+        let client = Client::new(Config {
+            api_key: "test_api_key".to_string(),
+            base_url: Some(server.url()),
+            ..Default::default()
+        })
+        .unwrap();
+        match client.upload_bytes("scan.bmp", Bytes::from(content)).await {
+            Err(Error::InvalidFile(msg)) => assert_eq!(msg, "Unsupported file type: scan.bmp"),
+            other => panic!("Expected InvalidFile error, got: {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_upload_normalizes_to_jpeg() {
+        let mut server = mockito::Server::new_async().await;
+
+        let mut content = std::io::Cursor::new(Vec::new());
+        image::DynamicImage::ImageRgb8(image::RgbImage::from_pixel(
+            8,
+            6,
+            image::Rgb([0, 128, 255]),
+        ))
+        .write_to(&mut content, image::ImageFormat::Tiff)
+        .unwrap();
+
+        let mock_presigned = server
+            .mock("POST", "/api/files/aws-presigned")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .match_body(Matcher::Json(json!({"fileName": "scan.jpg"})))
+            .with_body(
+                json!({
+                    "code": "success",
+                    "errno": 0,
+                    "requestId": "normalized-request-id",
+                    "mediaId": "normalized-media-id",
+                    "response": {
+                        "signedUrl": format!("{}/upload", server.url())
+                    }
+                })
+                .to_string(),
+            )
+            .expect(1)
+            .create_async()
+            .await;
+
+        let mock_put = server
+            .mock("PUT", "/upload")
+            .match_header("Content-Type", "image/jpeg")
+            .with_status(200)
+            .expect(1)
+            .create_async()
+            .await;
+
+        let client = Client::new(Config {
+            api_key: "test_api_key".to_string(),
+            base_url: Some(server.url()),
+            image_normalization: Some(ImageNormalization {
+                format: NormalizedFormat::Jpeg { quality: 85 },
+                ..Default::default()
+            }),
+            ..Default::default()
+        })
+        .unwrap();
+
+        let result = client
+            .upload_bytes("scan.tiff", Bytes::from(content.into_inner()))
+            .await
+            .unwrap();
+
+        let normalization = result.normalization.unwrap();
+        assert_eq!(normalization.original_format, "tiff");
+        assert_eq!(normalization.original_dimensions, (8, 6));
+        assert_eq!(normalization.format, "jpg");
+        assert_eq!(normalization.dimensions, (8, 6));
+        mock_presigned.assert_async().await;
+        mock_put.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_upload_social_media_link_success() {
+        let mut server = mockito::Server::new_async().await;
+
+        // Mock the social media upload endpoint
         let _m = server
-            .mock("GET", "/api/media/users/test-free-tier")
-            .with_status(400)
+            .mock("POST", "/api/files/social")
+            .with_status(200)
             .with_header("content-type", "application/json")
-            .with_body(r#"{"code": "free-tier-not-allowed", "errno": 400, "response": "This feature is not available in the free tier"}"#)
+            .match_header("X-API-KEY", "test_api_key")
+            .match_body(Matcher::Json(json!({
+                "socialLink": "https://www.youtube.com/watch?v=dQw4w9WgXcQ"
+            })))
+            .with_body(
+                json!({
+                    "code": "success",
+                    "errno": 0,
+                    "response": "",
+                    "requestId": "social-request-id",
+                })
+                .to_string(),
+            )
             .create_async()
             .await;
 
@@ -1237,12 +2545,941 @@ mod tests {
         };
         let client = Client::new(config).unwrap();
 
-        let result = client.get_result("test-free-tier", None).await;
+        let result = client
+            .upload_social_media("https://www.youtube.com/watch?v=dQw4w9WgXcQ")
+            .await;
+
+        println!("{:?}", result);
+        assert!(result.is_ok());
+        let upload_result = result.unwrap();
+        assert_eq!(upload_result.request_id, "social-request-id");
+    }
+
+    #[tokio::test]
+    async fn test_upload_social_media_link_invalid_url() {
+        let config = Config {
+            api_key: "test_api_key".to_string(),
+            ..Default::default()
+        };
+        let client = Client::new(config).unwrap();
+
+        // Test with invalid URL (no scheme)
+        let result = client.upload_social_media("www.example.com").await;
 
         assert!(result.is_err());
         match result.unwrap_err() {
-            Error::Unauthorized(_) => {} // Expected - free tier restrictions are auth-related
-            err => panic!("Unexpected error: {:?}", err),
+            Error::InvalidRequest(msg) => {
+                assert!(msg.contains("Invalid URL"));
+            }
+            err => panic!("Expected InvalidRequest error, got: {:?}", err),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_upload_social_media_link_invalid_scheme() {
+        let config = Config {
+            api_key: "test_api_key".to_string(),
+            ..Default::default()
+        };
+        let client = Client::new(config).unwrap();
+
+        // Test with invalid scheme
+        let result = client.upload_social_media("ftp://example.com/video").await;
+
+        assert!(result.is_err());
+        match result.unwrap_err() {
+            Error::InvalidRequest(msg) => {
+                assert!(msg.contains("http or https scheme"));
+            }
+            err => panic!("Expected InvalidRequest error, got: {:?}", err),
         }
     }
+
+    #[tokio::test]
+    async fn test_upload_social_media_link_ip_address() {
+        let config = Config {
+            api_key: "test_api_key".to_string(),
+            ..Default::default()
+        };
+        let client = Client::new(config).unwrap();
+
+        // Test with IP address (not allowed)
+        let result = client
+            .upload_social_media("https://192.168.1.1/video")
+            .await;
+
+        assert!(result.is_err());
+        match result.unwrap_err() {
+            Error::InvalidRequest(msg) => {
+                assert!(msg.contains("valid domain"));
+            }
+            err => panic!("Expected InvalidRequest error, got: {:?}", err),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_upload_social_media_link_server_error() {
+        let mut server = mockito::Server::new_async().await;
+
+        // Mock server error response
+        let _m = server
+            .mock("POST", "/api/files/social")
+            .with_status(500)
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"response": "Internal server error"}"#)
+            .create_async()
+            .await;
+
+        let config = Config {
+            api_key: "test_api_key".to_string(),
+            base_url: Some(server.url()),
+            ..Default::default()
+        };
+        let client = Client::new(config).unwrap();
+
+        let result = client
+            .upload_social_media("https://www.instagram.com/p/ABC123/")
+            .await;
+
+        assert!(result.is_err());
+        match result.unwrap_err() {
+            Error::ServerError(_) => {} // Expected error
+            err => panic!("Unexpected error: {:?}", err),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_bad_request_error_handling() {
+        let mut server = mockito::Server::new_async().await;
+
+        let _m = server
+            .mock("GET", "/api/media/users/test-bad-request")
+            .with_status(400)
+            .with_header("content-type", "application/json")
+            .with_body(
+                r#"{"code": "error", "errno": 400, "response": "Invalid request parameters"}"#,
+            )
+            .create_async()
+            .await;
+
+        let config = Config {
+            api_key: "test_api_key".to_string(),
+            base_url: Some(server.url()),
+            ..Default::default()
+        };
+        let client = Client::new(config).unwrap();
+
+        let result = client.get_result("test-bad-request", None).await;
+
+        assert!(result.is_err());
+        match result.unwrap_err() {
+            Error::BadRequest(error) => {
+                assert_eq!(error.http_status, 400);
+                assert_eq!(error.errno, 400);
+                assert_eq!(error.message, "Invalid request parameters");
+            }
+            err => panic!("Unexpected error: {:?}", err),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_upload_bad_request_error() {
+        let mut server = mockito::Server::new_async().await;
+
+        let dir = tempdir().unwrap();
+        let file_path = dir.path().join("test.jpg");
+        let mut file = File::create(&file_path).unwrap();
+        file.write_all(b"test data").unwrap();
+
+        let _m = server
+            .mock("POST", "/api/files/aws-presigned")
+            .with_status(400)
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"code": "error", "errno": 400, "response": "Invalid file format"}"#)
+            .create_async()
+            .await;
+
+        let config = Config {
+            api_key: "test_api_key".to_string(),
+            base_url: Some(server.url()),
+            ..Default::default()
+        };
+        let client = Client::new(config).unwrap();
+
+        let result = client
+            .upload(UploadOptions {
+                file_path: file_path.to_str().unwrap().to_string(),
+            })
+            .await;
+
+        assert!(result.is_err());
+        match result.unwrap_err() {
+            Error::BadRequest(error) => assert_eq!(error.message, "Invalid file format"),
+            err => panic!("Unexpected error: {:?}", err),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_quota_exceeded_error() {
+        let mut server = mockito::Server::new_async().await;
+
+        let _m = server
+            .mock("POST", "/api/files/social")
+            .with_status(400)
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"code": "upload-limit-reached", "errno": 400, "response": "Upload limit reached", "requestId": "quota-request-id"}"#)
+            .create_async()
+            .await;
+
+        let config = Config {
+            api_key: "test_api_key".to_string(),
+            base_url: Some(server.url()),
+            ..Default::default()
+        };
+        let client = Client::new(config).unwrap();
+
+        let result = client
+            .upload_social_media("https://www.youtube.com/watch?v=abc")
+            .await;
+
+        match result.unwrap_err() {
+            Error::QuotaExceeded(error) => {
+                assert_eq!(error.message, "Upload limit reached");
+                assert_eq!(error.request_id, Some("quota-request-id".to_string()));
+            }
+            err => panic!("Unexpected error: {:?}", err),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_free_tier_not_allowed_error() {
+        let mut server = mockito::Server::new_async().await;
+
+        let _m = server
+            .mock("GET", "/api/media/users/test-free-tier")
+            .with_status(400)
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"code": "free-tier-not-allowed", "errno": 400, "response": "This feature is not available in the free tier"}"#)
+            .create_async()
+            .await;
+
+        let config = Config {
+            api_key: "test_api_key".to_string(),
+            base_url: Some(server.url()),
+            ..Default::default()
+        };
+        let client = Client::new(config).unwrap();
+
+        let result = client.get_result("test-free-tier", None).await;
+
+        assert!(result.is_err());
+        match result.unwrap_err() {
+            Error::FreeTierNotAllowed(error) => assert_eq!(error.code, "free-tier-not-allowed"),
+            err => panic!("Unexpected error: {:?}", err),
+        }
+    }
+
+    fn retry_config(server: &mockito::Server) -> Config {
+        Config {
+            api_key: "test_api_key".to_string(),
+            base_url: Some(server.url()),
+            retry_policy: Some(RetryPolicy {
+                base_delay: Duration::from_millis(1),
+                max_delay: Duration::from_millis(5),
+                ..Default::default()
+            }),
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_get_retries_server_errors() {
+        let mut server = mockito::Server::new_async().await;
+
+        let mock_unavailable = server
+            .mock("GET", "/api/media/users/test-retry")
+            .with_status(503)
+            .with_body(r#"{"response": "Service unavailable"}"#)
+            .expect(2)
+            .create_async()
+            .await;
+
+        let mock_ok = server
+            .mock("GET", "/api/media/users/test-retry")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(
+                json!({
+                    "requestId": "test-retry",
+                    "overallStatus": "AUTHENTIC",
+                    "models": []
+                })
+                .to_string(),
+            )
+            .expect(1)
+            .create_async()
+            .await;
+
+        let client = Client::new(retry_config(&server)).unwrap();
+        let result = client.get_result("test-retry", None).await;
+
+        assert!(result.is_ok(), "Unexpected error: {:?}", result.err());
+        mock_unavailable.assert_async().await;
+        mock_ok.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_get_gives_up_after_max_retries() {
+        let mut server = mockito::Server::new_async().await;
+
+        let mock = server
+            .mock("GET", "/api/media/users/test-retry")
+            .with_status(502)
+            .with_body(r#"{"response": "Bad gateway"}"#)
+            .expect(4)
+            .create_async()
+            .await;
+
+        let client = Client::new(retry_config(&server)).unwrap();
+        let result = client.get_result("test-retry", None).await;
+
+        assert!(matches!(result, Err(Error::ServerError(_))));
+        mock.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_get_without_retry_policy() {
+        let mut server = mockito::Server::new_async().await;
+
+        let mock = server
+            .mock("GET", "/api/media/users/test-retry")
+            .with_status(503)
+            .with_body(r#"{"response": "Service unavailable"}"#)
+            .expect(1)
+            .create_async()
+            .await;
+
+        let client = Client::new(Config {
+            api_key: "test_api_key".to_string(),
+            base_url: Some(server.url()),
+            ..Default::default()
+        })
+        .unwrap();
+        let result = client.get_result("test-retry", None).await;
+
+        assert!(matches!(result, Err(Error::ServerError(_))));
+        mock.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_put_retries_without_new_presigned_url() {
+        let mut server = mockito::Server::new_async().await;
+
+        let dir = tempdir().unwrap();
+        let file_path = dir.path().join("test.jpg");
+        let mut file = File::create(&file_path).unwrap();
+        file.write_all(b"test image data").unwrap();
+
+        let mock_presigned = server
+            .mock("POST", "/api/files/aws-presigned")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(
+                json!({
+                    "code": "success",
+                    "errno": 0,
+                    "requestId": "test-request-id",
+                    "mediaId": "test-media-id",
+                    "response": {
+                        "signedUrl": format!("{}/upload", server.url())
+                    }
+                })
+                .to_string(),
+            )
+            .expect(1)
+            .create_async()
+            .await;
+
+        let mock_put_error = server
+            .mock("PUT", "/upload")
+            .with_status(500)
+            .expect(1)
+            .create_async()
+            .await;
+
+        let mock_put_ok = server
+            .mock("PUT", "/upload")
+            .match_body("test image data")
+            .with_status(200)
+            .expect(1)
+            .create_async()
+            .await;
+
+        let client = Client::new(retry_config(&server)).unwrap();
+        let result = client
+            .upload(UploadOptions {
+                file_path: file_path.to_str().unwrap().to_string(),
+            })
+            .await;
+
+        assert!(result.is_ok(), "Unexpected error: {:?}", result.err());
+        mock_presigned.assert_async().await;
+        mock_put_error.assert_async().await;
+        mock_put_ok.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_put_requests_new_url_when_expired() {
+        let mut server = mockito::Server::new_async().await;
+
+        let dir = tempdir().unwrap();
+        let file_path = dir.path().join("test.jpg");
+        let mut file = File::create(&file_path).unwrap();
+        file.write_all(b"test image data").unwrap();
+
+        let mock_presigned_expired = server
+            .mock("POST", "/api/files/aws-presigned")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(
+                json!({
+                    "code": "success",
+                    "errno": 0,
+                    "requestId": "expired-request-id",
+                    "mediaId": "expired-media-id",
+                    "response": {
+                        "signedUrl": format!("{}/upload-expired", server.url())
+                    }
+                })
+                .to_string(),
+            )
+            .expect(1)
+            .create_async()
+            .await;
+
+        let mock_presigned_fresh = server
+            .mock("POST", "/api/files/aws-presigned")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(
+                json!({
+                    "code": "success",
+                    "errno": 0,
+                    "requestId": "fresh-request-id",
+                    "mediaId": "fresh-media-id",
+                    "response": {
+                        "signedUrl": format!("{}/upload-fresh", server.url())
+                    }
+                })
+                .to_string(),
+            )
+            .expect(1)
+            .create_async()
+            .await;
+
+        let mock_put_expired = server
+            .mock("PUT", "/upload-expired")
+            .with_status(403)
+            .with_body(
+                "<Error><Code>AccessDenied</Code><Message>Request has expired</Message></Error>",
+            )
+            .expect(1)
+            .create_async()
+            .await;
+
+        let mock_put_fresh = server
+            .mock("PUT", "/upload-fresh")
+            .with_status(200)
+            .expect(1)
+            .create_async()
+            .await;
+
+        let client = Client::new(retry_config(&server)).unwrap();
+        let result = client
+            .upload(UploadOptions {
+                file_path: file_path.to_str().unwrap().to_string(),
+            })
+            .await
+            .unwrap();
+
+        assert_eq!(result.request_id, "fresh-request-id");
+        mock_presigned_expired.assert_async().await;
+        mock_presigned_fresh.assert_async().await;
+        mock_put_expired.assert_async().await;
+        mock_put_fresh.assert_async().await;
+    }
+
+    #[cfg(feature = "experimental-multipart")]
+    fn multipart_config(server: &mockito::Server) -> Config {
+        Config {
+            multipart_upload: Some(MultipartUpload {
+                threshold: 20,
+                part_size: 10,
+            }),
+            ..retry_config(server)
+        }
+    }
+
+    #[cfg(feature = "experimental-multipart")]
+    #[tokio::test]
+    async fn test_multipart_upload_retries_failed_part() {
+        let mut server = mockito::Server::new_async().await;
+
+        let dir = tempdir().unwrap();
+        let file_path = dir.path().join("large.jpg");
+        std::fs::write(&file_path, "aaaaaaaaaabbbbbbbbbbccccc").unwrap();
+
+        let mock_presigned = server
+            .mock("POST", "/api/files/aws-presigned")
+            .match_body(Matcher::Json(json!({
+                "fileName": "large.jpg",
+                "fileSize": 25,
+                "partSize": 10,
+            })))
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(
+                json!({
+                    "code": "success",
+                    "errno": 0,
+                    "requestId": "test-request-id",
+                    "mediaId": "test-media-id",
+                    "response": {
+                        "signedUrl": format!("{}/upload", server.url()),
+                        "multipart": {
+                            "uploadId": "test-upload-id",
+                            "partSize": 10,
+                            "partUrls": [
+                                format!("{}/upload/1", server.url()),
+                                format!("{}/upload/2", server.url()),
+                                format!("{}/upload/3", server.url()),
+                            ],
+                            "completeUrl": format!("{}/upload/complete", server.url()),
+                        }
+                    }
+                })
+                .to_string(),
+            )
+            .expect(1)
+            .create_async()
+            .await;
+
+        let mock_part_1 = server
+            .mock("PUT", "/upload/1")
+            .match_body("aaaaaaaaaa")
+            .with_status(200)
+            .with_header("ETag", "\"etag-1\"")
+            .expect(1)
+            .create_async()
+            .await;
+
+        // The failed part is sent again without restarting the upload
+        let mock_part_2_error = server
+            .mock("PUT", "/upload/2")
+            .with_status(503)
+            .expect(1)
+            .create_async()
+            .await;
+        let mock_part_2 = server
+            .mock("PUT", "/upload/2")
+            .match_body("bbbbbbbbbb")
+            .with_status(200)
+            .with_header("ETag", "\"etag-2\"")
+            .expect(1)
+            .create_async()
+            .await;
+
+        let mock_part_3 = server
+            .mock("PUT", "/upload/3")
+            .match_header("content-length", "5")
+            .match_body("ccccc")
+            .with_status(200)
+            .with_header("ETag", "\"etag-3\"")
+            .expect(1)
+            .create_async()
+            .await;
+
+        let mock_complete = server
+            .mock("POST", "/upload/complete")
+            .match_body(concat!(
+                "<CompleteMultipartUpload>",
+                "<Part><PartNumber>1</PartNumber><ETag>\"etag-1\"</ETag></Part>",
+                "<Part><PartNumber>2</PartNumber><ETag>\"etag-2\"</ETag></Part>",
+                "<Part><PartNumber>3</PartNumber><ETag>\"etag-3\"</ETag></Part>",
+                "</CompleteMultipartUpload>"
+            ))
+            .with_status(200)
+            .expect(1)
+            .create_async()
+            .await;
+
+        let client = Client::new(multipart_config(&server)).unwrap();
+        let (progress, progress_rx) = tokio::sync::watch::channel(UploadProgress::default());
+        let result = client
+            .upload_with_progress(
+                UploadOptions {
+                    file_path: file_path.to_str().unwrap().to_string(),
+                },
+                progress,
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(result.request_id, "test-request-id");
+        assert_eq!(
+            *progress_rx.borrow(),
+            UploadProgress {
+                bytes_sent: 25,
+                total_bytes: 25,
+            }
+        );
+        mock_presigned.assert_async().await;
+        mock_part_1.assert_async().await;
+        mock_part_2_error.assert_async().await;
+        mock_part_2.assert_async().await;
+        mock_part_3.assert_async().await;
+        mock_complete.assert_async().await;
+    }
+
+    #[cfg(feature = "experimental-multipart")]
+    #[tokio::test]
+    async fn test_multipart_upload_retries_parts_without_policy() {
+        let mut server = mockito::Server::new_async().await;
+
+        let dir = tempdir().unwrap();
+        let file_path = dir.path().join("large.jpg");
+        std::fs::write(&file_path, "aaaaaaaaaabbbbbbbbbb").unwrap();
+
+        let _m1 = server
+            .mock("POST", "/api/files/aws-presigned")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(
+                json!({
+                    "code": "success",
+                    "errno": 0,
+                    "requestId": "test-request-id",
+                    "mediaId": "test-media-id",
+                    "response": {
+                        "signedUrl": format!("{}/upload", server.url()),
+                        "multipart": {
+                            "uploadId": "test-upload-id",
+                            "partSize": 10,
+                            "partUrls": [
+                                format!("{}/upload/1", server.url()),
+                                format!("{}/upload/2", server.url()),
+                            ],
+                            "completeUrl": format!("{}/upload/complete", server.url()),
+                        }
+                    }
+                })
+                .to_string(),
+            )
+            .create_async()
+            .await;
+        let _part_1 = server
+            .mock("PUT", "/upload/1")
+            .with_status(200)
+            .with_header("ETag", "\"etag-1\"")
+            .create_async()
+            .await;
+
+        // A transient failure does not lose the parts already uploaded
+        let mock_part_2_error = server
+            .mock("PUT", "/upload/2")
+            .with_status(503)
+            .expect(1)
+            .create_async()
+            .await;
+        let mock_part_2 = server
+            .mock("PUT", "/upload/2")
+            .with_status(200)
+            .with_header("ETag", "\"etag-2\"")
+            .expect(1)
+            .create_async()
+            .await;
+        let mock_complete = server
+            .mock("POST", "/upload/complete")
+            .with_status(200)
+            .expect(1)
+            .create_async()
+            .await;
+
+        let client = Client::new(Config {
+            retry_policy: None,
+            ..multipart_config(&server)
+        })
+        .unwrap();
+        let result = client
+            .upload(UploadOptions {
+                file_path: file_path.to_str().unwrap().to_string(),
+            })
+            .await
+            .unwrap();
+
+        assert_eq!(result.request_id, "test-request-id");
+        mock_part_2_error.assert_async().await;
+        mock_part_2.assert_async().await;
+        mock_complete.assert_async().await;
+    }
+
+    #[cfg(feature = "experimental-multipart")]
+    #[tokio::test]
+    async fn test_multipart_upload_rejected_completion() {
+        let mut server = mockito::Server::new_async().await;
+
+        let _m1 = server
+            .mock("POST", "/api/files/aws-presigned")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(
+                json!({
+                    "code": "success",
+                    "errno": 0,
+                    "requestId": "test-request-id",
+                    "mediaId": "test-media-id",
+                    "response": {
+                        "signedUrl": format!("{}/upload", server.url()),
+                        "multipart": {
+                            "uploadId": "test-upload-id",
+                            "partSize": 20,
+                            "partUrls": [format!("{}/upload/1", server.url())],
+                            "completeUrl": format!("{}/upload/complete", server.url()),
+                        }
+                    }
+                })
+                .to_string(),
+            )
+            .create_async()
+            .await;
+
+        let _m2 = server
+            .mock("PUT", "/upload/1")
+            .with_status(200)
+            .with_header("ETag", "\"etag-1\"")
+            .create_async()
+            .await;
+
+        // Storage can fail a completion after answering with a 200
+        let _m3 = server
+            .mock("POST", "/upload/complete")
+            .with_status(200)
+            .with_body("<Error><Code>InvalidPart</Code></Error>")
+            .create_async()
+            .await;
+
+        let client = Client::new(multipart_config(&server)).unwrap();
+        let result = client
+            .upload_bytes("large.jpg", Bytes::from_static(b"aaaaaaaaaabbbbbbbbbb"))
+            .await;
+
+        match result {
+            Err(Error::UploadFailed(msg)) => assert!(msg.contains("InvalidPart"), "{msg}"),
+            other => panic!("Expected UploadFailed error, got: {:?}", other),
+        }
+    }
+
+    #[cfg(feature = "experimental-multipart")]
+    #[tokio::test]
+    async fn test_multipart_upload_falls_back_to_single_put() {
+        let mut server = mockito::Server::new_async().await;
+
+        let dir = tempdir().unwrap();
+        let file_path = dir.path().join("large.jpg");
+        std::fs::write(&file_path, "aaaaaaaaaabbbbbbbbbbccccc").unwrap();
+
+        // The API answers without multipart details when it cannot offer them
+        let _m1 = server
+            .mock("POST", "/api/files/aws-presigned")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(
+                json!({
+                    "code": "success",
+                    "errno": 0,
+                    "requestId": "test-request-id",
+                    "mediaId": "test-media-id",
+                    "response": {
+                        "signedUrl": format!("{}/upload", server.url())
+                    }
+                })
+                .to_string(),
+            )
+            .create_async()
+            .await;
+
+        let mock_put = server
+            .mock("PUT", "/upload")
+            .match_header("content-length", "25")
+            .match_body("aaaaaaaaaabbbbbbbbbbccccc")
+            .with_status(200)
+            .expect(1)
+            .create_async()
+            .await;
+
+        let client = Client::new(multipart_config(&server)).unwrap();
+        let result = client
+            .upload(UploadOptions {
+                file_path: file_path.to_str().unwrap().to_string(),
+            })
+            .await;
+
+        assert!(result.is_ok(), "Unexpected error: {:?}", result.err());
+        mock_put.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_get_rate_limited() {
+        let mut server = mockito::Server::new_async().await;
+
+        let mock = server
+            .mock("GET", "/api/media/users/test-throttled")
+            .with_status(429)
+            .with_header("retry-after", "7")
+            .with_body(r#"{"code": "rate-limited", "errno": 429, "response": "Too many requests"}"#)
+            .expect(1)
+            .create_async()
+            .await;
+
+        let client = Client::new(Config {
+            api_key: "test_api_key".to_string(),
+            base_url: Some(server.url()),
+            ..Default::default()
+        })
+        .unwrap();
+        let result = client.get_result("test-throttled", None).await;
+
+        match result {
+            Err(Error::RateLimited { retry_after }) => {
+                assert_eq!(retry_after, Some(Duration::from_secs(7)))
+            }
+            other => panic!("Expected RateLimited error, got: {:?}", other),
+        }
+        mock.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_get_waits_for_retry_after() {
+        let mut server = mockito::Server::new_async().await;
+
+        let mock_throttled = server
+            .mock("GET", "/api/media/users/test-throttled")
+            .with_status(429)
+            .with_header("retry-after", "1")
+            .expect(1)
+            .create_async()
+            .await;
+
+        let mock_ok = server
+            .mock("GET", "/api/media/users/test-throttled")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(
+                json!({
+                    "requestId": "test-throttled",
+                    "overallStatus": "AUTHENTIC",
+                    "models": []
+                })
+                .to_string(),
+            )
+            .expect(1)
+            .create_async()
+            .await;
+
+        let mut config = retry_config(&server);
+        if let Some(policy) = &mut config.retry_policy {
+            policy.max_delay = Duration::from_secs(2);
+        }
+        let client = Client::new(config).unwrap();
+        let started = std::time::Instant::now();
+        let result = client.get_result("test-throttled", None).await;
+
+        assert!(result.is_ok(), "Unexpected error: {:?}", result.err());
+        // The advertised delay wins over the much shorter policy backoff
+        assert!(started.elapsed() >= Duration::from_secs(1));
+        mock_throttled.assert_async().await;
+        mock_ok.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_get_does_not_wait_past_max_delay() {
+        let mut server = mockito::Server::new_async().await;
+
+        // A delay in seconds and a far-future date both exceed the policy
+        for (request_id, retry_after) in [
+            ("test-throttled-seconds", "86400"),
+            ("test-throttled-date", "Wed, 21 Oct 2099 07:28:00 GMT"),
+        ] {
+            let mock = server
+                .mock("GET", format!("/api/media/users/{}", request_id).as_str())
+                .with_status(429)
+                .with_header("retry-after", retry_after)
+                .expect(1)
+                .create_async()
+                .await;
+
+            let client = Client::new(retry_config(&server)).unwrap();
+            let result =
+                tokio::time::timeout(Duration::from_secs(5), client.get_result(request_id, None))
+                    .await
+                    .expect("Waited for the advertised delay");
+
+            match result {
+                Err(Error::RateLimited {
+                    retry_after: Some(retry_after),
+                }) => assert!(retry_after >= Duration::from_secs(86400)),
+                other => panic!("Expected RateLimited error, got: {:?}", other),
+            }
+            mock.assert_async().await;
+        }
+    }
+
+    #[tokio::test]
+    async fn test_rate_limit_is_shared_across_tasks() {
+        let mut server = mockito::Server::new_async().await;
+
+        let mock = server
+            .mock(
+                "GET",
+                Matcher::Regex(r"^/api/media/users/test-\d$".to_string()),
+            )
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(
+                json!({
+                    "requestId": "test",
+                    "overallStatus": "AUTHENTIC",
+                    "models": []
+                })
+                .to_string(),
+            )
+            .expect(4)
+            .create_async()
+            .await;
+
+        let client = Client::new(Config {
+            api_key: "test_api_key".to_string(),
+            base_url: Some(server.url()),
+            rate_limit: Some(crate::RateLimit {
+                requests_per_second: 20.0,
+                burst: 1,
+            }),
+            ..Default::default()
+        })
+        .unwrap();
+
+        let request_ids: Vec<String> = (0..4).map(|index| format!("test-{index}")).collect();
+        let started = std::time::Instant::now();
+        let results = futures::future::join_all(
+            request_ids
+                .iter()
+                .map(|request_id| client.get_result(request_id, None)),
+        )
+        .await;
+
+        assert!(results.iter().all(|result| result.is_ok()));
+        // One request uses the burst, the other three wait 50ms each
+        assert!(started.elapsed() >= Duration::from_millis(140));
+        mock.assert_async().await;
+    }
 }
@@ -0,0 +1,1532 @@
+use crate::error::{Error, Result};
+use image::{ImageError, ImageFormat, ImageReader, Limits};
+use std::io::{BufReader, Read, Seek, SeekFrom};
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// Largest `moov` box read into memory to list the tracks of a movie
+const MAX_MOVIE_BOX_SIZE: u64 = 64 * 1024 * 1024;
+/// Memory the decoder may allocate to check that an image decodes, 512 MiB
+const MAX_DECODE_MEMORY: u64 = 512 * 1024 * 1024;
+/// Number of bytes at the end of a JPEG searched for its end-of-image marker
+const JPEG_TAIL_LEN: u64 = 4096;
+/// Number of bytes at the end of an Ogg stream searched for its last page
+const OGG_TAIL_LEN: u64 = 64 * 1024;
+
+/// Properties of media read locally from its headers
+#[derive(Debug, Clone, PartialEq)]
+pub struct MediaInfo {
+    /// MIME type the content was inspected as
+    pub mime_type: &'static str,
+
+    /// Size of the content in bytes
+    pub size: u64,
+
+    /// Properties specific to the kind of media
+    pub details: MediaDetails,
+}
+
+/// Properties specific to a kind of media
+#[derive(Debug, Clone, PartialEq)]
+pub enum MediaDetails {
+    /// An image and its dimensions in pixels
+    Image { width: u32, height: u32 },
+
+    /// An audio stream. Properties the format does not record are `None`.
+    /// MP3 and AAC durations come from a Xing, Info or VBRI header or from
+    /// counting frames, so ADIF AAC and free-format MP3 without such a
+    /// header have no duration.
+    Audio {
+        duration: Option<Duration>,
+        sample_rate: Option<u32>,
+        channels: Option<u16>,
+    },
+
+    /// A video container and its tracks
+    Video {
+        duration: Option<Duration>,
+        tracks: Vec<MediaTrack>,
+    },
+
+    /// UTF-8 text
+    Text,
+}
+
+/// Kind of a track in a video container
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TrackKind {
+    Video,
+    Audio,
+
+    /// Any other track, identified by its handler type, e.g. `text`
+    Other(String),
+}
+
+/// A track of a video container
+#[derive(Debug, Clone, PartialEq)]
+pub struct MediaTrack {
+    /// Identifier of the track in the container
+    pub id: u32,
+
+    /// Kind of the track
+    pub kind: TrackKind,
+
+    /// Codec of the track's samples, e.g. `avc1` or `mp4a`
+    pub codec: Option<String>,
+
+    /// Duration of the track
+    pub duration: Option<Duration>,
+
+    /// Width and height of video tracks, in pixels
+    pub dimensions: Option<(u32, u32)>,
+
+    /// Sample rate of audio tracks
+    pub sample_rate: Option<u32>,
+
+    /// Number of channels of audio tracks
+    pub channels: Option<u16>,
+}
+
+/// Inspect a file on disk as content of the given MIME type
+pub(crate) async fn inspect_path(
+    path: PathBuf,
+    len: u64,
+    mime_type: &'static str,
+    name: String,
+) -> Result<MediaInfo> {
+    tokio::task::spawn_blocking(move || {
+        let file = std::fs::File::open(&path)?;
+        inspect_media(file, len, mime_type, &name)
+    })
+    .await
+    .map_err(|err| Error::UnknownError(format!("Media inspection failed: {err}")))?
+}
+
+/// Inspect `len` bytes of content as the given MIME type, rejecting media
+/// whose headers or container structure are broken or truncated, and images
+/// that do not decode. Compressed audio and video data is not decoded.
+/// `name` identifies the content in errors.
+pub(crate) fn inspect_media<R: Read + Seek>(
+    reader: R,
+    len: u64,
+    mime_type: &'static str,
+    name: &str,
+) -> Result<MediaInfo> {
+    let mut source = Source { reader, len };
+    let details = match mime_type {
+        "image/png" => inspect_png(&mut source)
+            .and_then(|details| decoded(&mut source, ImageFormat::Png, details)),
+        "image/jpeg" => inspect_jpeg(&mut source)
+            .and_then(|details| decoded(&mut source, ImageFormat::Jpeg, details)),
+        "image/gif" => inspect_gif(&mut source)
+            .and_then(|details| decoded(&mut source, ImageFormat::Gif, details)),
+        "image/webp" => inspect_webp(&mut source)
+            .and_then(|details| decoded(&mut source, ImageFormat::WebP, details)),
+        "audio/wav" => inspect_wav(&mut source),
+        "audio/flac" => inspect_flac(&mut source),
+        "audio/mpeg" | "audio/aac" => inspect_mpeg_audio(&mut source),
+        "audio/ogg" => inspect_ogg(&mut source),
+        "audio/x-caf" => inspect_caf(&mut source),
+        "audio/mp4" => inspect_movie(&mut source).and_then(movie_audio),
+        "video/mp4" | "video/quicktime" => inspect_movie(&mut source),
+        "text/plain" => inspect_text(&mut source),
+        other => Err(invalid(format!("{other} content cannot be inspected"))),
+    };
+
+    let details = details.map_err(|err| match err {
+        Error::InvalidFile(reason) => {
+            Error::InvalidFile(format!("Invalid media in {name}: {reason}"))
+        }
+        other => other,
+    })?;
+
+    Ok(MediaInfo {
+        mime_type,
+        size: len,
+        details,
+    })
+}
+
+/// Content read at arbitrary offsets, failing on reads past its end
+struct Source<R> {
+    reader: R,
+    len: u64,
+}
+
+impl<R: Read + Seek> Source<R> {
+    /// Read `count` bytes of the part of the content named `what`
+    fn read_at(&mut self, offset: u64, count: u64, what: &str) -> Result<Vec<u8>> {
+        if offset.checked_add(count).is_none_or(|end| end > self.len) {
+            return Err(truncated(what, offset, count, self.len));
+        }
+
+        let mut buffer = vec![0; count as usize];
+        self.reader.seek(SeekFrom::Start(offset))?;
+        self.reader.read_exact(&mut buffer)?;
+        Ok(buffer)
+    }
+}
+
+fn invalid(reason: impl Into<String>) -> Error {
+    Error::InvalidFile(reason.into())
+}
+
+fn truncated(what: &str, offset: u64, count: u64, len: u64) -> Error {
+    invalid(format!(
+        "{what} at offset {offset} needs {count} bytes but the content ends after {len} bytes"
+    ))
+}
+
+fn fourcc(bytes: &[u8]) -> String {
+    String::from_utf8_lossy(bytes).into_owned()
+}
+
+fn be16(bytes: &[u8]) -> u16 {
+    u16::from_be_bytes([bytes[0], bytes[1]])
+}
+
+fn be32(bytes: &[u8]) -> u32 {
+    u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])
+}
+
+fn be64(bytes: &[u8]) -> u64 {
+    u64::from_be_bytes(bytes[..8].try_into().unwrap())
+}
+
+fn le16(bytes: &[u8]) -> u16 {
+    u16::from_le_bytes([bytes[0], bytes[1]])
+}
+
+fn le24(bytes: &[u8]) -> u32 {
+    u32::from_le_bytes([bytes[0], bytes[1], bytes[2], 0])
+}
+
+fn le32(bytes: &[u8]) -> u32 {
+    u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])
+}
+
+fn image(width: u32, height: u32) -> Result<MediaDetails> {
+    if width == 0 || height == 0 {
+        return Err(invalid(format!(
+            "the image has invalid dimensions {width}x{height}"
+        )));
+    }
+    Ok(MediaDetails::Image { width, height })
+}
+
+/// Decode an image whose structure was checked, so damage inside its
+/// compressed data is found too. The first frame of an animation is decoded.
+fn decoded<R: Read + Seek>(
+    source: &mut Source<R>,
+    format: ImageFormat,
+    details: MediaDetails,
+) -> Result<MediaDetails> {
+    let mut limits = Limits::default();
+    limits.max_alloc = Some(MAX_DECODE_MEMORY);
+
+    source.reader.seek(SeekFrom::Start(0))?;
+    let mut reader = ImageReader::with_format(BufReader::new(&mut source.reader), format);
+    reader.limits(limits);
+    let image = reader.decode().map_err(|err| match err {
+        ImageError::Limits(_) => invalid("the image needs more memory than allowed to decode"),
+        ImageError::IoError(err) if err.kind() == std::io::ErrorKind::UnexpectedEof => {
+            invalid("the image data is truncated")
+        }
+        other => invalid(format!("the image data cannot be decoded: {other}")),
+    })?;
+
+    if let MediaDetails::Image { width, height } = details {
+        if (image.width(), image.height()) != (width, height) {
+            return Err(invalid(format!(
+                "the image decodes to {}x{} but its header says {width}x{height}",
+                image.width(),
+                image.height()
+            )));
+        }
+    }
+    Ok(details)
+}
+
+fn duration(units: u64, rate: u64) -> Option<Duration> {
+    (rate > 0).then(|| Duration::from_secs_f64(units as f64 / rate as f64))
+}
+
+fn inspect_png<R: Read + Seek>(source: &mut Source<R>) -> Result<MediaDetails> {
+    // The signature is followed by the IHDR chunk holding the dimensions
+    let header = source.read_at(0, 33, "PNG header")?;
+    if &header[..8] != b"\x89PNG\r\n\x1A\n" {
+        return Err(invalid("the PNG signature is missing"));
+    }
+    if &header[12..16] != b"IHDR" || be32(&header[8..]) != 13 {
+        return Err(invalid("the first PNG chunk is not a valid IHDR chunk"));
+    }
+    let details = image(be32(&header[16..]), be32(&header[20..]))?;
+
+    let mut offset = 33;
+    let mut has_data = false;
+    loop {
+        if offset == source.len {
+            return Err(invalid("the PNG ends without an IEND chunk"));
+        }
+
+        let chunk = source.read_at(offset, 8, "PNG chunk header")?;
+        let size = 12 + be32(&chunk) as u64;
+        if offset + size > source.len {
+            return Err(truncated(
+                &format!("PNG chunk '{}'", fourcc(&chunk[4..])),
+                offset,
+                size,
+                source.len,
+            ));
+        }
+
+        match &chunk[4..] {
+            b"IDAT" => has_data = true,
+            b"IEND" => break,
+            _ => {}
+        }
+        offset += size;
+    }
+
+    if !has_data {
+        return Err(invalid("the PNG has no image data"));
+    }
+    Ok(details)
+}
+
+fn inspect_jpeg<R: Read + Seek>(source: &mut Source<R>) -> Result<MediaDetails> {
+    if source.read_at(0, 2, "JPEG header")? != [0xFF, 0xD8] {
+        return Err(invalid("the JPEG start-of-image marker is missing"));
+    }
+
+    // Walk the segments up to the start of the compressed image data
+    let mut offset = 2;
+    let mut dimensions = None;
+    loop {
+        let marker = source.read_at(offset, 2, "JPEG marker")?;
+        if marker[0] != 0xFF {
+            return Err(invalid(format!(
+                "expected a JPEG marker at offset {offset}"
+            )));
+        }
+
+        match marker[1] {
+            // Fill bytes may precede any marker
+            0xFF => {
+                offset += 1;
+                continue;
+            }
+            0x01 | 0xD0..=0xD8 => {
+                offset += 2;
+                continue;
+            }
+            0xD9 => return Err(invalid("the JPEG ends before its image data")),
+            _ => {}
+        }
+
+        let length = be16(&source.read_at(offset + 2, 2, "JPEG segment length")?) as u64;
+        if length < 2 {
+            return Err(invalid(format!(
+                "the JPEG segment at offset {offset} has an invalid length"
+            )));
+        }
+
+        // Start-of-frame markers, excluding DHT, JPG and DAC
+        if matches!(marker[1], 0xC0..=0xCF) && !matches!(marker[1], 0xC4 | 0xC8 | 0xCC) {
+            let frame = source.read_at(offset + 4, 5, "JPEG frame header")?;
+            dimensions = Some((be16(&frame[3..]) as u32, be16(&frame[1..]) as u32));
+        }
+        if marker[1] == 0xDA {
+            break;
+        }
+        offset += 2 + length;
+    }
+
+    let (width, height) =
+        dimensions.ok_or_else(|| invalid("the JPEG has no frame header before its image data"))?;
+    let details = image(width, height)?;
+
+    // The compressed data runs until the end-of-image marker
+    let tail_start = source.len.saturating_sub(JPEG_TAIL_LEN).max(offset);
+    let tail = source.read_at(tail_start, source.len - tail_start, "JPEG image data")?;
+    if !tail.windows(2).any(|bytes| bytes == [0xFF, 0xD9]) {
+        return Err(invalid("the JPEG ends without an end-of-image marker"));
+    }
+    Ok(details)
+}
+
+fn inspect_gif<R: Read + Seek>(source: &mut Source<R>) -> Result<MediaDetails> {
+    let header = source.read_at(0, 13, "GIF header")?;
+    if &header[..6] != b"GIF87a" && &header[..6] != b"GIF89a" {
+        return Err(invalid("the GIF signature is missing"));
+    }
+    let details = image(le16(&header[6..]) as u32, le16(&header[8..]) as u32)?;
+
+    if source.len == 13 || source.read_at(source.len - 1, 1, "GIF trailer")? != [0x3B] {
+        return Err(invalid("the GIF ends without a trailer"));
+    }
+    Ok(details)
+}
+
+/// Check that a RIFF file holds as many bytes as its header announces
+fn check_riff<R: Read + Seek>(source: &mut Source<R>, form: &[u8; 4]) -> Result<()> {
+    let header = source.read_at(0, 12, "RIFF header")?;
+    if &header[..4] != b"RIFF" || &header[8..] != form {
+        return Err(invalid(format!(
+            "the RIFF {} header is missing",
+            fourcc(form).trim()
+        )));
+    }
+
+    let size = 8 + le32(&header[4..]) as u64;
+    if size > source.len {
+        return Err(truncated("RIFF data", 0, size, source.len));
+    }
+    Ok(())
+}
+
+fn inspect_webp<R: Read + Seek>(source: &mut Source<R>) -> Result<MediaDetails> {
+    check_riff(source, b"WEBP")?;
+
+    // The first chunk holds the dimensions of the image
+    let chunk = source.read_at(12, 18, "WebP chunk")?;
+    let data = &chunk[8..];
+    let (width, height) = match &chunk[..4] {
+        b"VP8 " => {
+            if data[3..6] != [0x9D, 0x01, 0x2A] {
+                return Err(invalid("the VP8 start code is missing"));
+            }
+            (
+                (le16(&data[6..]) & 0x3FFF) as u32,
+                (le16(&data[8..]) & 0x3FFF) as u32,
+            )
+        }
+        b"VP8L" => {
+            if data[0] != 0x2F {
+                return Err(invalid("the VP8L signature is missing"));
+            }
+            let bits = le32(&data[1..]);
+            ((bits & 0x3FFF) + 1, ((bits >> 14) & 0x3FFF) + 1)
+        }
+        b"VP8X" => (le24(&data[4..]) + 1, le24(&data[7..]) + 1),
+        other => {
+            return Err(invalid(format!(
+                "the WebP chunk '{}' is not an image",
+                fourcc(other)
+            )))
+        }
+    };
+    image(width, height)
+}
+
+fn inspect_wav<R: Read + Seek>(source: &mut Source<R>) -> Result<MediaDetails> {
+    check_riff(source, b"WAVE")?;
+
+    let mut offset = 12;
+    let mut format = None;
+    let mut data_size = None;
+    while offset + 8 <= source.len {
+        let chunk = source.read_at(offset, 8, "WAV chunk header")?;
+        let size = le32(&chunk[4..]) as u64;
+
+        match &chunk[..4] {
+            b"fmt " => {
+                let fmt = source.read_at(offset + 8, 16, "WAV format chunk")?;
+                format = Some((le16(&fmt[2..]), le32(&fmt[4..]), le32(&fmt[8..])));
+            }
+            b"data" => {
+                if offset + 8 + size > source.len {
+                    return Err(truncated("WAV data chunk", offset, 8 + size, source.len));
+                }
+                data_size = Some(size);
+            }
+            _ => {}
+        }
+
+        // Chunks are padded to an even size
+        offset += 8 + size + (size & 1);
+    }
+
+    let (channels, sample_rate, byte_rate) =
+        format.ok_or_else(|| invalid("the WAV has no format chunk"))?;
+    let data_size = data_size.ok_or_else(|| invalid("the WAV has no data chunk"))?;
+    if channels == 0 || sample_rate == 0 {
+        return Err(invalid(
+            "the WAV format chunk has no channels or sample rate",
+        ));
+    }
+
+    Ok(MediaDetails::Audio {
+        duration: duration(data_size, byte_rate as u64),
+        sample_rate: Some(sample_rate),
+        channels: Some(channels),
+    })
+}
+
+fn inspect_flac<R: Read + Seek>(source: &mut Source<R>) -> Result<MediaDetails> {
+    if source.read_at(0, 4, "FLAC header")? != b"fLaC" {
+        return Err(invalid("the FLAC signature is missing"));
+    }
+
+    let mut offset = 4;
+    let mut stream_info = None;
+    loop {
+        let header = source.read_at(offset, 4, "FLAC metadata block header")?;
+        let size = 4 + u32::from_be_bytes([0, header[1], header[2], header[3]]) as u64;
+
+        // The stream info block always comes first
+        if stream_info.is_none() {
+            if header[0] & 0x7F != 0 || size < 38 {
+                return Err(invalid("the first FLAC metadata block is not stream info"));
+            }
+            let info = source.read_at(offset + 4, 34, "FLAC stream info")?;
+            let sample_rate =
+                (info[10] as u32) << 12 | (info[11] as u32) << 4 | (info[12] as u32) >> 4;
+            let channels = ((info[12] >> 1) & 0x07) as u16 + 1;
+            let samples = ((info[13] & 0x0F) as u64) << 32 | be32(&info[14..]) as u64;
+            stream_info = Some((sample_rate, channels, samples));
+        }
+
+        if offset + size > source.len {
+            return Err(truncated("FLAC metadata block", offset, size, source.len));
+        }
+        offset += size;
+
+        if header[0] & 0x80 != 0 {
+            break;
+        }
+    }
+
+    if offset == source.len {
+        return Err(invalid("the FLAC has no audio frames"));
+    }
+
+    let (sample_rate, channels, samples) = stream_info.unwrap_or_default();
+    if sample_rate == 0 {
+        return Err(invalid("the FLAC stream info has no sample rate"));
+    }
+
+    Ok(MediaDetails::Audio {
+        // A total of zero samples means the length is unknown
+        duration: duration(samples, sample_rate as u64).filter(|_| samples > 0),
+        sample_rate: Some(sample_rate),
+        channels: Some(channels),
+    })
+}
+
+/// Sample rates of ADTS frames by their sampling frequency index
+const ADTS_SAMPLE_RATES: [u32; 13] = [
+    96000, 88200, 64000, 48000, 44100, 32000, 24000, 22050, 16000, 12000, 11025, 8000, 7350,
+];
+
+/// Bitrates in kbit/s of MPEG-1 layers I, II and III, then of MPEG-2 and
+/// MPEG-2.5 layer I and layers II and III, by bitrate index. Index 0 is the
+/// free format, whose frame length is not recorded.
+const MPEG_BITRATES: [[u32; 15]; 5] = [
+    [
+        0, 32, 64, 96, 128, 160, 192, 224, 256, 288, 320, 352, 384, 416, 448,
+    ],
+    [
+        0, 32, 48, 56, 64, 80, 96, 112, 128, 160, 192, 224, 256, 320, 384,
+    ],
+    [
+        0, 32, 40, 48, 56, 64, 80, 96, 112, 128, 160, 192, 224, 256, 320,
+    ],
+    [
+        0, 32, 48, 56, 64, 80, 96, 112, 128, 144, 160, 176, 192, 224, 256,
+    ],
+    [0, 8, 16, 24, 32, 40, 48, 56, 64, 80, 96, 112, 128, 144, 160],
+];
+
+/// Fields of an MPEG audio frame header
+struct MpegFrame {
+    sample_rate: u32,
+    channels: u16,
+
+    /// Samples per channel in the frame
+    samples: u64,
+
+    /// Length of the frame in bytes, unknown in the free format
+    len: Option<u64>,
+
+    /// Offset of a Xing or Info header in the first frame
+    xing_offset: u64,
+}
+
+/// Parse an MPEG-1, MPEG-2 or MPEG-2.5 audio frame header
+fn mpeg_frame(header: &[u8]) -> Result<MpegFrame> {
+    if header[0] != 0xFF || header[1] & 0xE0 != 0xE0 {
+        return Err(invalid("the MPEG audio frame sync is missing"));
+    }
+
+    // Versions 3, 2 and 0 are MPEG-1, MPEG-2 and MPEG-2.5, which halve the
+    // sample rate. Layers 3, 2 and 1 are layers I, II and III.
+    let version = (header[1] >> 3) & 0x03;
+    let layer = (header[1] >> 1) & 0x03;
+    let bitrate_index = header[2] >> 4;
+    let rate_index = (header[2] >> 2) & 0x03;
+    if version == 1 || layer == 0 || bitrate_index == 15 || rate_index == 3 {
+        return Err(invalid("the MPEG audio frame header uses reserved values"));
+    }
+
+    let base = [44100, 48000, 32000][rate_index as usize];
+    let sample_rate = match version {
+        3 => base,
+        2 => base / 2,
+        _ => base / 4,
+    };
+    let mono = header[3] >> 6 == 3;
+    let mpeg1 = version == 3;
+
+    let table = match (mpeg1, layer) {
+        (true, 3) => 0,
+        (true, 2) => 1,
+        (true, _) => 2,
+        (false, 3) => 3,
+        (false, _) => 4,
+    };
+    let bitrate = MPEG_BITRATES[table][bitrate_index as usize] as u64 * 1000;
+    let padding = ((header[2] >> 1) & 0x01) as u64;
+    let (samples, len) = match layer {
+        3 => (384, (12 * bitrate / sample_rate as u64 + padding) * 4),
+        2 => (1152, 144 * bitrate / sample_rate as u64 + padding),
+        _ if mpeg1 => (1152, 144 * bitrate / sample_rate as u64 + padding),
+        _ => (576, 72 * bitrate / sample_rate as u64 + padding),
+    };
+
+    // The Xing header follows the side information of layer III
+    let side_info = match (mpeg1, mono) {
+        (true, false) => 32,
+        (true, true) | (false, false) => 17,
+        (false, true) => 9,
+    };
+
+    Ok(MpegFrame {
+        sample_rate,
+        channels: if mono { 1 } else { 2 },
+        samples,
+        len: (bitrate > 0).then_some(len),
+        xing_offset: 4 + side_info,
+    })
+}
+
+/// Read `count` bytes at `offset`, or `None` when the content ends first
+fn peek<R: Read + Seek>(
+    source: &mut Source<R>,
+    offset: u64,
+    count: u64,
+) -> Result<Option<Vec<u8>>> {
+    if offset.checked_add(count).is_none_or(|end| end > source.len) {
+        return Ok(None);
+    }
+    source.read_at(offset, count, "").map(Some)
+}
+
+/// Number of frames recorded in the Xing, Info or VBRI header of the first
+/// MPEG audio frame, which encoders write to give the length of the stream
+fn vbr_frame_count<R: Read + Seek>(
+    source: &mut Source<R>,
+    offset: u64,
+    frame: &MpegFrame,
+) -> Result<Option<u64>> {
+    if let Some(xing) = peek(source, offset + frame.xing_offset, 12)? {
+        if (&xing[..4] == b"Xing" || &xing[..4] == b"Info") && be32(&xing[4..]) & 0x01 != 0 {
+            return Ok(Some(be32(&xing[8..]) as u64));
+        }
+    }
+    if let Some(vbri) = peek(source, offset + 36, 18)? {
+        if &vbri[..4] == b"VBRI" {
+            return Ok(Some(be32(&vbri[14..]) as u64));
+        }
+    }
+    Ok(None)
+}
+
+/// Count the samples of the complete frames from `offset` on, stopping at
+/// the first bytes that are not a frame, such as a trailing ID3v1 tag.
+/// `frame` gives the length and samples of the frame starting with a header.
+fn scan_frames<R: Read + Seek>(
+    source: &mut Source<R>,
+    mut offset: u64,
+    header_len: u64,
+    frame: impl Fn(&[u8]) -> Option<(u64, u64)>,
+) -> Result<u64> {
+    let mut samples = 0;
+    while let Some(header) = peek(source, offset, header_len)? {
+        match frame(&header) {
+            Some((len, frame_samples)) if len >= header_len && offset + len <= source.len => {
+                samples += frame_samples;
+                offset += len;
+            }
+            _ => break,
+        }
+    }
+    Ok(samples)
+}
+
+fn inspect_mpeg_audio<R: Read + Seek>(source: &mut Source<R>) -> Result<MediaDetails> {
+    let start = source.read_at(0, source.len.min(10), "MPEG audio header")?;
+
+    // ADIF files only describe their stream in a variable-length header
+    if start.starts_with(b"ADIF") {
+        return Ok(MediaDetails::Audio {
+            duration: None,
+            sample_rate: None,
+            channels: None,
+        });
+    }
+
+    // Skip an ID3v2 tag, whose size is stored in 7-bit bytes
+    let mut offset = 0;
+    if start.starts_with(b"ID3") {
+        let tag = source.read_at(0, 10, "ID3 tag header")?;
+        let size = tag[6..10]
+            .iter()
+            .fold(0u64, |size, byte| size << 7 | (byte & 0x7F) as u64);
+        let footer = if tag[5] & 0x10 != 0 { 10 } else { 0 };
+        offset = 10 + size + footer;
+    }
+
+    let header = source.read_at(offset, 4, "MPEG audio frame header")?;
+    if header[0] != 0xFF || header[1] & 0xE0 != 0xE0 {
+        return Err(invalid(format!(
+            "no MPEG audio frame starts at offset {offset}"
+        )));
+    }
+
+    if header[1] & 0x06 == 0 {
+        // ADTS frames of AAC, holding 1024 samples per raw data block
+        let sample_rate = *ADTS_SAMPLE_RATES
+            .get(((header[2] >> 2) & 0x0F) as usize)
+            .ok_or_else(|| invalid("the ADTS frame has a reserved sampling frequency"))?;
+        let channels = ((header[2] & 0x01) << 2 | header[3] >> 6) as u16;
+        let samples = scan_frames(source, offset, 7, |header| {
+            if header[0] != 0xFF || header[1] & 0xF6 != 0xF0 {
+                return None;
+            }
+            let len = ((header[3] & 0x03) as u64) << 11
+                | (header[4] as u64) << 3
+                | (header[5] >> 5) as u64;
+            Some((len, ((header[6] & 0x03) as u64 + 1) * 1024))
+        })?;
+
+        return Ok(MediaDetails::Audio {
+            duration: duration(samples, sample_rate as u64).filter(|_| samples > 0),
+            sample_rate: Some(sample_rate),
+            channels: (channels > 0).then_some(channels),
+        });
+    }
+
+    let frame = mpeg_frame(&header)?;
+    let samples = match vbr_frame_count(source, offset, &frame)? {
+        Some(frames) => frames * frame.samples,
+        None if frame.len.is_some() => scan_frames(source, offset, 4, |header| {
+            let next = mpeg_frame(header).ok()?;
+            // Every frame of a stream has the sample rate of the first
+            (next.sample_rate == frame.sample_rate).then_some((next.len?, next.samples))
+        })?,
+        None => 0,
+    };
+
+    Ok(MediaDetails::Audio {
+        duration: duration(samples, frame.sample_rate as u64).filter(|_| samples > 0),
+        sample_rate: Some(frame.sample_rate),
+        channels: Some(frame.channels),
+    })
+}
+
+fn inspect_ogg<R: Read + Seek>(source: &mut Source<R>) -> Result<MediaDetails> {
+    let page = source.read_at(0, 27, "Ogg page header")?;
+    if &page[..4] != b"OggS" {
+        return Err(invalid("the Ogg capture pattern is missing"));
+    }
+    let segments = page[26] as u64;
+    let table = source.read_at(27, segments, "Ogg segment table")?;
+    let packet_len = table.iter().map(|&len| len as u64).sum::<u64>();
+    let packet = source.read_at(27 + segments, packet_len, "Ogg identification packet")?;
+
+    // Granule positions count samples at the stream's rate, 48 kHz for Opus
+    let (sample_rate, channels, granule_rate, pre_skip) =
+        if packet.len() >= 16 && packet.starts_with(b"\x01vorbis") {
+            let sample_rate = le32(&packet[12..]);
+            (Some(sample_rate), Some(packet[11] as u16), sample_rate, 0)
+        } else if packet.len() >= 16 && packet.starts_with(b"OpusHead") {
+            let pre_skip = le16(&packet[10..]) as u64;
+            (
+                Some(le32(&packet[12..])),
+                Some(packet[9] as u16),
+                48000,
+                pre_skip,
+            )
+        } else {
+            (None, None, 0, 0)
+        };
+
+    // The last page holds the final granule position and must be complete
+    let tail_start = source.len.saturating_sub(OGG_TAIL_LEN);
+    let tail = source.read_at(tail_start, source.len - tail_start, "Ogg pages")?;
+    let last = tail
+        .windows(5)
+        .rposition(|bytes| bytes == b"OggS\x00")
+        .ok_or_else(|| invalid("no Ogg page starts near the end of the stream"))?;
+    let last_offset = tail_start + last as u64;
+    let page = source.read_at(last_offset, 27, "Ogg page header")?;
+    let segments = page[26] as u64;
+    let table = source.read_at(last_offset + 27, segments, "Ogg segment table")?;
+    let page_len = 27 + segments + table.iter().map(|&len| len as u64).sum::<u64>();
+    if last_offset + page_len > source.len {
+        return Err(truncated("Ogg page", last_offset, page_len, source.len));
+    }
+
+    let granule = u64::from_le_bytes(page[6..14].try_into().unwrap());
+    let duration = if granule == u64::MAX {
+        None
+    } else {
+        duration(granule.saturating_sub(pre_skip), granule_rate as u64)
+    };
+
+    Ok(MediaDetails::Audio {
+        duration,
+        sample_rate,
+        channels,
+    })
+}
+
+fn inspect_caf<R: Read + Seek>(source: &mut Source<R>) -> Result<MediaDetails> {
+    if source.read_at(0, 8, "CAF header")?[..4] != *b"caff" {
+        return Err(invalid("the CAF signature is missing"));
+    }
+
+    let mut offset = 8;
+    let mut description = None;
+    let mut data_size = None;
+    let mut valid_frames = None;
+    while offset < source.len {
+        let chunk = source.read_at(offset, 12, "CAF chunk header")?;
+        let size = be64(&chunk[4..]);
+
+        // Only a final data chunk may leave its size unset
+        if &chunk[..4] == b"data" && size == u64::MAX {
+            data_size = Some(source.len - offset - 12);
+            break;
+        }
+        if size
+            .checked_add(offset + 12)
+            .is_none_or(|end| end > source.len)
+        {
+            return Err(truncated(
+                &format!("CAF chunk '{}'", fourcc(&chunk[..4])),
+                offset,
+                12 + size,
+                source.len,
+            ));
+        }
+
+        match &chunk[..4] {
+            b"desc" => {
+                let desc = source.read_at(offset + 12, 32, "CAF audio description")?;
+                description = Some((
+                    f64::from_bits(be64(&desc)),
+                    be32(&desc[16..]) as u64,
+                    be32(&desc[20..]) as u64,
+                    be32(&desc[24..]),
+                ));
+            }
+            b"data" => data_size = Some(size),
+            b"pakt" => {
+                let table = source.read_at(offset + 12, 16, "CAF packet table")?;
+                valid_frames = Some(be64(&table[8..]));
+            }
+            _ => {}
+        }
+        offset += 12 + size;
+    }
+
+    let (sample_rate, bytes_per_packet, frames_per_packet, channels) =
+        description.ok_or_else(|| invalid("the CAF has no audio description chunk"))?;
+    let data_size = data_size.ok_or_else(|| invalid("the CAF has no data chunk"))?;
+    if !sample_rate.is_normal() || sample_rate < 1.0 {
+        return Err(invalid("the CAF audio description has no sample rate"));
+    }
+
+    // Data starts with an edit count, and constant-size packets give the
+    // number of frames when there is no packet table
+    let frames = valid_frames.or_else(|| {
+        (bytes_per_packet > 0 && frames_per_packet > 0)
+            .then(|| data_size.saturating_sub(4) / bytes_per_packet * frames_per_packet)
+    });
+
+    Ok(MediaDetails::Audio {
+        duration: frames.map(|frames| Duration::from_secs_f64(frames as f64 / sample_rate)),
+        sample_rate: Some(sample_rate as u32),
+        channels: u16::try_from(channels).ok(),
+    })
+}
+
+/// Walk the top-level boxes of an ISO base media file, checking that each
+/// one fits in the content, and parse the tracks listed in its `moov` box
+fn inspect_movie<R: Read + Seek>(source: &mut Source<R>) -> Result<MediaDetails> {
+    let mut offset = 0;
+    let mut movie_box = None;
+    while offset < source.len {
+        let header = source.read_at(offset, 8, "box header")?;
+        let kind = fourcc(&header[4..]);
+        let (size, header_len) = match be32(&header) {
+            0 => (source.len - offset, 8),
+            1 => (be64(&source.read_at(offset + 8, 8, "box size")?), 16),
+            size => (size as u64, 8),
+        };
+
+        if size < header_len {
+            return Err(invalid(format!(
+                "box '{kind}' at offset {offset} has an invalid size of {size} bytes"
+            )));
+        }
+        if offset.checked_add(size).is_none_or(|end| end > source.len) {
+            return Err(truncated(
+                &format!("box '{kind}'"),
+                offset,
+                size,
+                source.len,
+            ));
+        }
+
+        if &header[4..] == b"moov" {
+            movie_box = Some((offset + header_len, size - header_len));
+        }
+        offset += size;
+    }
+
+    let (start, size) =
+        movie_box.ok_or_else(|| invalid("there is no 'moov' box describing the tracks"))?;
+    if size > MAX_MOVIE_BOX_SIZE {
+        return Err(invalid("the 'moov' box is too large to inspect"));
+    }
+    let movie = source.read_at(start, size, "box 'moov'")?;
+
+    let mut duration = None;
+    let mut tracks = Vec::new();
+    for (kind, payload) in child_boxes(&movie)? {
+        match kind {
+            b"mvhd" => duration = header_duration(payload, "mvhd")?,
+            b"trak" => tracks.push(parse_track(payload)?),
+            _ => {}
+        }
+    }
+
+    if tracks.is_empty() {
+        return Err(invalid("the 'moov' box lists no tracks"));
+    }
+    Ok(MediaDetails::Video { duration, tracks })
+}
+
+/// Audio properties of a movie holding an audio track, such as an m4a file
+fn movie_audio(details: MediaDetails) -> Result<MediaDetails> {
+    let (duration, tracks) = match details {
+        MediaDetails::Video { duration, tracks } => (duration, tracks),
+        other => return Ok(other),
+    };
+
+    let track = tracks
+        .into_iter()
+        .find(|track| track.kind == TrackKind::Audio)
+        .ok_or_else(|| invalid("the file has no audio track"))?;
+    Ok(MediaDetails::Audio {
+        duration: duration.or(track.duration),
+        sample_rate: track.sample_rate,
+        channels: track.channels,
+    })
+}
+
+/// Boxes nested in the payload of a box, as their type and payload
+fn child_boxes(data: &[u8]) -> Result<Vec<(&[u8; 4], &[u8])>> {
+    let mut boxes = Vec::new();
+    let mut offset = 0;
+    while offset < data.len() {
+        let header = data
+            .get(offset..offset + 8)
+            .ok_or_else(|| invalid("a box header is cut off inside the 'moov' box"))?;
+        let kind: &[u8; 4] = header[4..].try_into().unwrap();
+        let (size, header_len) = match be32(header) {
+            0 => (data.len() - offset, 8),
+            1 => {
+                let size = data
+                    .get(offset + 8..offset + 16)
+                    .ok_or_else(|| invalid("a box header is cut off inside the 'moov' box"))?;
+                (usize::try_from(be64(size)).unwrap_or(usize::MAX), 16)
+            }
+            size => (size as usize, 8),
+        };
+
+        if size < header_len || offset.checked_add(size).is_none_or(|end| end > data.len()) {
+            return Err(invalid(format!(
+                "box '{}' does not fit inside its parent box",
+                fourcc(kind)
+            )));
+        }
+        boxes.push((kind, &data[offset + header_len..offset + size]));
+        offset += size;
+    }
+    Ok(boxes)
+}
+
+/// Duration recorded in an `mvhd` or `mdhd` box, which share their layout
+fn header_duration(payload: &[u8], kind: &str) -> Result<Option<Duration>> {
+    let (timescale, units) = match payload.first() {
+        Some(0) if payload.len() >= 20 => {
+            let units = be32(&payload[16..]);
+            (
+                be32(&payload[12..]),
+                (units != u32::MAX).then_some(units as u64),
+            )
+        }
+        Some(1) if payload.len() >= 32 => {
+            let units = be64(&payload[24..]);
+            (be32(&payload[20..]), (units != u64::MAX).then_some(units))
+        }
+        _ => return Err(invalid(format!("the '{kind}' box is malformed"))),
+    };
+
+    if timescale == 0 {
+        return Err(invalid(format!("the '{kind}' box has a zero timescale")));
+    }
+    Ok(units.and_then(|units| duration(units, timescale as u64)))
+}
+
+fn parse_track(trak: &[u8]) -> Result<MediaTrack> {
+    let mut track = MediaTrack {
+        id: 0,
+        kind: TrackKind::Other(String::new()),
+        codec: None,
+        duration: None,
+        dimensions: None,
+        sample_rate: None,
+        channels: None,
+    };
+    let mut sample_entry = None;
+
+    for (kind, payload) in child_boxes(trak)? {
+        match kind {
+            b"tkhd" => {
+                // The dimensions are 16.16 fixed-point values closing the box
+                let id_offset = if payload.first() == Some(&1) { 20 } else { 12 };
+                if payload.len() < id_offset + 4 || payload.len() < 84 {
+                    return Err(invalid("the 'tkhd' box is malformed"));
+                }
+                track.id = be32(&payload[id_offset..]);
+                let width = be32(&payload[payload.len() - 8..]) >> 16;
+                let height = be32(&payload[payload.len() - 4..]) >> 16;
+                track.dimensions = Some((width, height)).filter(|_| width > 0 && height > 0);
+            }
+            b"mdia" => {
+                for (kind, payload) in child_boxes(payload)? {
+                    match kind {
+                        b"mdhd" => track.duration = header_duration(payload, "mdhd")?,
+                        b"hdlr" if payload.len() >= 12 => {
+                            track.kind = match &payload[8..12] {
+                                b"vide" => TrackKind::Video,
+                                b"soun" => TrackKind::Audio,
+                                other => TrackKind::Other(fourcc(other)),
+                            };
+                        }
+                        b"minf" => sample_entry = find_sample_entry(payload)?,
+                        _ => {}
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    if let Some(entry) = sample_entry {
+        track.codec = Some(fourcc(&entry[4..8]));
+        // Audio sample entries list the channels and the 16.16 sample rate
+        if track.kind == TrackKind::Audio && entry.len() >= 36 {
+            track.channels = Some(be16(&entry[24..]));
+            track.sample_rate = Some(be32(&entry[32..]) >> 16);
+        }
+    }
+    if track.kind != TrackKind::Video {
+        track.dimensions = None;
+    }
+    Ok(track)
+}
+
+/// First sample entry of the `stsd` box in a `minf` box, including its header
+fn find_sample_entry(minf: &[u8]) -> Result<Option<&[u8]>> {
+    for (kind, payload) in child_boxes(minf)? {
+        if kind != b"stbl" {
+            continue;
+        }
+        for (kind, payload) in child_boxes(payload)? {
+            if kind != b"stsd" {
+                continue;
+            }
+            // Entries follow the version, flags and entry count
+            let entry = payload
+                .get(8..)
+                .filter(|entry| entry.len() >= 8)
+                .ok_or_else(|| invalid("the 'stsd' box has no sample entry"))?;
+            let size = (be32(entry) as usize).min(entry.len());
+            return Ok(Some(&entry[..size.max(8)]));
+        }
+    }
+    Ok(None)
+}
+
+fn inspect_text<R: Read + Seek>(source: &mut Source<R>) -> Result<MediaDetails> {
+    let content = source.read_at(0, source.len, "text")?;
+    if let Err(err) = std::str::from_utf8(&content) {
+        return Err(invalid(format!(
+            "the text is not valid UTF-8 at byte {}",
+            err.valid_up_to()
+        )));
+    }
+    if content.contains(&0) {
+        return Err(invalid("the text contains NUL bytes"));
+    }
+    Ok(MediaDetails::Text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{DynamicImage, Rgb, RgbImage};
+    use std::io::Cursor;
+
+    fn inspect(content: &[u8], mime_type: &'static str) -> Result<MediaDetails> {
+        inspect_media(
+            Cursor::new(content),
+            content.len() as u64,
+            mime_type,
+            "test",
+        )
+        .map(|info| info.details)
+    }
+
+    fn assert_invalid(result: Result<MediaDetails>, reason: &str) {
+        match result {
+            Err(Error::InvalidFile(msg)) => {
+                assert!(msg.starts_with("Invalid media in test: "), "{msg}");
+                assert!(msg.contains(reason), "{msg}");
+            }
+            other => panic!("Expected InvalidFile error, got: {:?}", other),
+        }
+    }
+
+    fn png_chunk(kind: &[u8], data: &[u8]) -> Vec<u8> {
+        let mut chunk = (data.len() as u32).to_be_bytes().to_vec();
+        chunk.extend_from_slice(kind);
+        chunk.extend_from_slice(data);
+        chunk.extend_from_slice(&[0; 4]);
+        chunk
+    }
+
+    fn png() -> Vec<u8> {
+        let mut ihdr = 640u32.to_be_bytes().to_vec();
+        ihdr.extend_from_slice(&480u32.to_be_bytes());
+        ihdr.extend_from_slice(&[8, 2, 0, 0, 0]);
+
+        let mut content = b"\x89PNG\r\n\x1A\n".to_vec();
+        content.extend(png_chunk(b"IHDR", &ihdr));
+        content.extend(png_chunk(b"IDAT", &[0; 64]));
+        content.extend(png_chunk(b"IEND", &[]));
+        content
+    }
+
+    fn encoded_image(width: u32, height: u32, format: ImageFormat) -> Vec<u8> {
+        let image = RgbImage::from_fn(width, height, |x, y| Rgb([x as u8, y as u8, 128]));
+        let mut content = Cursor::new(Vec::new());
+        DynamicImage::ImageRgb8(image)
+            .write_to(&mut content, format)
+            .unwrap();
+        content.into_inner()
+    }
+
+    /// MPEG-1 layer III frame at 128 kbit/s and 44.1 kHz, starting with
+    /// `data` after its header
+    fn mp3_frame(data: &[u8]) -> Vec<u8> {
+        let mut frame = vec![0xFF, 0xFB, 0x90, 0x64];
+        frame.extend_from_slice(data);
+        frame.resize(417, 0);
+        frame
+    }
+
+    /// ADTS frame of AAC at 44.1 kHz in stereo, with a single block
+    fn adts_frame() -> Vec<u8> {
+        let mut frame = vec![0xFF, 0xF1, 0x50, 0x80, 0x0C, 0x9F, 0xFC];
+        frame.resize(100, 0);
+        frame
+    }
+
+    fn jpeg() -> Vec<u8> {
+        let mut content = vec![0xFF, 0xD8];
+        content.extend_from_slice(&[0xFF, 0xE0, 0x00, 0x10]);
+        content.extend_from_slice(b"JFIF\x00\x01\x01\x00\x00\x01\x00\x01\x00\x00");
+        // Baseline frame of 320x200 with a single component
+        content.extend_from_slice(&[0xFF, 0xC0, 0x00, 0x0B, 0x08, 0x00, 0xC8, 0x01, 0x40]);
+        content.extend_from_slice(&[0x01, 0x01, 0x11, 0x00]);
+        content.extend_from_slice(&[0xFF, 0xDA, 0x00, 0x08, 0x01, 0x01, 0x00, 0x00, 0x3F, 0x00]);
+        content.extend_from_slice(&[0x12; 32]);
+        content.extend_from_slice(&[0xFF, 0xD9]);
+        content
+    }
+
+    fn wav(data_len: u32, actual_len: usize) -> Vec<u8> {
+        let mut content = b"RIFF".to_vec();
+        content.extend_from_slice(&(36 + data_len).to_le_bytes());
+        content.extend_from_slice(b"WAVEfmt ");
+        content.extend_from_slice(&16u32.to_le_bytes());
+        // PCM, stereo, 8 kHz, 16 bits
+        content.extend_from_slice(&1u16.to_le_bytes());
+        content.extend_from_slice(&2u16.to_le_bytes());
+        content.extend_from_slice(&8000u32.to_le_bytes());
+        content.extend_from_slice(&32000u32.to_le_bytes());
+        content.extend_from_slice(&4u16.to_le_bytes());
+        content.extend_from_slice(&16u16.to_le_bytes());
+        content.extend_from_slice(b"data");
+        content.extend_from_slice(&data_len.to_le_bytes());
+        content.extend(vec![0; actual_len]);
+        content
+    }
+
+    fn iso_box(kind: &[u8], payload: &[u8]) -> Vec<u8> {
+        let mut content = ((payload.len() + 8) as u32).to_be_bytes().to_vec();
+        content.extend_from_slice(kind);
+        content.extend_from_slice(payload);
+        content
+    }
+
+    fn track(id: u32, handler: &[u8], sample_entry: Vec<u8>) -> Vec<u8> {
+        let mut tkhd = vec![0; 84];
+        tkhd[12..16].copy_from_slice(&id.to_be_bytes());
+        if handler == b"vide" {
+            tkhd[76..80].copy_from_slice(&(640u32 << 16).to_be_bytes());
+            tkhd[80..84].copy_from_slice(&(480u32 << 16).to_be_bytes());
+        }
+
+        let mut mdhd = vec![0; 24];
+        mdhd[12..16].copy_from_slice(&90000u32.to_be_bytes());
+        mdhd[16..20].copy_from_slice(&180000u32.to_be_bytes());
+
+        let mut hdlr = vec![0; 8];
+        hdlr.extend_from_slice(handler);
+        hdlr.extend_from_slice(&[0; 13]);
+
+        let mut stsd = vec![0, 0, 0, 0, 0, 0, 0, 1];
+        stsd.extend(sample_entry);
+
+        let minf = iso_box(b"minf", &iso_box(b"stbl", &iso_box(b"stsd", &stsd)));
+        let mut mdia = iso_box(b"mdhd", &mdhd);
+        mdia.extend(iso_box(b"hdlr", &hdlr));
+        mdia.extend(minf);
+
+        let mut trak = iso_box(b"tkhd", &tkhd);
+        trak.extend(iso_box(b"mdia", &mdia));
+        iso_box(b"trak", &trak)
+    }
+
+    fn mp4(mdat_len: u32, actual_len: usize) -> Vec<u8> {
+        let mut mvhd = vec![0; 100];
+        mvhd[12..16].copy_from_slice(&1000u32.to_be_bytes());
+        mvhd[16..20].copy_from_slice(&2000u32.to_be_bytes());
+
+        let mut video_entry = iso_box(b"avc1", &[0; 8]);
+        video_entry.truncate(16);
+        let mut audio_entry = vec![0; 28];
+        audio_entry[16..18].copy_from_slice(&2u16.to_be_bytes());
+        audio_entry[24..28].copy_from_slice(&(44100u32 << 16).to_be_bytes());
+        let audio_entry = iso_box(b"mp4a", &audio_entry);
+
+        let mut moov = iso_box(b"mvhd", &mvhd);
+        moov.extend(track(1, b"vide", video_entry));
+        moov.extend(track(2, b"soun", audio_entry));
+
+        let mut content = iso_box(b"ftyp", b"isom\x00\x00\x02\x00isomiso2");
+        content.extend(iso_box(b"moov", &moov));
+        content.extend_from_slice(&(mdat_len + 8).to_be_bytes());
+        content.extend_from_slice(b"mdat");
+        content.extend(vec![0; actual_len]);
+        content
+    }
+
+    fn ogg_page(granule: u64, packet: &[u8]) -> Vec<u8> {
+        let mut page = b"OggS\x00\x02".to_vec();
+        page.extend_from_slice(&granule.to_le_bytes());
+        page.extend_from_slice(&[0; 12]);
+        page.push(1);
+        page.push(packet.len() as u8);
+        page.extend_from_slice(packet);
+        page
+    }
+
+    #[test]
+    fn test_inspect_images() {
+        for (mime_type, format, width, height) in [
+            ("image/png", ImageFormat::Png, 64, 48),
+            ("image/jpeg", ImageFormat::Jpeg, 32, 20),
+            ("image/gif", ImageFormat::Gif, 16, 32),
+            ("image/webp", ImageFormat::WebP, 40, 30),
+        ] {
+            assert_eq!(
+                inspect(&encoded_image(width, height, format), mime_type).unwrap(),
+                MediaDetails::Image { width, height },
+                "{mime_type}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_inspect_undecodable_images() {
+        // Intact structures around data that does not decode
+        assert_invalid(
+            inspect(&png(), "image/png"),
+            "the image data cannot be decoded",
+        );
+        assert_invalid(
+            inspect(&jpeg(), "image/jpeg"),
+            "the image data cannot be decoded",
+        );
+
+        let mut gif = b"GIF89a\x10\x00\x20\x00\x00\x00\x00".to_vec();
+        gif.push(0x3B);
+        assert_invalid(inspect(&gif, "image/gif"), "cannot be decoded");
+
+        // A huge image is rejected before its pixels are allocated
+        let mut huge = encoded_image(32, 20, ImageFormat::Jpeg);
+        let frame = huge
+            .windows(2)
+            .position(|bytes| bytes == [0xFF, 0xC0])
+            .unwrap();
+        huge[frame + 5..frame + 9].copy_from_slice(&[0xEA, 0x60, 0xEA, 0x60]);
+        assert_invalid(
+            inspect(&huge, "image/jpeg"),
+            "needs more memory than allowed to decode",
+        );
+    }
+
+    #[test]
+    fn test_inspect_truncated_images() {
+        let png = png();
+        assert_invalid(
+            inspect(&png[..png.len() - 2], "image/png"),
+            "PNG chunk 'IEND' at offset",
+        );
+        assert_invalid(
+            inspect(&png[..png.len() - 12], "image/png"),
+            "ends without an IEND chunk",
+        );
+
+        let jpeg = jpeg();
+        assert_invalid(
+            inspect(&jpeg[..jpeg.len() - 10], "image/jpeg"),
+            "without an end-of-image marker",
+        );
+        assert_invalid(
+            inspect(&jpeg[..26], "image/jpeg"),
+            "JPEG frame header at offset",
+        );
+
+        assert_invalid(
+            inspect(b"GIF89a\x10\x00\x20\x00\x00\x00\x00\x2C", "image/gif"),
+            "without a trailer",
+        );
+        assert_invalid(inspect(b"not a png", "image/png"), "PNG header");
+    }
+
+    #[test]
+    fn test_inspect_audio() {
+        assert_eq!(
+            inspect(&wav(32000, 32000), "audio/wav").unwrap(),
+            MediaDetails::Audio {
+                duration: Some(Duration::from_secs(1)),
+                sample_rate: Some(8000),
+                channels: Some(2),
+            }
+        );
+
+        // 2 seconds of 44.1 kHz stereo
+        let mut flac = b"fLaC\x80\x00\x00\x22".to_vec();
+        let mut info = [0u8; 34];
+        let fields: u64 = 44100 << 44 | 1 << 41 | 15 << 36 | 88200;
+        info[10..18].copy_from_slice(&fields.to_be_bytes());
+        flac.extend_from_slice(&info);
+        flac.extend_from_slice(&[0xFF, 0xF8, 0x00, 0x00]);
+        assert_eq!(
+            inspect(&flac, "audio/flac").unwrap(),
+            MediaDetails::Audio {
+                duration: Some(Duration::from_secs(2)),
+                sample_rate: Some(44100),
+                channels: Some(2),
+            }
+        );
+
+        let mp3 = b"ID3\x04\x00\x00\x00\x00\x00\x00\xFF\xFB\x90\x64\x00\x00";
+        assert_eq!(
+            inspect(mp3, "audio/mpeg").unwrap(),
+            MediaDetails::Audio {
+                duration: None,
+                sample_rate: Some(44100),
+                channels: Some(2),
+            }
+        );
+
+        let mut vorbis = b"\x01vorbis\x00\x00\x00\x00\x01".to_vec();
+        vorbis.extend_from_slice(&48000u32.to_le_bytes());
+        vorbis.extend_from_slice(&[0; 14]);
+        let mut ogg = ogg_page(0, &vorbis);
+        ogg.extend(ogg_page(96000, &[0; 20]));
+        assert_eq!(
+            inspect(&ogg, "audio/ogg").unwrap(),
+            MediaDetails::Audio {
+                duration: Some(Duration::from_secs(2)),
+                sample_rate: Some(48000),
+                channels: Some(1),
+            }
+        );
+        assert_invalid(
+            inspect(&ogg[..ogg.len() - 5], "audio/ogg"),
+            "Ogg page at offset",
+        );
+    }
+
+    #[test]
+    fn test_inspect_mpeg_audio_duration() {
+        let frame_duration = |frames: u32, samples: u32| {
+            Duration::from_secs_f64((frames * samples) as f64 / 44100.0)
+        };
+
+        // Frames are counted up to a trailing ID3v1 tag
+        let mut mp3 = b"ID3\x04\x00\x00\x00\x00\x00\x00".to_vec();
+        for _ in 0..10 {
+            mp3.extend(mp3_frame(&[]));
+        }
+        mp3.extend_from_slice(b"TAG");
+        mp3.extend_from_slice(&[0; 125]);
+        assert_eq!(
+            inspect(&mp3, "audio/mpeg").unwrap(),
+            MediaDetails::Audio {
+                duration: Some(frame_duration(10, 1152)),
+                sample_rate: Some(44100),
+                channels: Some(2),
+            }
+        );
+
+        // A Xing header gives the number of frames of the whole stream
+        let mut xing = vec![0; 32];
+        xing.extend_from_slice(b"Xing\x00\x00\x00\x01");
+        xing.extend_from_slice(&100u32.to_be_bytes());
+        match inspect(&mp3_frame(&xing), "audio/mpeg").unwrap() {
+            MediaDetails::Audio { duration, .. } => {
+                assert_eq!(duration, Some(frame_duration(100, 1152)))
+            }
+            other => panic!("Unexpected details: {:?}", other),
+        }
+
+        // ADTS frames of AAC are counted, including a final partial frame
+        let mut aac = [adts_frame(), adts_frame(), adts_frame()].concat();
+        aac.extend_from_slice(&adts_frame()[..50]);
+        assert_eq!(
+            inspect(&aac, "audio/aac").unwrap(),
+            MediaDetails::Audio {
+                duration: Some(frame_duration(3, 1024)),
+                sample_rate: Some(44100),
+                channels: Some(2),
+            }
+        );
+
+        // ADIF streams do not record their length
+        match inspect(b"ADIF\x00\x00\x00\x00", "audio/aac").unwrap() {
+            MediaDetails::Audio { duration, .. } => assert_eq!(duration, None),
+            other => panic!("Unexpected details: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_inspect_truncated_audio() {
+        assert_invalid(
+            inspect(&wav(32000, 1000), "audio/wav"),
+            "RIFF data at offset 0 needs 32044 bytes",
+        );
+        assert_invalid(
+            inspect(b"fLaC\x80\x00\x00\x22\x00\x00", "audio/flac"),
+            "FLAC stream info",
+        );
+        assert_invalid(
+            inspect(
+                b"ID3\x04\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00",
+                "audio/mpeg",
+            ),
+            "no MPEG audio frame starts at offset 10",
+        );
+    }
+
+    #[test]
+    fn test_inspect_movie() {
+        let details = inspect(&mp4(100, 100), "video/mp4").unwrap();
+        let (duration, tracks) = match details {
+            MediaDetails::Video { duration, tracks } => (duration, tracks),
+            other => panic!("Unexpected details: {:?}", other),
+        };
+
+        assert_eq!(duration, Some(Duration::from_secs(2)));
+        assert_eq!(
+            tracks,
+            vec![
+                MediaTrack {
+                    id: 1,
+                    kind: TrackKind::Video,
+                    codec: Some("avc1".to_string()),
+                    duration: Some(Duration::from_secs(2)),
+                    dimensions: Some((640, 480)),
+                    sample_rate: None,
+                    channels: None,
+                },
+                MediaTrack {
+                    id: 2,
+                    kind: TrackKind::Audio,
+                    codec: Some("mp4a".to_string()),
+                    duration: Some(Duration::from_secs(2)),
+                    dimensions: None,
+                    sample_rate: Some(44100),
+                    channels: Some(2),
+                },
+            ]
+        );
+
+        // The audio track describes an m4a file
+        assert_eq!(
+            inspect(&mp4(100, 100), "audio/mp4").unwrap(),
+            MediaDetails::Audio {
+                duration: Some(Duration::from_secs(2)),
+                sample_rate: Some(44100),
+                channels: Some(2),
+            }
+        );
+    }
+
+    #[test]
+    fn test_inspect_truncated_movie() {
+        let content = mp4(100, 40);
+        let mdat_offset = content.len() - 48;
+        assert_invalid(
+            inspect(&content, "video/mp4"),
+            &format!("box 'mdat' at offset {mdat_offset} needs 108 bytes"),
+        );
+
+        let without_moov = iso_box(b"ftyp", b"isom\x00\x00\x02\x00");
+        assert_invalid(inspect(&without_moov, "video/mp4"), "no 'moov' box");
+    }
+
+    #[test]
+    fn test_inspect_text() {
+        assert_eq!(
+            inspect(b"plain text", "text/plain").unwrap(),
+            MediaDetails::Text
+        );
+        assert_invalid(
+            inspect(b"caf\xC3\xA9 \xC3", "text/plain"),
+            "not valid UTF-8 at byte 6",
+        );
+    }
+}
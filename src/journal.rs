@@ -0,0 +1,224 @@
+use crate::error::Result;
+use crate::jsonl::JsonlLog;
+use crate::models::{DetectionResult, UploadResult};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// State of a single path in a batch journal
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(tag = "state", rename_all = "lowercase")]
+pub enum JournalState {
+    /// The path is part of the batch but has not been uploaded yet
+    Queued,
+
+    /// The file was uploaded and is waiting for a result
+    Uploaded { upload: UploadResult },
+
+    /// The file reached a terminal result
+    Completed {
+        upload: UploadResult,
+        result: DetectionResult,
+    },
+}
+
+/// A single line of the journal
+#[derive(Debug, Deserialize, Serialize)]
+struct JournalRecord {
+    path: PathBuf,
+    #[serde(flatten)]
+    state: JournalState,
+}
+
+/// Append-only JSONL journal recording the progress of a batch.
+///
+/// Each line records the latest state of one path. When a journal is reopened,
+/// the last record for each path wins, so a restarted batch can skip finished
+/// files and resume polling for uploaded ones.
+#[derive(Debug)]
+pub struct BatchJournal {
+    log: JsonlLog,
+    states: HashMap<PathBuf, JournalState>,
+}
+
+impl BatchJournal {
+    /// Open a journal, loading any records left by a previous run
+    pub fn open(path: &Path) -> Result<Self> {
+        let (log, records) = JsonlLog::open::<JournalRecord>(path, "batch journal")?;
+        let states = records
+            .into_iter()
+            .map(|record| (record.path, record.state))
+            .collect();
+        Ok(Self { log, states })
+    }
+
+    /// State recorded for a path by a previous run, if any
+    pub fn state(&self, path: &Path) -> Option<&JournalState> {
+        self.states.get(path)
+    }
+
+    /// Record that a path is part of the batch, unless it already has a state
+    pub fn record_queued(&self, paths: &[PathBuf]) -> Result<()> {
+        let records = paths
+            .iter()
+            .filter(|path| !self.states.contains_key(*path))
+            .map(|path| JournalRecord {
+                path: path.clone(),
+                state: JournalState::Queued,
+            })
+            .collect::<Vec<_>>();
+
+        self.append(&records)
+    }
+
+    /// Record that a path was uploaded
+    pub fn record_uploaded(&self, path: &Path, upload: &UploadResult) -> Result<()> {
+        self.append(&[JournalRecord {
+            path: path.to_path_buf(),
+            state: JournalState::Uploaded {
+                upload: upload.clone(),
+            },
+        }])
+    }
+
+    /// Record that a path reached a terminal result
+    pub fn record_completed(
+        &self,
+        path: &Path,
+        upload: &UploadResult,
+        result: &DetectionResult,
+    ) -> Result<()> {
+        self.append(&[JournalRecord {
+            path: path.to_path_buf(),
+            state: JournalState::Completed {
+                upload: upload.clone(),
+                result: result.clone(),
+            },
+        }])
+    }
+
+    fn append(&self, records: &[JournalRecord]) -> Result<()> {
+        self.log.append(records)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::Error;
+    use crate::models::AnalysisStatus;
+    use tempfile::tempdir;
+
+    fn upload(request_id: &str) -> UploadResult {
+        UploadResult {
+            request_id: request_id.to_string(),
+            media_id: None,
+            result_url: None,
+            normalization: None,
+        }
+    }
+
+    #[test]
+    fn test_journal_roundtrip() {
+        let dir = tempdir().unwrap();
+        let journal_path = dir.path().join("batch.jsonl");
+        let queued = PathBuf::from("queued.jpg");
+        let uploaded = PathBuf::from("uploaded.jpg");
+        let completed = PathBuf::from("completed.jpg");
+
+        let journal = BatchJournal::open(&journal_path).unwrap();
+        journal
+            .record_queued(&[queued.clone(), uploaded.clone(), completed.clone()])
+            .unwrap();
+        journal
+            .record_uploaded(&uploaded, &upload("uploaded-id"))
+            .unwrap();
+        journal
+            .record_uploaded(&completed, &upload("completed-id"))
+            .unwrap();
+        journal
+            .record_completed(
+                &completed,
+                &upload("completed-id"),
+                &DetectionResult {
+                    request_id: "completed-id".to_string(),
+                    status: AnalysisStatus::Authentic,
+                    score: Some(0.1),
+                    models: vec![],
+                },
+            )
+            .unwrap();
+        drop(journal);
+
+        let journal = BatchJournal::open(&journal_path).unwrap();
+        assert!(matches!(journal.state(&queued), Some(JournalState::Queued)));
+        match journal.state(&uploaded) {
+            Some(JournalState::Uploaded { upload }) => {
+                assert_eq!(upload.request_id, "uploaded-id")
+            }
+            other => panic!("Unexpected state: {:?}", other),
+        }
+        match journal.state(&completed) {
+            Some(JournalState::Completed { upload, result }) => {
+                assert_eq!(upload.request_id, "completed-id");
+                assert_eq!(result.score, Some(0.1));
+            }
+            other => panic!("Unexpected state: {:?}", other),
+        }
+        assert!(journal.state(Path::new("unknown.jpg")).is_none());
+    }
+
+    #[test]
+    fn test_journal_requeue_keeps_existing_state() {
+        let dir = tempdir().unwrap();
+        let journal_path = dir.path().join("batch.jsonl");
+        let path = PathBuf::from("file.jpg");
+
+        let journal = BatchJournal::open(&journal_path).unwrap();
+        journal
+            .record_uploaded(&path, &upload("request-id"))
+            .unwrap();
+        drop(journal);
+
+        let journal = BatchJournal::open(&journal_path).unwrap();
+        journal.record_queued(std::slice::from_ref(&path)).unwrap();
+        drop(journal);
+
+        let journal = BatchJournal::open(&journal_path).unwrap();
+        assert!(matches!(
+            journal.state(&path),
+            Some(JournalState::Uploaded { .. })
+        ));
+    }
+
+    #[test]
+    fn test_journal_ignores_truncated_last_line() {
+        let dir = tempdir().unwrap();
+        let journal_path = dir.path().join("batch.jsonl");
+        std::fs::write(
+            &journal_path,
+            "{\"path\":\"a.jpg\",\"state\":\"queued\"}\n{\"path\":\"b.jpg\",\"sta",
+        )
+        .unwrap();
+
+        let journal = BatchJournal::open(&journal_path).unwrap();
+        assert!(journal.state(Path::new("a.jpg")).is_some());
+        assert!(journal.state(Path::new("b.jpg")).is_none());
+    }
+
+    #[test]
+    fn test_journal_rejects_corrupt_record() {
+        let dir = tempdir().unwrap();
+        let journal_path = dir.path().join("batch.jsonl");
+        std::fs::write(
+            &journal_path,
+            "not json\n{\"path\":\"a.jpg\",\"state\":\"queued\"}\n",
+        )
+        .unwrap();
+
+        match BatchJournal::open(&journal_path) {
+            Err(Error::InvalidData(msg)) => assert!(msg.contains("line 1")),
+            other => panic!("Expected InvalidData error, got: {:?}", other),
+        }
+    }
+}
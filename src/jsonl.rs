@@ -0,0 +1,144 @@
+use crate::error::{Error, Result};
+use crate::utils::lock;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::Path;
+use std::sync::Mutex;
+
+/// Append-only file of JSON records, one per line, used by the batch journal
+/// and the file-backed dedupe store
+#[derive(Debug)]
+pub(crate) struct JsonlLog {
+    file: Mutex<File>,
+
+    /// What the log holds, for error messages
+    name: &'static str,
+}
+
+impl JsonlLog {
+    /// Open a log for appending, returning the records left by previous runs
+    /// in the order they were written
+    pub(crate) fn open<T: DeserializeOwned>(
+        path: &Path,
+        name: &'static str,
+    ) -> Result<(Self, Vec<T>)> {
+        let (records, complete_len) = match std::fs::read_to_string(path) {
+            Ok(contents) => {
+                let complete_len = contents.rfind('\n').map_or(0, |index| index + 1);
+                (parse(&contents, name)?, complete_len)
+            }
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => (Vec::new(), 0),
+            Err(err) => return Err(err.into()),
+        };
+
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        // Drop a partial last line so the next record starts on a line of its own
+        if file.metadata()?.len() > complete_len as u64 {
+            file.set_len(complete_len as u64)?;
+        }
+
+        let log = Self {
+            file: Mutex::new(file),
+            name,
+        };
+        Ok((log, records))
+    }
+
+    /// Append records to the log
+    pub(crate) fn append<T: Serialize>(&self, records: &[T]) -> Result<()> {
+        if records.is_empty() {
+            return Ok(());
+        }
+
+        let mut buffer = Vec::new();
+        for record in records {
+            serde_json::to_writer(&mut buffer, record)?;
+            buffer.push(b'\n');
+        }
+
+        // Write all lines at once so concurrent records never interleave
+        let mut file = lock(&self.file, self.name)?;
+        file.write_all(&buffer)?;
+        file.flush()?;
+
+        Ok(())
+    }
+}
+
+fn parse<T: DeserializeOwned>(contents: &str, name: &str) -> Result<Vec<T>> {
+    let mut records = Vec::new();
+    let lines: Vec<&str> = contents.lines().collect();
+
+    for (index, line) in lines.iter().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        match serde_json::from_str::<T>(line) {
+            Ok(record) => records.push(record),
+            // A crash while appending can leave a partial last line
+            Err(_) if index == lines.len() - 1 && !contents.ends_with('\n') => {}
+            Err(err) => {
+                return Err(Error::InvalidData(format!(
+                    "Invalid {name} record on line {}: {err}",
+                    index + 1
+                )))
+            }
+        }
+    }
+
+    Ok(records)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::Deserialize;
+    use tempfile::tempdir;
+
+    #[derive(Debug, PartialEq, Deserialize, Serialize)]
+    struct Record {
+        key: String,
+        value: u32,
+    }
+
+    fn record(key: &str, value: u32) -> Record {
+        Record {
+            key: key.to_string(),
+            value,
+        }
+    }
+
+    #[test]
+    fn test_log_roundtrip() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("log.jsonl");
+
+        let (log, records) = JsonlLog::open::<Record>(&path, "test log").unwrap();
+        assert!(records.is_empty());
+        log.append(&[record("a", 1), record("b", 2)]).unwrap();
+        log.append::<Record>(&[]).unwrap();
+        log.append(&[record("a", 3)]).unwrap();
+        drop(log);
+
+        let (_, records) = JsonlLog::open::<Record>(&path, "test log").unwrap();
+        assert_eq!(records, [record("a", 1), record("b", 2), record("a", 3)]);
+    }
+
+    #[test]
+    fn test_log_appends_after_partial_line() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("log.jsonl");
+        std::fs::write(&path, "{\"key\":\"a\",\"value\":1}\n{\"key\":\"b\",\"va").unwrap();
+
+        let (log, records) = JsonlLog::open::<Record>(&path, "test log").unwrap();
+        assert_eq!(records, [record("a", 1)]);
+        log.append(&[record("c", 3)]).unwrap();
+        drop(log);
+
+        let (_, records) = JsonlLog::open::<Record>(&path, "test log").unwrap();
+        assert_eq!(records, [record("a", 1), record("c", 3)]);
+    }
+}
@@ -73,18 +73,41 @@
 
 mod client;
 mod config;
+mod dedupe;
+mod directory;
 mod error;
+mod fetch;
 mod file;
 mod http;
+mod inspect;
+mod journal;
+mod jsonl;
 mod models;
+mod normalize;
+mod poll;
+mod poller;
+mod rate_limit;
 mod utils;
 
 // Re-exports
 pub use client::Client;
-pub use config::Config;
-pub use error::{Error, Result};
+pub use config::{
+    Config, ContentTypePolicy, MultipartUpload, RateLimit, RetryPolicy, RetryableError,
+};
+pub use dedupe::{DedupeCache, DedupeEntry, DedupeStore, FileDedupeStore, MemoryDedupeStore};
+pub use error::{ApiError, Error, Result};
+pub use file::{MediaCategory, MediaType, MEDIA_TYPES};
+pub use inspect::{MediaDetails, MediaInfo, MediaTrack, TrackKind};
 pub use models::{
-    AnalysisResult, BatchOptions, DetectionModel, DetectionResult, DetectionResultList,
-    FormattedDetectionResultList, GetResultOptions, GetResultsOptions, ResultsSummary,
-    UploadOptions, UploadResult,
+    AnalysisResult, AnalysisStatus, BatchEntry, BatchOptions, BatchProgress, BatchReport,
+    CachedUpload, DetectionModel, DetectionResult, DetectionResultList, DirectoryOptions,
+    DirectoryReport, FetchOptions, FormattedDetectionResultList, GetResultOptions,
+    GetResultsOptions, Normalization, PollerOptions, ResultsSummary, SkipReason, SkippedFile,
+    UploadOptions, UploadProgress, UploadResult,
+};
+pub use normalize::{
+    BuiltinImageDecoder, DecodedImage, ImageDecoder, ImageNormalization, NormalizedFormat,
 };
+pub use poll::{Deadline, ExponentialBackoff, FixedInterval, PollStrategy};
+pub use poller::ResultPoller;
+pub use tokio_util::sync::CancellationToken;
@@ -1,4 +1,11 @@
+use crate::error::{Error, Result};
+use crate::poll::PollStrategy;
 use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::watch;
+use tokio_util::sync::CancellationToken;
 
 /// Base API response
 #[derive(Debug, Clone, Deserialize, Serialize, Default)]
@@ -27,6 +34,32 @@ pub struct UploadOptions {
     pub file_path: String,
 }
 
+/// Options for downloading media from a direct URL before uploading it
+#[derive(Debug, Clone, Default)]
+pub struct FetchOptions {
+    /// Largest download accepted, in bytes. The size limit of the file type
+    /// always applies.
+    pub max_size: Option<u64>,
+
+    /// Time allowed for the download, including redirects. Defaults to the
+    /// client timeout.
+    pub timeout: Option<Duration>,
+
+    /// Maximum number of redirects to follow. Defaults to 5, and 0 rejects
+    /// any redirect.
+    pub max_redirects: Option<usize>,
+
+    /// Whether hosts resolving to private, loopback, link-local or multicast
+    /// addresses can be fetched, including IPv6 addresses embedding such an
+    /// IPv4 address. They are blocked by default.
+    pub allow_private_addresses: bool,
+
+    /// Name of the uploaded file, whose extension determines the file type.
+    /// Taken from the URL, or from the content type when the URL has no
+    /// supported extension, when unset.
+    pub file_name: Option<String>,
+}
+
 #[derive(Debug, Clone, Serialize)]
 pub struct UploadSocialMediaOptions {
     /// Path to the file to upload
@@ -63,6 +96,32 @@ pub struct SignedUrlDetails {
     /// The presigned URL for uploading
     #[serde(rename = "signedUrl")]
     pub signed_url: String,
+
+    /// Presigned URLs for uploading in parts, when a multipart upload was
+    /// requested and the API supports it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub multipart: Option<MultipartDetails>,
+}
+
+/// Presigned URLs of a multipart upload. Experimental: not part of the
+/// documented API.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct MultipartDetails {
+    /// Identifier of the multipart upload
+    #[serde(rename = "uploadId")]
+    pub upload_id: String,
+
+    /// Size of every part except the last, in bytes
+    #[serde(rename = "partSize")]
+    pub part_size: u64,
+
+    /// Presigned URL for each part, in order
+    #[serde(rename = "partUrls")]
+    pub part_urls: Vec<String>,
+
+    /// Presigned URL completing the upload once every part is uploaded
+    #[serde(rename = "completeUrl")]
+    pub complete_url: String,
 }
 
 /// Result of an upload operation
@@ -78,6 +137,45 @@ pub struct UploadResult {
     /// URL where the result can be accessed
     #[serde(default)]
     pub result_url: Option<String>,
+
+    /// Conversion applied to an image before it was uploaded
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub normalization: Option<Normalization>,
+}
+
+/// Conversion of an image the API would not accept into one it does
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct Normalization {
+    /// Extension of the original image, in lowercase
+    pub original_format: String,
+
+    /// Size of the original image in bytes
+    pub original_size: u64,
+
+    /// Width and height of the original image
+    pub original_dimensions: (u32, u32),
+
+    /// Extension of the uploaded image
+    pub format: String,
+
+    /// Size of the uploaded image in bytes
+    pub size: u64,
+
+    /// Width and height of the uploaded image
+    pub dimensions: (u32, u32),
+}
+
+/// Upload made through the dedupe cache
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct CachedUpload {
+    /// Upload of the content, which may come from an earlier upload
+    pub upload: UploadResult,
+
+    /// Terminal result recorded for the content, if any
+    pub result: Option<DetectionResult>,
+
+    /// Whether the upload was reused instead of sent
+    pub cached: bool,
 }
 
 /// Options for getting a result
@@ -90,6 +188,21 @@ pub struct GetResultOptions {
     /// How long to wait between attempts
     #[serde(skip_serializing_if = "Option::is_none")]
     pub polling_interval: Option<u64>,
+
+    /// Strategy deciding when to poll and when to give up. Takes precedence
+    /// over `max_attempts` and `polling_interval`.
+    #[serde(skip)]
+    pub poll_strategy: Option<Arc<dyn PollStrategy>>,
+
+    /// Wall-clock limit on waiting, whatever the strategy. On its own, it
+    /// polls every 2 seconds until the deadline.
+    #[serde(skip)]
+    pub deadline: Option<Duration>,
+
+    /// Token stopping the wait when cancelled, with an `Error::Cancelled`
+    /// naming the request
+    #[serde(skip)]
+    pub cancel: Option<CancellationToken>,
 }
 
 #[derive(Deserialize, Debug, Clone, PartialEq)]
@@ -99,14 +212,112 @@ pub enum FloatOrObject {
     Object(serde_json::Map<String, serde_json::Value>),
 }
 
+/// Status of an analysis or of a single model within it
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Deserialize, Serialize)]
+#[serde(from = "String", into = "String")]
+pub enum AnalysisStatus {
+    /// The media was found to be manipulated. The API also reports this as `FAKE`.
+    Manipulated,
+
+    /// The media was found to be authentic
+    Authentic,
+
+    /// The media may be manipulated
+    Suspicious,
+
+    /// The analysis is still running
+    Analyzing,
+
+    /// The media is still being downloaded, e.g. from a social media link
+    Downloading,
+
+    /// The model does not apply to this media
+    NotApplicable,
+
+    /// The analysis failed
+    Error,
+
+    /// A status this SDK does not know about, kept as returned by the API
+    Unknown(String),
+}
+
+impl AnalysisStatus {
+    /// Status as returned by the API
+    pub fn as_str(&self) -> &str {
+        match self {
+            Self::Manipulated => "MANIPULATED",
+            Self::Authentic => "AUTHENTIC",
+            Self::Suspicious => "SUSPICIOUS",
+            Self::Analyzing => "ANALYZING",
+            Self::Downloading => "DOWNLOADING",
+            Self::NotApplicable => "NOT_APPLICABLE",
+            Self::Error => "ERROR",
+            Self::Unknown(status) => status,
+        }
+    }
+
+    /// Whether the analysis is finished and its status will not change.
+    /// Unknown statuses are treated as terminal.
+    pub fn is_terminal(&self) -> bool {
+        !matches!(self, Self::Analyzing | Self::Downloading)
+    }
+
+    /// Whether the analysis reached a verdict on the media: manipulated,
+    /// authentic or suspicious
+    pub fn is_verdict(&self) -> bool {
+        matches!(self, Self::Manipulated | Self::Authentic | Self::Suspicious)
+    }
+
+    /// Whether the media was found to be manipulated
+    pub fn is_manipulated(&self) -> bool {
+        matches!(self, Self::Manipulated)
+    }
+}
+
+impl From<String> for AnalysisStatus {
+    fn from(status: String) -> Self {
+        match status.as_str() {
+            "MANIPULATED" | "FAKE" => Self::Manipulated,
+            "AUTHENTIC" => Self::Authentic,
+            "SUSPICIOUS" => Self::Suspicious,
+            "ANALYZING" => Self::Analyzing,
+            "DOWNLOADING" => Self::Downloading,
+            "NOT_APPLICABLE" => Self::NotApplicable,
+            "ERROR" => Self::Error,
+            _ => Self::Unknown(status),
+        }
+    }
+}
+
+impl From<&str> for AnalysisStatus {
+    fn from(status: &str) -> Self {
+        Self::from(status.to_string())
+    }
+}
+
+impl From<AnalysisStatus> for String {
+    fn from(status: AnalysisStatus) -> Self {
+        match status {
+            AnalysisStatus::Unknown(status) => status,
+            status => status.as_str().to_string(),
+        }
+    }
+}
+
+impl std::fmt::Display for AnalysisStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
 /// Model-specific detection results
 #[derive(Debug, Clone, Deserialize)]
 pub struct DetectionModel {
     /// Name of the model
     pub name: String,
 
-    /// Status of the detection (COMPLETED, PROCESSING, ERROR, etc.)
-    pub status: String,
+    /// Status of the detection
+    pub status: AnalysisStatus,
 
     /// Raw prediction number from the model (may be on 0-100 scale, used internally for normalization)
     #[serde(rename = "predictionNumber")]
@@ -131,9 +342,9 @@ pub struct AnalysisResult {
     #[serde(rename = "requestId")]
     pub request_id: String,
 
-    /// Status of the analysis (COMPLETED, PROCESSING, ERROR, etc.)
+    /// Status of the analysis
     #[serde(rename = "overallStatus")]
-    pub status: String,
+    pub status: AnalysisStatus,
 
     /// Overall detection score (0-1 range, normalized by the SDK, higher is more likely to be MANIPULATED)
     #[serde(default)]
@@ -180,33 +391,302 @@ pub struct BatchOptions {
     /// Maximum number of concurrent uploads
     pub max_concurrency: Option<usize>,
 
+    /// Maximum number of files polled for results at the same time.
+    /// Defaults to `max_concurrency`. Uploads go on while uploaded files
+    /// wait for a polling slot.
+    pub max_polling_concurrency: Option<usize>,
+
     /// Maximum number of attempts to get results
     pub max_attempts: Option<u64>,
 
     /// How long to wait between attempts
     pub polling_interval: Option<u64>,
+
+    /// Strategy deciding when to poll for each file's result and when to
+    /// give up. Takes precedence over `max_attempts` and `polling_interval`.
+    pub poll_strategy: Option<Arc<dyn PollStrategy>>,
+
+    /// Wall-clock limit on waiting for each file's result, counted from its
+    /// first poll
+    pub deadline: Option<Duration>,
+
+    /// Token stopping the batch when cancelled. No new file is uploaded and
+    /// polling stops, while uploads in progress are finished so the files
+    /// uploaded so far stay in the report as pending, with their request IDs.
+    pub cancel: Option<CancellationToken>,
+
+    /// Path of a JSONL journal recording each file's progress. When set, a
+    /// rerun of the same batch skips finished files, resumes polling for
+    /// uploaded ones and only uploads what is left. Paths are matched exactly
+    /// as given, so reruns must pass the same path strings. Streamed batches
+    /// record each path as it arrives.
+    pub journal_path: Option<PathBuf>,
+
+    /// Channel receiving the progress of the batch as files are uploaded and
+    /// finished
+    pub progress: Option<watch::Sender<BatchProgress>>,
 }
 
-#[derive(Debug, Clone, Deserialize)]
+/// Progress of an upload to a presigned URL. A retried upload starts again
+/// from zero bytes sent.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct UploadProgress {
+    /// Bytes handed to the connection so far
+    pub bytes_sent: u64,
+
+    /// Size of the upload
+    pub total_bytes: u64,
+}
+
+/// Progress of a batch. Finished files are counted like the summary counts
+/// of [`BatchReport`], so the final progress matches the report.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct BatchProgress {
+    /// Number of files in the batch, `None` for streams of unknown length
+    pub total_files: Option<usize>,
+
+    /// Number of files with a detection result
+    pub files_done: usize,
+
+    /// Number of files that failed to upload or to return a result
+    pub files_failed: usize,
+
+    /// Number of files uploaded without waiting for a result
+    pub files_pending: usize,
+
+    /// Number of files not uploaded because the batch was cancelled
+    pub files_cancelled: usize,
+
+    /// Number of files being uploaded or polled
+    pub files_in_flight: usize,
+
+    /// Bytes sent by the uploads started so far
+    pub bytes_sent: u64,
+
+    /// Total size of the uploads started so far
+    pub total_bytes: u64,
+}
+
+impl BatchProgress {
+    /// Count a file that left the pipeline with the given result
+    pub(crate) fn count_finished(&mut self, result: Option<&Result<DetectionResult>>) {
+        match BatchOutcome::of(result) {
+            BatchOutcome::Succeeded => self.files_done += 1,
+            BatchOutcome::Failed => self.files_failed += 1,
+            BatchOutcome::Pending => self.files_pending += 1,
+            BatchOutcome::Cancelled => self.files_cancelled += 1,
+        }
+    }
+}
+
+/// How a file of a batch ended, as counted by reports and progress
+enum BatchOutcome {
+    Succeeded,
+    Failed,
+    Pending,
+    Cancelled,
+}
+
+impl BatchOutcome {
+    fn of(result: Option<&Result<DetectionResult>>) -> Self {
+        match result {
+            Some(Ok(_)) => Self::Succeeded,
+            Some(Err(Error::Cancelled { .. })) => Self::Cancelled,
+            Some(Err(_)) => Self::Failed,
+            None => Self::Pending,
+        }
+    }
+}
+
+/// Outcome of a single file in a batch
+#[derive(Debug)]
+pub struct BatchEntry {
+    /// Path of the input file
+    pub path: PathBuf,
+
+    /// Upload result, if the file was uploaded successfully
+    pub upload: Option<UploadResult>,
+
+    /// Detection result or the error that stopped this file.
+    /// `None` if the file was uploaded but results were not awaited.
+    pub result: Option<Result<DetectionResult>>,
+}
+
+impl BatchEntry {
+    /// Request ID assigned to this file, if it was uploaded
+    pub fn request_id(&self) -> Option<&str> {
+        self.upload
+            .as_ref()
+            .map(|upload| upload.request_id.as_str())
+    }
+
+    /// Whether the file was uploaded but its result was not awaited
+    pub fn is_pending(&self) -> bool {
+        self.result.is_none()
+    }
+}
+
+/// Report of a batch run, with one entry per input path in input order
+#[derive(Debug, Default)]
+pub struct BatchReport {
+    /// Per-file outcomes, in the same order as the input paths
+    pub entries: Vec<BatchEntry>,
+
+    /// Number of files with a detection result
+    pub succeeded: usize,
+
+    /// Number of files that failed to upload or to return a result
+    pub failed: usize,
+
+    /// Number of files uploaded without waiting for a result
+    pub pending: usize,
+
+    /// Number of files not uploaded because the batch was cancelled
+    pub cancelled: usize,
+}
+
+impl BatchReport {
+    /// Build a report from per-file entries, computing the summary counts
+    pub fn from_entries(entries: Vec<BatchEntry>) -> Self {
+        let mut report = Self::default();
+        for entry in &entries {
+            match BatchOutcome::of(entry.result.as_ref()) {
+                BatchOutcome::Succeeded => report.succeeded += 1,
+                BatchOutcome::Failed => report.failed += 1,
+                BatchOutcome::Pending => report.pending += 1,
+                BatchOutcome::Cancelled => report.cancelled += 1,
+            }
+        }
+        report.entries = entries;
+        report
+    }
+
+    /// Successful detection results, paired with their input paths
+    pub fn results(&self) -> impl Iterator<Item = (&Path, &DetectionResult)> {
+        self.entries.iter().filter_map(|entry| match &entry.result {
+            Some(Ok(result)) => Some((entry.path.as_path(), result)),
+            _ => None,
+        })
+    }
+
+    /// Failed files, paired with the error that stopped them
+    pub fn failures(&self) -> impl Iterator<Item = (&Path, &Error)> {
+        self.entries.iter().filter_map(|entry| match &entry.result {
+            Some(Err(Error::Cancelled { .. })) => None,
+            Some(Err(err)) => Some((entry.path.as_path(), err)),
+            _ => None,
+        })
+    }
+
+    /// Request IDs of the files uploaded without a result, e.g. because the
+    /// batch was cancelled, so their results can be fetched later
+    pub fn pending_request_ids(&self) -> impl Iterator<Item = &str> {
+        self.entries
+            .iter()
+            .filter(|entry| entry.is_pending())
+            .filter_map(BatchEntry::request_id)
+    }
+
+    /// Total number of files in the batch
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Whether the batch had no input files
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+/// Options for processing a directory tree
+#[derive(Debug, Clone, Default)]
+pub struct DirectoryOptions {
+    /// Glob patterns a file must match to be processed, relative to the root.
+    /// All files are considered when empty.
+    pub include: Vec<String>,
+
+    /// Glob patterns excluding files and directories, relative to the root
+    pub exclude: Vec<String>,
+
+    /// Whether to follow symbolic links instead of skipping them
+    pub follow_symlinks: bool,
+
+    /// Whether to include hidden files and directories (names starting with `.`)
+    pub include_hidden: bool,
+
+    /// Maximum depth to descend below the root, where files directly in the
+    /// root are at depth 1. Unlimited when `None`.
+    pub max_depth: Option<usize>,
+
+    /// Options for the batch processing the selected files
+    pub batch: BatchOptions,
+}
+
+/// Reason a file was not selected from a directory tree
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SkipReason {
+    /// The file or directory is hidden
+    Hidden,
+
+    /// The entry is a symbolic link and links are not followed
+    Symlink,
+
+    /// The path matched an exclude pattern
+    Excluded,
+
+    /// The path did not match any include pattern
+    NotIncluded,
+
+    /// The file extension is not a supported file type
+    UnsupportedType,
+
+    /// The file exceeds the size limit of its file type
+    TooLarge { size: u64, limit: u64 },
+
+    /// The entry could not be read
+    Unreadable(String),
+}
+
+/// A file or directory that was skipped while walking a directory tree
+#[derive(Debug, Clone)]
+pub struct SkippedFile {
+    /// Path of the skipped entry
+    pub path: PathBuf,
+
+    /// Why the entry was skipped
+    pub reason: SkipReason,
+}
+
+/// Report of a directory run
+#[derive(Debug, Default)]
+pub struct DirectoryReport {
+    /// Outcome of the files that were selected and processed
+    pub batch: BatchReport,
+
+    /// Entries that were not processed, with the reason for each
+    pub skipped: Vec<SkippedFile>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
 pub struct DetectionModelResult {
     /// Name of the model
     pub name: String,
 
-    /// Status of the detection (COMPLETED, PROCESSING, ERROR, etc.)
-    pub status: String,
+    /// Status of the detection
+    pub status: AnalysisStatus,
 
     /// Detection score (0-1 range, normalized by the SDK, higher is more likely to be MANIPULATED)
     pub score: Option<f64>,
 }
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
 pub struct DetectionResult {
     /// Unique identifier for the upload request
     #[serde(rename = "requestId")]
     pub request_id: String,
 
-    /// Status of the analysis (COMPLETED, PROCESSING, ERROR, etc.)
-    pub status: String,
+    /// Status of the analysis
+    pub status: AnalysisStatus,
 
     /// Confidence score (0-1 range, null if processing)
     pub score: Option<f64>,
@@ -245,6 +725,29 @@ pub struct GetResultsOptions {
     /// How long to wait between attempts
     #[serde(skip_serializing_if = "Option::is_none")]
     pub polling_interval: Option<u64>,
+
+    /// Strategy deciding when to poll until no result is being analyzed and
+    /// when to give up. Takes precedence over `max_attempts` and
+    /// `polling_interval`.
+    #[serde(skip)]
+    pub poll_strategy: Option<Arc<dyn PollStrategy>>,
+}
+
+/// Options for waiting on many requests through a shared poller
+#[derive(Debug, Clone, Default)]
+pub struct PollerOptions {
+    /// Delay between polling rounds. Defaults to 5 seconds.
+    pub interval: Option<Duration>,
+
+    /// Number of results listed per page. Defaults to 100.
+    pub page_size: Option<u32>,
+
+    /// Maximum number of pages listed per round. Defaults to 10.
+    pub max_pages: Option<u32>,
+
+    /// Consecutive rounds a request can be missing from the listed pages
+    /// before it is fetched on its own. Defaults to 3.
+    pub straggler_rounds: Option<u32>,
 }
 
 /// Paginated list of detection results
@@ -333,6 +836,7 @@ mod tests {
         let options = GetResultOptions {
             max_attempts: Some(30),
             polling_interval: Some(2000),
+            ..Default::default()
         };
 
         let json_str = serde_json::to_string(&options).unwrap();
@@ -346,8 +850,10 @@ mod tests {
     fn test_batch_options_defaults() {
         let options = BatchOptions::default();
         assert_eq!(options.max_concurrency, None);
+        assert_eq!(options.max_polling_concurrency, None);
         assert_eq!(options.max_attempts, None);
         assert_eq!(options.polling_interval, None);
+        assert_eq!(options.journal_path, None);
     }
 
     #[test]
@@ -367,7 +873,7 @@ mod tests {
         let model: DetectionModel = serde_json::from_value(json_data).unwrap();
 
         assert_eq!(model.name, "TestModel");
-        assert_eq!(model.status, "COMPLETED");
+        assert_eq!(model.status.as_str(), "COMPLETED");
         assert_eq!(model.final_score, Some(80.0));
 
         let info = model.info.unwrap();
@@ -420,22 +926,22 @@ mod tests {
         let result: AnalysisResult = serde_json::from_value(json_data).unwrap();
 
         assert_eq!(result.request_id, "test-request-123");
-        assert_eq!(result.status, "COMPLETED");
+        assert_eq!(result.status.as_str(), "COMPLETED");
         assert_eq!(result.final_score, Some(75.0));
         assert_eq!(result.models.len(), 3);
 
         assert_eq!(result.models[0].name, "ModelA");
-        assert_eq!(result.models[0].status, "COMPLETED");
+        assert_eq!(result.models[0].status.as_str(), "COMPLETED");
         assert_eq!(result.models[0].final_score, Some(80.0));
         assert_eq!(result.models[0].prediction_number, None);
 
         assert_eq!(result.models[1].name, "ModelB");
-        assert_eq!(result.models[1].status, "NOT_APPLICABLE");
+        assert_eq!(result.models[1].status, AnalysisStatus::NotApplicable);
         assert_eq!(result.models[1].final_score, None);
         assert_eq!(result.models[1].prediction_number, None);
 
         assert_eq!(result.models[2].name, "ModelC");
-        assert_eq!(result.models[2].status, "NOT_APPLICABLE");
+        assert_eq!(result.models[2].status, AnalysisStatus::NotApplicable);
         assert_eq!(result.models[2].final_score, None);
         assert!(matches!(
             result.models[2].prediction_number,
@@ -449,4 +955,62 @@ mod tests {
         assert_eq!(results_summary.status, "COMPLETED");
         assert_eq!(results_summary.metadata.unwrap()["finalScore"], 75);
     }
+
+    #[test]
+    fn test_analysis_status_parsing() {
+        let cases = [
+            ("MANIPULATED", AnalysisStatus::Manipulated),
+            ("FAKE", AnalysisStatus::Manipulated),
+            ("AUTHENTIC", AnalysisStatus::Authentic),
+            ("SUSPICIOUS", AnalysisStatus::Suspicious),
+            ("ANALYZING", AnalysisStatus::Analyzing),
+            ("DOWNLOADING", AnalysisStatus::Downloading),
+            ("NOT_APPLICABLE", AnalysisStatus::NotApplicable),
+            ("ERROR", AnalysisStatus::Error),
+            (
+                "COMPLETED",
+                AnalysisStatus::Unknown("COMPLETED".to_string()),
+            ),
+        ];
+
+        for (value, expected) in cases {
+            let status: AnalysisStatus = serde_json::from_value(json!(value)).unwrap();
+            assert_eq!(status, expected);
+        }
+    }
+
+    #[test]
+    fn test_analysis_status_serialization() {
+        assert_eq!(
+            serde_json::to_value(AnalysisStatus::from("FAKE")).unwrap(),
+            json!("MANIPULATED")
+        );
+        assert_eq!(
+            serde_json::to_value(AnalysisStatus::NotApplicable).unwrap(),
+            json!("NOT_APPLICABLE")
+        );
+        assert_eq!(
+            serde_json::to_value(AnalysisStatus::from("QUEUED")).unwrap(),
+            json!("QUEUED")
+        );
+        assert_eq!(AnalysisStatus::Suspicious.to_string(), "SUSPICIOUS");
+    }
+
+    #[test]
+    fn test_analysis_status_helpers() {
+        assert!(!AnalysisStatus::Analyzing.is_terminal());
+        assert!(!AnalysisStatus::Downloading.is_terminal());
+        assert!(AnalysisStatus::Manipulated.is_terminal());
+        assert!(AnalysisStatus::Error.is_terminal());
+        assert!(AnalysisStatus::from("COMPLETED").is_terminal());
+
+        assert!(AnalysisStatus::Suspicious.is_verdict());
+        assert!(!AnalysisStatus::Error.is_verdict());
+        assert!(!AnalysisStatus::NotApplicable.is_verdict());
+        assert!(!AnalysisStatus::from("COMPLETED").is_verdict());
+
+        assert!(AnalysisStatus::Manipulated.is_manipulated());
+        assert!(!AnalysisStatus::Suspicious.is_manipulated());
+        assert!(!AnalysisStatus::Authentic.is_manipulated());
+    }
 }
@@ -0,0 +1,567 @@
+use crate::error::{Error, Result};
+use crate::file::{find_media_type, find_media_type_by_mime, MediaCategory};
+use crate::models::Normalization;
+use image::codecs::jpeg::JpegEncoder;
+use image::codecs::png::{CompressionType, FilterType, PngEncoder};
+use image::imageops::FilterType as ResizeFilter;
+use image::{DynamicImage, ImageError, ImageFormat, ImageReader, Limits, RgbImage, RgbaImage};
+use std::fmt::Debug;
+use std::io::Cursor;
+use std::path::Path;
+use std::sync::Arc;
+
+/// Memory the decoder may allocate for an image by default, 512 MiB
+const DEFAULT_MAX_MEMORY: u64 = 512 * 1024 * 1024;
+
+/// Decodes images for normalization. Implement it to normalize formats the
+/// built-in decoder cannot read, such as AVIF.
+pub trait ImageDecoder: Debug + Send + Sync {
+    /// Whether images with the given lowercase extension can be decoded
+    fn supports(&self, extension: &str) -> bool;
+
+    /// Decode an image without allocating more than `max_memory` bytes
+    fn decode(&self, content: &[u8], max_memory: u64) -> Result<DecodedImage>;
+}
+
+/// Pixels of a decoded image
+#[derive(Debug, Clone)]
+pub struct DecodedImage(DynamicImage);
+
+impl DecodedImage {
+    /// Image from 8-bit RGB pixels, row by row
+    pub fn from_rgb8(width: u32, height: u32, pixels: Vec<u8>) -> Result<Self> {
+        check_pixels(width, height, 3, &pixels)?;
+        RgbImage::from_raw(width, height, pixels)
+            .map(|image| Self(DynamicImage::ImageRgb8(image)))
+            .ok_or_else(|| invalid_pixels(width, height))
+    }
+
+    /// Image from 8-bit RGBA pixels, row by row
+    pub fn from_rgba8(width: u32, height: u32, pixels: Vec<u8>) -> Result<Self> {
+        check_pixels(width, height, 4, &pixels)?;
+        RgbaImage::from_raw(width, height, pixels)
+            .map(|image| Self(DynamicImage::ImageRgba8(image)))
+            .ok_or_else(|| invalid_pixels(width, height))
+    }
+
+    /// Width in pixels
+    pub fn width(&self) -> u32 {
+        self.0.width()
+    }
+
+    /// Height in pixels
+    pub fn height(&self) -> u32 {
+        self.0.height()
+    }
+}
+
+/// Check that pixels exactly fill an image of the given dimensions
+fn check_pixels(width: u32, height: u32, channels: u64, pixels: &[u8]) -> Result<()> {
+    if width as u64 * height as u64 * channels != pixels.len() as u64 {
+        return Err(invalid_pixels(width, height));
+    }
+    Ok(())
+}
+
+fn invalid_pixels(width: u32, height: u32) -> Error {
+    Error::InvalidData(format!(
+        "The decoded pixels do not fill a {width}x{height} image"
+    ))
+}
+
+/// Decoder for the formats the `image` crate reads: BMP, GIF, JPEG, PNG,
+/// TIFF and WebP
+#[derive(Debug, Default, Clone, Copy)]
+pub struct BuiltinImageDecoder;
+
+impl ImageDecoder for BuiltinImageDecoder {
+    fn supports(&self, extension: &str) -> bool {
+        ImageFormat::from_extension(extension).is_some_and(|format| format.reading_enabled())
+    }
+
+    fn decode(&self, content: &[u8], max_memory: u64) -> Result<DecodedImage> {
+        let mut limits = Limits::default();
+        limits.max_alloc = Some(max_memory);
+        let mut reader = ImageReader::new(Cursor::new(content)).with_guessed_format()?;
+        reader.limits(limits);
+        reader.decode().map(DecodedImage).map_err(image_error)
+    }
+}
+
+/// Format normalized images are encoded in
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum NormalizedFormat {
+    /// Lossless PNG, keeping transparency
+    #[default]
+    Png,
+
+    /// JPEG at a quality from 1 to 100. Transparency is dropped.
+    Jpeg { quality: u8 },
+}
+
+impl NormalizedFormat {
+    /// Extension of images in this format
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Self::Png => "png",
+            Self::Jpeg { .. } => "jpg",
+        }
+    }
+
+    /// MIME type of images in this format
+    pub fn mime_type(&self) -> &'static str {
+        match self {
+            Self::Png => "image/png",
+            Self::Jpeg { .. } => "image/jpeg",
+        }
+    }
+}
+
+/// Settings for converting images that cannot be uploaded as they are into
+/// PNG or JPEG images that can
+#[derive(Debug, Clone)]
+pub struct ImageNormalization {
+    /// Decoder of the original images
+    pub decoder: Arc<dyn ImageDecoder>,
+
+    /// Format of normalized images
+    pub format: NormalizedFormat,
+
+    /// Largest width or height of normalized images. Larger images are
+    /// downscaled to fit.
+    pub max_dimension: Option<u32>,
+
+    /// Largest amount of memory in bytes the decoder may allocate for an
+    /// image, and largest image file read for decoding. Larger images are
+    /// rejected.
+    pub max_memory: u64,
+}
+
+impl Default for ImageNormalization {
+    fn default() -> Self {
+        Self {
+            decoder: Arc::new(BuiltinImageDecoder),
+            format: NormalizedFormat::Png,
+            max_dimension: None,
+            max_memory: DEFAULT_MAX_MEMORY,
+        }
+    }
+}
+
+impl ImageNormalization {
+    /// Whether an upload is normalized: images larger than their size limit
+    /// and images in formats that are not accepted, when they can be decoded
+    pub(crate) fn applies_to(&self, file_name: &str, size: u64) -> bool {
+        let path = Path::new(file_name);
+        let extension = match path.extension().and_then(|extension| extension.to_str()) {
+            Some(extension) => extension.to_lowercase(),
+            None => return false,
+        };
+
+        let rejected = match find_media_type(path) {
+            Some(media_type) => {
+                media_type.category == MediaCategory::Image && size > media_type.size_limit
+            }
+            None => true,
+        };
+        rejected && self.decoder.supports(&extension)
+    }
+
+    /// Reject image files larger than the decoder may allocate, before they
+    /// are read into memory
+    pub(crate) fn check_size(&self, file_name: &str, size: u64) -> Result<()> {
+        if size > self.max_memory {
+            return Err(invalid(format!(
+                "Cannot normalize {file_name}: the file of {size} bytes is larger than the {} bytes allowed",
+                self.max_memory
+            )));
+        }
+        Ok(())
+    }
+}
+
+fn invalid(reason: impl Into<String>) -> Error {
+    Error::InvalidFile(reason.into())
+}
+
+/// Error of an image decoded from or encoded to memory, where reads past the
+/// end mean the image is truncated
+fn image_error(err: ImageError) -> Error {
+    match err {
+        ImageError::IoError(err) if err.kind() == std::io::ErrorKind::UnexpectedEof => {
+            invalid("the image is truncated")
+        }
+        ImageError::Limits(_) => invalid("the image needs more memory than allowed to decode"),
+        other => invalid(other.to_string()),
+    }
+}
+
+/// Convert an image to the normalized format within its upload size limit,
+/// downscaling it when needed. Returns the name, content and record of the
+/// normalized image.
+pub(crate) fn normalize_image(
+    normalization: &ImageNormalization,
+    file_name: &str,
+    content: &[u8],
+) -> Result<(String, Vec<u8>, Normalization)> {
+    let path = Path::new(file_name);
+    let context = |err| match err {
+        Error::InvalidFile(reason) => invalid(format!("Cannot normalize {file_name}: {reason}")),
+        other => other,
+    };
+
+    normalization.check_size(file_name, content.len() as u64)?;
+    let DecodedImage(image) = normalization
+        .decoder
+        .decode(content, normalization.max_memory)
+        .map_err(context)?;
+    if image.width() == 0 || image.height() == 0 {
+        return Err(Error::InvalidData(format!(
+            "The decoded image of {file_name} has invalid dimensions {}x{}",
+            image.width(),
+            image.height()
+        )));
+    }
+
+    let format = normalization.format;
+    let size_limit = find_media_type_by_mime(format.mime_type())
+        .map(|media_type| media_type.size_limit)
+        .unwrap_or(u64::MAX);
+    let (encoded, dimensions) =
+        encode_within(&image, format, normalization.max_dimension, size_limit).map_err(context)?;
+
+    let stem = path
+        .file_stem()
+        .and_then(|stem| stem.to_str())
+        .unwrap_or("image");
+    let record = Normalization {
+        original_format: path
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .unwrap_or_default()
+            .to_lowercase(),
+        original_size: content.len() as u64,
+        original_dimensions: (image.width(), image.height()),
+        format: format.extension().to_string(),
+        size: encoded.len() as u64,
+        dimensions,
+    };
+    Ok((format!("{stem}.{}", format.extension()), encoded, record))
+}
+
+/// Encode an image no larger than `size_limit` bytes, fitting it within
+/// `max_dimension` and downscaling it further as needed. Returns the encoded
+/// image and its dimensions.
+fn encode_within(
+    image: &DynamicImage,
+    format: NormalizedFormat,
+    max_dimension: Option<u32>,
+    size_limit: u64,
+) -> Result<(Vec<u8>, (u32, u32))> {
+    let longest = image.width().max(image.height());
+    let mut scale = match max_dimension {
+        Some(max) if max < longest => max as f64 / longest as f64,
+        _ => 1.0,
+    };
+
+    loop {
+        let width = ((image.width() as f64 * scale).round() as u32).clamp(1, image.width());
+        let height = ((image.height() as f64 * scale).round() as u32).clamp(1, image.height());
+        let encoded = if (width, height) == (image.width(), image.height()) {
+            encode(image, format)?
+        } else {
+            encode(
+                &image.resize_exact(width, height, ResizeFilter::Lanczos3),
+                format,
+            )?
+        };
+
+        if encoded.len() as u64 <= size_limit {
+            return Ok((encoded, (width, height)));
+        }
+        if width == 1 && height == 1 {
+            return Err(invalid("the image does not fit within the size limit"));
+        }
+
+        // Shrink the area in proportion to the excess, with a margin since
+        // smaller images compress less well
+        scale *= (size_limit as f64 / encoded.len() as f64).sqrt() * 0.9;
+    }
+}
+
+/// Encode an image with 8 bits per channel, keeping transparency in PNGs
+fn encode(image: &DynamicImage, format: NormalizedFormat) -> Result<Vec<u8>> {
+    let mut encoded = Vec::new();
+    match format {
+        NormalizedFormat::Png => {
+            let encoder = PngEncoder::new_with_quality(
+                &mut encoded,
+                CompressionType::Best,
+                FilterType::Adaptive,
+            );
+            match image {
+                DynamicImage::ImageLuma8(_)
+                | DynamicImage::ImageLumaA8(_)
+                | DynamicImage::ImageRgb8(_)
+                | DynamicImage::ImageRgba8(_) => image.write_with_encoder(encoder),
+                _ if image.color().has_alpha() => {
+                    DynamicImage::ImageRgba8(image.to_rgba8()).write_with_encoder(encoder)
+                }
+                _ => DynamicImage::ImageRgb8(image.to_rgb8()).write_with_encoder(encoder),
+            }
+        }
+        NormalizedFormat::Jpeg { quality } => {
+            let encoder = JpegEncoder::new_with_quality(&mut encoded, quality.clamp(1, 100));
+            match image {
+                DynamicImage::ImageLuma8(_) | DynamicImage::ImageRgb8(_) => {
+                    image.write_with_encoder(encoder)
+                }
+                _ => DynamicImage::ImageRgb8(image.to_rgb8()).write_with_encoder(encoder),
+            }
+        }
+    }
+    .map_err(image_error)?;
+    Ok(encoded)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{GenericImageView, Rgb, RgbImage, Rgba, RgbaImage};
+
+    fn gradient(width: u32, height: u32) -> RgbImage {
+        RgbImage::from_fn(width, height, |x, y| {
+            Rgb([(x * 7) as u8, (y * 13) as u8, (x ^ y) as u8])
+        })
+    }
+
+    fn encoded(image: DynamicImage, format: ImageFormat) -> Vec<u8> {
+        let mut content = Cursor::new(Vec::new());
+        image.write_to(&mut content, format).unwrap();
+        content.into_inner()
+    }
+
+    fn decode(content: &[u8]) -> DynamicImage {
+        BuiltinImageDecoder.decode(content, u64::MAX).unwrap().0
+    }
+
+    /// Decoder standing in for an AVIF decoder, which the `image` crate only
+    /// provides through a system library
+    #[derive(Debug)]
+    struct SolidDecoder;
+
+    impl ImageDecoder for SolidDecoder {
+        fn supports(&self, extension: &str) -> bool {
+            extension == "avif"
+        }
+
+        fn decode(&self, content: &[u8], _max_memory: u64) -> Result<DecodedImage> {
+            if !content.starts_with(b"\x00\x00\x00\x1cftypavif") {
+                return Err(invalid("not an AVIF image"));
+            }
+            DecodedImage::from_rgba8(6, 4, [10, 20, 30, 128].repeat(6 * 4))
+        }
+    }
+
+    #[test]
+    fn test_normalize_tiff() {
+        let original = gradient(40, 30);
+        let content = encoded(DynamicImage::ImageRgb8(original.clone()), ImageFormat::Tiff);
+
+        let (name, png, record) =
+            normalize_image(&ImageNormalization::default(), "dir/scan.TIFF", &content).unwrap();
+        assert_eq!(name, "scan.png");
+        assert_eq!(
+            record,
+            Normalization {
+                original_format: "tiff".to_string(),
+                original_size: content.len() as u64,
+                original_dimensions: (40, 30),
+                format: "png".to_string(),
+                size: png.len() as u64,
+                dimensions: (40, 30),
+            }
+        );
+        assert!(png.starts_with(b"\x89PNG\r\n\x1A\n"));
+        assert_eq!(decode(&png).to_rgb8(), original);
+    }
+
+    #[test]
+    fn test_normalize_jpeg_to_jpeg_with_quality() {
+        let content = encoded(DynamicImage::ImageRgb8(gradient(64, 48)), ImageFormat::Jpeg);
+        let normalization = |quality| ImageNormalization {
+            format: NormalizedFormat::Jpeg { quality },
+            max_dimension: Some(32),
+            ..Default::default()
+        };
+
+        let (name, high, record) =
+            normalize_image(&normalization(95), "photo.jpeg", &content).unwrap();
+        assert_eq!(name, "photo.jpg");
+        assert_eq!(record.original_format, "jpeg");
+        assert_eq!(record.original_dimensions, (64, 48));
+        assert_eq!(record.format, "jpg");
+        assert_eq!(record.dimensions, (32, 24));
+        assert!(high.starts_with(&[0xFF, 0xD8, 0xFF]));
+        assert_eq!(decode(&high).dimensions(), (32, 24));
+
+        let (_, low, _) = normalize_image(&normalization(10), "photo.jpeg", &content).unwrap();
+        assert!(low.len() < high.len());
+    }
+
+    #[test]
+    fn test_normalize_keeps_transparency_in_png() {
+        let original = RgbaImage::from_pixel(5, 3, Rgba([200, 100, 50, 64]));
+        let content = encoded(DynamicImage::ImageRgba8(original.clone()), ImageFormat::Bmp);
+
+        let (_, png, record) =
+            normalize_image(&ImageNormalization::default(), "scan.bmp", &content).unwrap();
+        assert_eq!(record.original_format, "bmp");
+        assert_eq!(decode(&png).to_rgba8(), original);
+
+        // JPEG has no alpha channel
+        let normalization = ImageNormalization {
+            format: NormalizedFormat::Jpeg { quality: 90 },
+            ..Default::default()
+        };
+        let (_, jpeg, _) = normalize_image(&normalization, "scan.bmp", &content).unwrap();
+        assert!(!decode(&jpeg).color().has_alpha());
+    }
+
+    #[test]
+    fn test_normalize_with_custom_decoder() {
+        let normalization = ImageNormalization {
+            decoder: Arc::new(SolidDecoder),
+            ..Default::default()
+        };
+        assert!(normalization.applies_to("photo.avif", 100));
+        assert!(!normalization.applies_to("scan.tiff", 100));
+
+        let content = b"\x00\x00\x00\x1cftypavif\x00\x00\x00\x00";
+        let (name, png, record) = normalize_image(&normalization, "photo.avif", content).unwrap();
+        assert_eq!(name, "photo.png");
+        assert_eq!(record.original_format, "avif");
+        assert_eq!(record.dimensions, (6, 4));
+        assert_eq!(
+            decode(&png).to_rgba8().get_pixel(5, 3),
+            &Rgba([10, 20, 30, 128])
+        );
+
+        match normalize_image(&normalization, "photo.avif", b"garbage") {
+            Err(Error::InvalidFile(msg)) => {
+                assert_eq!(msg, "Cannot normalize photo.avif: not an AVIF image")
+            }
+            other => panic!("Expected InvalidFile error, got: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_normalize_caps_decoder_memory() {
+        // 64x64 RGB pixels need 12 KiB once decoded
+        let content = encoded(DynamicImage::ImageRgb8(gradient(64, 64)), ImageFormat::Png);
+        let normalization = ImageNormalization {
+            max_memory: 4096,
+            ..Default::default()
+        };
+        match normalize_image(&normalization, "photo.png", &content) {
+            Err(Error::InvalidFile(msg)) => assert_eq!(
+                msg,
+                "Cannot normalize photo.png: the image needs more memory than allowed to decode"
+            ),
+            other => panic!("Expected InvalidFile error, got: {:?}", other),
+        }
+
+        // A header announcing a huge image is rejected before decoding
+        let mut huge = content.clone();
+        huge[16..24].copy_from_slice(&[0, 1, 0, 0, 0, 1, 0, 0]);
+        assert!(matches!(
+            normalize_image(&ImageNormalization::default(), "photo.png", &huge),
+            Err(Error::InvalidFile(_))
+        ));
+    }
+
+    #[test]
+    fn test_normalize_rejects_large_files() {
+        let content = encoded(DynamicImage::ImageRgb8(gradient(16, 16)), ImageFormat::Bmp);
+        let normalization = ImageNormalization {
+            max_memory: 100,
+            ..Default::default()
+        };
+        match normalize_image(&normalization, "scan.bmp", &content) {
+            Err(Error::InvalidFile(msg)) => assert_eq!(
+                msg,
+                format!(
+                    "Cannot normalize scan.bmp: the file of {} bytes is larger than the 100 bytes allowed",
+                    content.len()
+                )
+            ),
+            other => panic!("Expected InvalidFile error, got: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_decoded_image_from_pixels() {
+        let image = DecodedImage::from_rgb8(2, 1, vec![1, 2, 3, 4, 5, 6]).unwrap();
+        assert_eq!((image.width(), image.height()), (2, 1));
+        assert!(matches!(
+            DecodedImage::from_rgba8(2, 2, vec![0; 15]),
+            Err(Error::InvalidData(_))
+        ));
+        assert!(matches!(
+            DecodedImage::from_rgb8(1, 1, vec![0; 4]),
+            Err(Error::InvalidData(_))
+        ));
+    }
+
+    #[test]
+    fn test_normalize_rejects_corrupt_images() {
+        let content = encoded(DynamicImage::ImageRgb8(gradient(16, 16)), ImageFormat::Tiff);
+        assert!(matches!(
+            normalize_image(
+                &ImageNormalization::default(),
+                "scan.tiff",
+                &content[..content.len() / 2]
+            ),
+            Err(Error::InvalidFile(_))
+        ));
+        match normalize_image(&ImageNormalization::default(), "scan.bmp", b"not an image") {
+            Err(Error::InvalidFile(msg)) => assert!(msg.starts_with("Cannot normalize scan.bmp: ")),
+            other => panic!("Expected InvalidFile error, got: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_encode_within_size_limit() {
+        let image = DynamicImage::ImageRgb8(gradient(200, 100));
+        let full = encode(&image, NormalizedFormat::Png).unwrap();
+
+        let (png, dimensions) =
+            encode_within(&image, NormalizedFormat::Png, None, full.len() as u64 / 3).unwrap();
+        assert!(png.len() as u64 <= full.len() as u64 / 3);
+        assert!(dimensions.0 < 200 && dimensions.1 < 100);
+        assert_eq!(decode(&png).dimensions(), dimensions);
+
+        match encode_within(&image, NormalizedFormat::Png, None, 10) {
+            Err(Error::InvalidFile(msg)) => {
+                assert_eq!(msg, "the image does not fit within the size limit")
+            }
+            other => panic!("Expected InvalidFile error, got: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_applies_to() {
+        let normalization = ImageNormalization::default();
+        assert!(normalization.applies_to("scan.bmp", 100));
+        assert!(normalization.applies_to("scan.tiff", 100));
+        assert!(normalization.applies_to("scan.TIF", 100));
+        assert!(!normalization.applies_to("photo.png", 100));
+        assert!(normalization.applies_to("photo.png", 60 * 1024 * 1024));
+        assert!(normalization.applies_to("photo.jpg", 60 * 1024 * 1024));
+        assert!(normalization.applies_to("photo.webp", 60 * 1024 * 1024));
+        // AVIF needs a decoder backed by a system library
+        assert!(!normalization.applies_to("photo.avif", 100));
+        assert!(!normalization.applies_to("clip.mp4", 300 * 1024 * 1024));
+        assert!(!normalization.applies_to("noextension", 100));
+    }
+}
@@ -0,0 +1,261 @@
+use std::fmt::Debug;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Decides how long to wait between polls for a result and when to stop.
+/// Strategies are shared by every file of a batch, so they keep no state of
+/// their own.
+pub trait PollStrategy: Debug + Send + Sync {
+    /// Delay before the next poll, given the number of polls made so far and
+    /// the time since the first one, or `None` to stop waiting
+    fn next_delay(&self, attempt: u64, elapsed: Duration) -> Option<Duration>;
+}
+
+/// Poll at a fixed interval for a limited number of attempts
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FixedInterval {
+    /// Delay between polls
+    pub interval: Duration,
+
+    /// Maximum number of polls
+    pub max_attempts: u64,
+}
+
+impl Default for FixedInterval {
+    fn default() -> Self {
+        Self {
+            interval: Duration::from_secs(2),
+            max_attempts: 150,
+        }
+    }
+}
+
+impl PollStrategy for FixedInterval {
+    fn next_delay(&self, attempt: u64, _elapsed: Duration) -> Option<Duration> {
+        (attempt < self.max_attempts).then_some(self.interval)
+    }
+}
+
+/// Poll often at first and less and less often as the analysis takes longer,
+/// which suits long videos
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ExponentialBackoff {
+    /// Delay after the first poll
+    pub initial_interval: Duration,
+
+    /// Factor applied to the delay after each poll
+    pub multiplier: f64,
+
+    /// Upper bound for the delay between polls
+    pub max_interval: Duration,
+
+    /// Maximum number of polls. Unlimited when `None`.
+    pub max_attempts: Option<u64>,
+}
+
+impl Default for ExponentialBackoff {
+    fn default() -> Self {
+        Self {
+            initial_interval: Duration::from_secs(1),
+            multiplier: 1.5,
+            max_interval: Duration::from_secs(30),
+            max_attempts: Some(60),
+        }
+    }
+}
+
+impl PollStrategy for ExponentialBackoff {
+    fn next_delay(&self, attempt: u64, _elapsed: Duration) -> Option<Duration> {
+        if self
+            .max_attempts
+            .is_some_and(|max_attempts| attempt >= max_attempts)
+        {
+            return None;
+        }
+
+        let exponent = attempt.saturating_sub(1).min(i32::MAX as u64) as i32;
+        let delay = self.initial_interval.as_secs_f64() * self.multiplier.powi(exponent);
+        Some(
+            Duration::try_from_secs_f64(delay)
+                .unwrap_or(self.max_interval)
+                .min(self.max_interval),
+        )
+    }
+}
+
+/// Poll at a fixed interval until a wall-clock deadline, polling one last
+/// time when it is reached
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Deadline {
+    /// Delay between polls
+    pub interval: Duration,
+
+    /// How long to wait for the result, counted from the first poll
+    pub deadline: Duration,
+}
+
+impl PollStrategy for Deadline {
+    fn next_delay(&self, _attempt: u64, elapsed: Duration) -> Option<Duration> {
+        let remaining = self.deadline.checked_sub(elapsed)?;
+        (!remaining.is_zero()).then(|| self.interval.min(remaining))
+    }
+}
+
+/// Delay between polls when only a deadline is given
+const DEADLINE_POLLING_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Strategy to wait with: the given one, a fixed interval when both
+/// `max_attempts` and `polling_interval` are non-zero, or else polling until
+/// the deadline. Results are fetched once without waiting when this is `None`.
+pub(crate) fn select_strategy(
+    strategy: &Option<Arc<dyn PollStrategy>>,
+    max_attempts: Option<u64>,
+    polling_interval: Option<u64>,
+    deadline: Option<Duration>,
+) -> Option<Arc<dyn PollStrategy>> {
+    if let Some(strategy) = strategy {
+        return Some(strategy.clone());
+    }
+
+    match (max_attempts, polling_interval) {
+        (Some(max_attempts), Some(polling_interval))
+            if max_attempts > 0 && polling_interval > 0 =>
+        {
+            Some(Arc::new(FixedInterval {
+                interval: Duration::from_millis(polling_interval),
+                max_attempts,
+            }))
+        }
+        _ => deadline.map(|deadline| {
+            Arc::new(Deadline {
+                interval: DEADLINE_POLLING_INTERVAL,
+                deadline,
+            }) as Arc<dyn PollStrategy>
+        }),
+    }
+}
+
+/// Delay before the next poll under a strategy and an optional deadline,
+/// which shortens the last delay so the final poll happens at the deadline
+pub(crate) fn next_delay(
+    strategy: &dyn PollStrategy,
+    deadline: Option<Duration>,
+    attempt: u64,
+    elapsed: Duration,
+) -> Option<Duration> {
+    let delay = strategy.next_delay(attempt, elapsed)?;
+    match deadline {
+        Some(deadline) => Deadline {
+            interval: delay,
+            deadline,
+        }
+        .next_delay(attempt, elapsed),
+        None => Some(delay),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fixed_interval() {
+        let strategy = FixedInterval {
+            interval: Duration::from_millis(500),
+            max_attempts: 3,
+        };
+        assert_eq!(
+            strategy.next_delay(1, Duration::ZERO),
+            Some(Duration::from_millis(500))
+        );
+        assert_eq!(
+            strategy.next_delay(2, Duration::from_secs(100)),
+            Some(Duration::from_millis(500))
+        );
+        assert_eq!(strategy.next_delay(3, Duration::ZERO), None);
+    }
+
+    #[test]
+    fn test_exponential_backoff() {
+        let strategy = ExponentialBackoff {
+            initial_interval: Duration::from_secs(1),
+            multiplier: 2.0,
+            max_interval: Duration::from_secs(5),
+            max_attempts: None,
+        };
+        let delays: Vec<_> = (1..=5)
+            .map(|attempt| strategy.next_delay(attempt, Duration::ZERO).unwrap())
+            .collect();
+        assert_eq!(delays, [1, 2, 4, 5, 5].map(Duration::from_secs).to_vec());
+        assert_eq!(
+            strategy.next_delay(u64::MAX, Duration::ZERO),
+            Some(Duration::from_secs(5))
+        );
+
+        let strategy = ExponentialBackoff {
+            max_attempts: Some(2),
+            ..strategy
+        };
+        assert!(strategy.next_delay(1, Duration::ZERO).is_some());
+        assert_eq!(strategy.next_delay(2, Duration::ZERO), None);
+    }
+
+    #[test]
+    fn test_deadline() {
+        let strategy = Deadline {
+            interval: Duration::from_secs(2),
+            deadline: Duration::from_secs(5),
+        };
+        assert_eq!(
+            strategy.next_delay(1, Duration::ZERO),
+            Some(Duration::from_secs(2))
+        );
+        assert_eq!(
+            strategy.next_delay(3, Duration::from_secs(4)),
+            Some(Duration::from_secs(1))
+        );
+        assert_eq!(strategy.next_delay(4, Duration::from_secs(5)), None);
+        assert_eq!(strategy.next_delay(4, Duration::from_secs(6)), None);
+    }
+
+    #[test]
+    fn test_next_delay_honours_deadline() {
+        let strategy = FixedInterval {
+            interval: Duration::from_secs(10),
+            max_attempts: 5,
+        };
+        let deadline = Some(Duration::from_secs(15));
+        assert_eq!(
+            next_delay(&strategy, deadline, 1, Duration::ZERO),
+            Some(Duration::from_secs(10))
+        );
+        assert_eq!(
+            next_delay(&strategy, deadline, 2, Duration::from_secs(10)),
+            Some(Duration::from_secs(5))
+        );
+        assert_eq!(
+            next_delay(&strategy, deadline, 3, Duration::from_secs(15)),
+            None
+        );
+        assert_eq!(next_delay(&strategy, None, 5, Duration::ZERO), None);
+    }
+
+    #[test]
+    fn test_select_strategy() {
+        assert!(select_strategy(&None, None, None, None).is_none());
+        assert!(select_strategy(&None, Some(5), Some(0), None).is_none());
+
+        let strategy = select_strategy(&None, Some(5), Some(100), None).unwrap();
+        assert_eq!(
+            strategy.next_delay(1, Duration::ZERO),
+            Some(Duration::from_millis(100))
+        );
+        assert_eq!(strategy.next_delay(5, Duration::ZERO), None);
+
+        let strategy = select_strategy(&None, None, None, Some(Duration::from_secs(60))).unwrap();
+        assert_eq!(
+            strategy.next_delay(100, Duration::from_secs(59)),
+            Some(Duration::from_secs(1))
+        );
+    }
+}
@@ -0,0 +1,753 @@
+use crate::client::{cancellable, Client};
+use crate::error::{Error, Result};
+use crate::models::{DetectionResult, GetResultOptions, GetResultsOptions, PollerOptions};
+use crate::poll::{next_delay, select_strategy, PollStrategy};
+use crate::utils::{format_date, lock};
+use futures::{stream, StreamExt};
+use std::collections::HashMap;
+use std::sync::{Mutex, PoisonError};
+use std::time::{Duration, SystemTime};
+use tokio::sync::{oneshot, watch, Mutex as AsyncMutex};
+use tokio::time::{sleep_until, timeout, Instant};
+
+/// Delay between polling rounds when none is given
+const DEFAULT_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Number of results listed per page when none is given
+const DEFAULT_PAGE_SIZE: u32 = 100;
+
+/// Maximum number of pages listed per round when none is given
+const DEFAULT_MAX_PAGES: u32 = 10;
+
+/// Rounds a request can be missing from the listed pages when none is given
+const DEFAULT_STRAGGLER_ROUNDS: u32 = 3;
+
+/// Number of stragglers fetched at the same time
+const STRAGGLER_CONCURRENCY: usize = 8;
+
+/// Margin added around the date window, since the API may filter dates in
+/// another time zone
+const DATE_MARGIN: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// A request awaited by one or more waiters
+struct Pending {
+    /// When the request was submitted, which bounds the listed date window
+    submitted_at: SystemTime,
+
+    /// Channels of the waiters, sent the result once it is terminal
+    waiters: Vec<oneshot::Sender<Result<DetectionResult>>>,
+
+    /// Consecutive rounds the request was missing from the listed pages
+    missed_rounds: u32,
+
+    /// Last result listed or fetched while the request was being analyzed
+    last: Option<DetectionResult>,
+}
+
+impl Pending {
+    /// Send a result to every waiter
+    fn resolve(self, result: &Result<DetectionResult>) {
+        for waiter in self.waiters {
+            let _ = waiter.send(match result {
+                Ok(result) => Ok(result.clone()),
+                Err(err) => Err(err.duplicate()),
+            });
+        }
+    }
+}
+
+/// Waits on many requests at once, refreshing them in bulk through the
+/// paginated results list instead of polling each request on its own.
+///
+/// Each round lists the results submitted within the date window of the
+/// pending requests and resolves those that reached a terminal status.
+/// Requests missing from the listed pages for a few rounds are fetched on
+/// their own. Rounds are run by one of the waiters at a time, so the poller
+/// needs no background task.
+pub struct ResultPoller<'a> {
+    client: &'a Client,
+    interval: Duration,
+    page_size: u32,
+    max_pages: u32,
+    straggler_rounds: u32,
+    pending: Mutex<HashMap<String, Pending>>,
+
+    /// Held by the waiter running the rounds, with the time of the next round
+    driver: AsyncMutex<Option<Instant>>,
+
+    /// Number of rounds run, which waiters count as polling attempts
+    rounds: watch::Sender<u64>,
+}
+
+impl<'a> ResultPoller<'a> {
+    pub(crate) fn new(client: &'a Client, options: PollerOptions) -> Self {
+        Self {
+            client,
+            interval: options.interval.unwrap_or(DEFAULT_INTERVAL),
+            page_size: options.page_size.unwrap_or(DEFAULT_PAGE_SIZE).max(1),
+            max_pages: options.max_pages.unwrap_or(DEFAULT_MAX_PAGES).max(1),
+            straggler_rounds: options.straggler_rounds.unwrap_or(DEFAULT_STRAGGLER_ROUNDS),
+            pending: Mutex::new(HashMap::new()),
+            driver: AsyncMutex::new(None),
+            rounds: watch::Sender::new(0),
+        }
+    }
+
+    /// Wait for the terminal result of a request submitted just now. See
+    /// [`ResultPoller::wait_since`] for the options.
+    pub async fn wait(
+        &self,
+        request_id: &str,
+        options: Option<GetResultOptions>,
+    ) -> Result<DetectionResult> {
+        self.wait_since(request_id, SystemTime::now(), options)
+            .await
+    }
+
+    /// Wait for the terminal result of a request submitted at `submitted_at`.
+    /// Requests submitted earlier than the given time are still found, by
+    /// fetching them on their own.
+    ///
+    /// The options give up and cancel waiting as for [`Client::get_result`],
+    /// with `Error::Timeout` or `Error::Cancelled`. Rounds run at the
+    /// poller's interval, and each one counts as an attempt of the strategy.
+    /// Without options, waiting never gives up.
+    pub async fn wait_since(
+        &self,
+        request_id: &str,
+        submitted_at: SystemTime,
+        options: Option<GetResultOptions>,
+    ) -> Result<DetectionResult> {
+        let options = options.unwrap_or_default();
+        let strategy = select_strategy(
+            &options.poll_strategy,
+            options.max_attempts,
+            options.polling_interval,
+            options.deadline,
+        );
+        let start = Instant::now();
+
+        let waiting = self.wait_rounds(
+            request_id,
+            submitted_at,
+            strategy.as_deref(),
+            options.deadline,
+            start,
+        );
+        cancellable(options.cancel.as_ref(), &[request_id], async {
+            match options.deadline {
+                Some(deadline) => timeout(deadline, waiting)
+                    .await
+                    .unwrap_or_else(|_| Err(self.timeout(request_id, start))),
+                None => waiting.await,
+            }
+        })
+        .await
+    }
+
+    /// Wait for a request through the polling rounds, giving up when the
+    /// strategy says so
+    async fn wait_rounds(
+        &self,
+        request_id: &str,
+        submitted_at: SystemTime,
+        strategy: Option<&dyn PollStrategy>,
+        deadline: Option<Duration>,
+        start: Instant,
+    ) -> Result<DetectionResult> {
+        let mut receiver = self.register(request_id, submitted_at)?;
+        let mut rounds = self.rounds.subscribe();
+        let first_round = *rounds.borrow_and_update();
+
+        loop {
+            tokio::select! {
+                biased;
+                result = &mut receiver => {
+                    return result.unwrap_or_else(|_| {
+                        Err(Error::UnknownError(format!(
+                            "Poller stopped before the result of {} was ready",
+                            request_id
+                        )))
+                    });
+                }
+                // Rounds run by other waiters count as attempts too
+                Ok(()) = rounds.changed(), if strategy.is_some() => {}
+                mut next_round = self.driver.lock() => {
+                    // The previous driver may have resolved this request
+                    if let Ok(result) = receiver.try_recv() {
+                        return result;
+                    }
+                    self.run_round(&mut next_round).await?;
+                }
+            }
+
+            if let Some(strategy) = strategy {
+                let attempt = *rounds.borrow_and_update() - first_round;
+                if attempt > 0 && next_delay(strategy, deadline, attempt, start.elapsed()).is_none()
+                {
+                    // The last round may have resolved the request
+                    if let Ok(result) = receiver.try_recv() {
+                        return result;
+                    }
+                    return Err(self.timeout(request_id, start));
+                }
+            }
+        }
+    }
+
+    /// Timeout of a wait started at `start`, with the last result seen for
+    /// the request
+    fn timeout(&self, request_id: &str, start: Instant) -> Error {
+        let last = self
+            .pending
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .get(request_id)
+            .and_then(|entry| entry.last.clone());
+        Error::Timeout {
+            request_id: request_id.to_string(),
+            elapsed: start.elapsed(),
+            last: last.map(Box::new),
+        }
+    }
+
+    /// Number of requests still awaited
+    pub fn pending_count(&self) -> usize {
+        self.pending
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .len()
+    }
+
+    /// Add a waiter for a request
+    fn register(
+        &self,
+        request_id: &str,
+        submitted_at: SystemTime,
+    ) -> Result<oneshot::Receiver<Result<DetectionResult>>> {
+        let (sender, receiver) = oneshot::channel();
+        let mut pending = lock(&self.pending, "result poller")?;
+        let entry = pending
+            .entry(request_id.to_string())
+            .or_insert_with(|| Pending {
+                submitted_at,
+                waiters: Vec::new(),
+                missed_rounds: 0,
+                last: None,
+            });
+        entry.submitted_at = entry.submitted_at.min(submitted_at);
+        entry.waiters.push(sender);
+        Ok(receiver)
+    }
+
+    /// Refresh the pending requests once, after waiting for the round to be due
+    async fn run_round(&self, next_round: &mut Option<Instant>) -> Result<()> {
+        if let Some(next_round) = *next_round {
+            sleep_until(next_round).await;
+        }
+        *next_round = Some(Instant::now() + self.interval);
+
+        // Forget requests whose waiters all went away
+        let earliest = {
+            let mut pending = lock(&self.pending, "result poller")?;
+            pending.retain(|_, entry| {
+                entry.waiters.retain(|waiter| !waiter.is_closed());
+                !entry.waiters.is_empty()
+            });
+            pending.values().map(|entry| entry.submitted_at).min()
+        };
+        let Some(earliest) = earliest else {
+            return Ok(());
+        };
+
+        let listed = match self.list_results(earliest).await {
+            Ok(listed) => listed,
+            // Polling each request on its own would fail the same way, e.g.
+            // with a revoked API key, so every wait ends with the error
+            Err(err) => {
+                let err = Err(err);
+                for (_, entry) in lock(&self.pending, "result poller")?.drain() {
+                    entry.resolve(&err);
+                }
+                return Ok(());
+            }
+        };
+
+        let stragglers = {
+            let mut pending = lock(&self.pending, "result poller")?;
+            let mut stragglers = Vec::new();
+            let mut resolved = Vec::new();
+            for (request_id, entry) in pending.iter_mut() {
+                match listed.get(request_id) {
+                    Some(result) if result.status.is_terminal() => {
+                        resolved.push(request_id.clone())
+                    }
+                    Some(result) => {
+                        entry.missed_rounds = 0;
+                        entry.last = Some(result.clone());
+                    }
+                    None => {
+                        entry.missed_rounds += 1;
+                        if entry.missed_rounds >= self.straggler_rounds {
+                            stragglers.push(request_id.clone());
+                        }
+                    }
+                }
+            }
+            for request_id in resolved {
+                if let Some(entry) = pending.remove(&request_id) {
+                    entry.resolve(&Ok(listed[&request_id].clone()));
+                }
+            }
+            stragglers
+        };
+
+        let fetched: Vec<_> = stream::iter(stragglers)
+            .map(|request_id| async move {
+                let result = self.client.fetch_result(&request_id).await;
+                (request_id, result)
+            })
+            .buffer_unordered(STRAGGLER_CONCURRENCY)
+            .collect()
+            .await;
+
+        let mut pending = lock(&self.pending, "result poller")?;
+        for (request_id, result) in fetched {
+            match &result {
+                Ok(analyzing) if !analyzing.status.is_terminal() => {
+                    if let Some(entry) = pending.get_mut(&request_id) {
+                        entry.last = Some(analyzing.clone());
+                    }
+                }
+                // Like a failed listing, a transient failure is retried in
+                // the next round, until the wait gives up
+                Err(err) if err.is_retryable() => {}
+                _ => {
+                    if let Some(entry) = pending.remove(&request_id) {
+                        entry.resolve(&result);
+                    }
+                }
+            }
+        }
+        drop(pending);
+
+        self.rounds.send_modify(|rounds| *rounds += 1);
+        Ok(())
+    }
+
+    /// List the results submitted within the date window of the pending
+    /// requests, by request ID. Listing stops at the first page failing with
+    /// a transient error, and the requests it missed are eventually fetched
+    /// on their own. Other errors are returned.
+    async fn list_results(&self, earliest: SystemTime) -> Result<HashMap<String, DetectionResult>> {
+        let start = earliest.checked_sub(DATE_MARGIN).unwrap_or(earliest);
+        let options = GetResultsOptions {
+            size: Some(self.page_size),
+            start_date: Some(format_date(start)),
+            end_date: Some(format_date(SystemTime::now() + DATE_MARGIN)),
+            ..Default::default()
+        };
+
+        let mut listed = HashMap::new();
+        for page_number in 0..self.max_pages {
+            let page = match self
+                .client
+                .fetch_results(GetResultsOptions {
+                    page_number: Some(page_number),
+                    ..options.clone()
+                })
+                .await
+            {
+                Ok(page) => page,
+                Err(err) if err.is_retryable() => break,
+                Err(err) => return Err(err),
+            };
+            listed.extend(
+                page.items
+                    .into_iter()
+                    .map(|result| (result.request_id.clone(), result)),
+            );
+
+            if page_number + 1 >= page.total_pages {
+                break;
+            }
+            // Stop early once every pending request was listed
+            let pending = lock(&self.pending, "result poller")?;
+            if pending
+                .keys()
+                .all(|request_id| listed.contains_key(request_id))
+            {
+                break;
+            }
+        }
+        Ok(listed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        CancellationToken, Client, Config, Error, FixedInterval, GetResultOptions, PollerOptions,
+    };
+    use futures::future::join_all;
+    use mockito::Matcher;
+    use serde_json::json;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    fn test_client(server: &mockito::Server) -> Client {
+        Client::new(Config {
+            api_key: "test_api_key".to_string(),
+            base_url: Some(server.url()),
+            ..Default::default()
+        })
+        .unwrap()
+    }
+
+    fn page(items: serde_json::Value, current_page: u32, total_pages: u32) -> String {
+        json!({
+            "totalItems": 200,
+            "totalPages": total_pages,
+            "currentPage": current_page,
+            "currentPageItemsCount": items.as_array().unwrap().len(),
+            "mediaList": items,
+        })
+        .to_string()
+    }
+
+    #[tokio::test]
+    async fn test_poller_lists_results_in_bulk() {
+        let mut server = mockito::Server::new_async().await;
+
+        // "a" is done at once, "b" after a second round, and "c" is never listed
+        let rounds = Arc::new(AtomicUsize::new(0));
+        let counter = rounds.clone();
+        let list_mock = server
+            .mock("GET", "/api/v2/media/users/pages/0")
+            .match_query(Matcher::AllOf(vec![
+                Matcher::UrlEncoded("size".to_string(), "50".to_string()),
+                Matcher::Regex(r"startDate=\d{4}-\d{2}-\d{2}".to_string()),
+                Matcher::Regex(r"endDate=\d{4}-\d{2}-\d{2}".to_string()),
+            ]))
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body_from_request(move |_| {
+                let b_status = match counter.fetch_add(1, Ordering::SeqCst) {
+                    0 => "ANALYZING",
+                    _ => "MANIPULATED",
+                };
+                page(
+                    json!([
+                        {"requestId": "a", "overallStatus": "AUTHENTIC", "finalScore": 10, "models": []},
+                        {"requestId": "b", "overallStatus": b_status, "models": []},
+                    ]),
+                    0,
+                    1,
+                )
+                .into_bytes()
+            })
+            .expect(2)
+            .create_async()
+            .await;
+        let straggler_mock = server
+            .mock("GET", "/api/media/users/c")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(
+                json!({"requestId": "c", "overallStatus": "AUTHENTIC", "models": []}).to_string(),
+            )
+            .expect(1)
+            .create_async()
+            .await;
+        let single_mock = server
+            .mock("GET", Matcher::Regex("^/api/media/users/[ab]$".to_string()))
+            .expect(0)
+            .create_async()
+            .await;
+
+        let client = test_client(&server);
+        let poller = client.result_poller(PollerOptions {
+            interval: Some(Duration::from_millis(10)),
+            page_size: Some(50),
+            straggler_rounds: Some(1),
+            ..Default::default()
+        });
+
+        let results = join_all(["a", "b", "c", "a"].map(|id| poller.wait(id, None))).await;
+        let statuses: Vec<_> = results
+            .into_iter()
+            .map(|result| result.unwrap().status.as_str().to_string())
+            .collect();
+
+        assert_eq!(
+            statuses,
+            ["AUTHENTIC", "MANIPULATED", "AUTHENTIC", "AUTHENTIC"]
+        );
+        assert_eq!(poller.pending_count(), 0);
+        list_mock.assert_async().await;
+        straggler_mock.assert_async().await;
+        single_mock.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_poller_stops_listing_once_all_are_found() {
+        let mut server = mockito::Server::new_async().await;
+
+        let first_page = server
+            .mock("GET", "/api/v2/media/users/pages/0")
+            .match_query(Matcher::Any)
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(page(
+                json!([{"requestId": "other", "overallStatus": "AUTHENTIC", "models": []}]),
+                0,
+                5,
+            ))
+            .expect(1)
+            .create_async()
+            .await;
+        let second_page = server
+            .mock("GET", "/api/v2/media/users/pages/1")
+            .match_query(Matcher::Any)
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(page(
+                json!([{"requestId": "d", "overallStatus": "MANIPULATED", "models": []}]),
+                1,
+                5,
+            ))
+            .expect(1)
+            .create_async()
+            .await;
+        let third_page = server
+            .mock("GET", "/api/v2/media/users/pages/2")
+            .match_query(Matcher::Any)
+            .expect(0)
+            .create_async()
+            .await;
+
+        let client = test_client(&server);
+        let poller = client.result_poller(PollerOptions::default());
+        let result = poller.wait("d", None).await.unwrap();
+
+        assert_eq!(result.request_id, "d");
+        first_page.assert_async().await;
+        second_page.assert_async().await;
+        third_page.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_poller_falls_back_when_listing_fails() {
+        let mut server = mockito::Server::new_async().await;
+
+        server
+            .mock("GET", "/api/v2/media/users/pages/0")
+            .match_query(Matcher::Any)
+            .with_status(503)
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"response": "Service unavailable"}"#)
+            .create_async()
+            .await;
+        server
+            .mock("GET", "/api/media/users/e")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(
+                json!({"requestId": "e", "overallStatus": "AUTHENTIC", "models": []}).to_string(),
+            )
+            .create_async()
+            .await;
+        server
+            .mock("GET", "/api/media/users/missing")
+            .with_status(404)
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"response": "Resource not found"}"#)
+            .create_async()
+            .await;
+
+        let client = test_client(&server);
+        let poller = client.result_poller(PollerOptions {
+            interval: Some(Duration::from_millis(10)),
+            straggler_rounds: Some(2),
+            ..Default::default()
+        });
+
+        let (found, missing) = tokio::join!(poller.wait("e", None), poller.wait("missing", None));
+        assert_eq!(found.unwrap().request_id, "e");
+        assert!(matches!(missing, Err(Error::NotFound(_))));
+    }
+
+    #[tokio::test]
+    async fn test_poller_retries_stragglers_after_transient_errors() {
+        let mut server = mockito::Server::new_async().await;
+
+        server
+            .mock("GET", "/api/v2/media/users/pages/0")
+            .match_query(Matcher::Any)
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(page(json!([]), 0, 1))
+            .create_async()
+            .await;
+        let unavailable_mock = server
+            .mock("GET", "/api/media/users/s")
+            .with_status(503)
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"response": "Service unavailable"}"#)
+            .expect(2)
+            .create_async()
+            .await;
+        let result_mock = server
+            .mock("GET", "/api/media/users/s")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(
+                json!({"requestId": "s", "overallStatus": "AUTHENTIC", "models": []}).to_string(),
+            )
+            .expect(1)
+            .create_async()
+            .await;
+
+        let client = test_client(&server);
+        let poller = client.result_poller(PollerOptions {
+            interval: Some(Duration::from_millis(10)),
+            straggler_rounds: Some(1),
+            ..Default::default()
+        });
+
+        let result = poller.wait("s", None).await.unwrap();
+        assert_eq!(result.status.to_string(), "AUTHENTIC");
+        unavailable_mock.assert_async().await;
+        result_mock.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_poller_fails_every_wait_when_listing_is_unauthorized() {
+        let mut server = mockito::Server::new_async().await;
+
+        let list_mock = server
+            .mock("GET", "/api/v2/media/users/pages/0")
+            .match_query(Matcher::Any)
+            .with_status(401)
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"response": "Invalid API key"}"#)
+            .expect(1)
+            .create_async()
+            .await;
+        let result_mock = server
+            .mock("GET", Matcher::Regex("^/api/media/users/".to_string()))
+            .expect(0)
+            .create_async()
+            .await;
+
+        let client = test_client(&server);
+        let poller = client.result_poller(PollerOptions {
+            interval: Some(Duration::from_millis(10)),
+            straggler_rounds: Some(1),
+            ..Default::default()
+        });
+
+        let results =
+            join_all(["a", "b", "a"].map(|request_id| poller.wait(request_id, None))).await;
+        for result in results {
+            assert!(matches!(result, Err(Error::Unauthorized(_))), "{result:?}");
+        }
+        assert_eq!(poller.pending_count(), 0);
+        list_mock.assert_async().await;
+        result_mock.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_poller_reports_poisoned_lock() {
+        let server = mockito::Server::new_async().await;
+        let client = test_client(&server);
+        let poller = client.result_poller(PollerOptions::default());
+
+        // A panic while holding the lock poisons it
+        std::thread::scope(|scope| {
+            let _ = scope
+                .spawn(|| {
+                    let _pending = poller.pending.lock().unwrap();
+                    panic!("poisoning the lock");
+                })
+                .join();
+        });
+
+        assert_eq!(poller.pending_count(), 0);
+        match poller.wait("a", None).await {
+            Err(Error::UnknownError(msg)) => assert_eq!(msg, "Lock of the result poller poisoned"),
+            other => panic!("Expected UnknownError, got: {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_poller_wait_gives_up_or_is_cancelled() {
+        let mut server = mockito::Server::new_async().await;
+
+        // "a" is analyzed forever
+        let list_mock = server
+            .mock("GET", "/api/v2/media/users/pages/0")
+            .match_query(Matcher::Any)
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(page(
+                json!([{"requestId": "a", "overallStatus": "ANALYZING", "models": []}]),
+                0,
+                1,
+            ))
+            .expect_at_least(2)
+            .create_async()
+            .await;
+
+        let client = test_client(&server);
+        let poller = client.result_poller(PollerOptions {
+            interval: Some(Duration::from_millis(10)),
+            ..Default::default()
+        });
+
+        let attempts = GetResultOptions {
+            poll_strategy: Some(Arc::new(FixedInterval {
+                interval: Duration::from_millis(10),
+                max_attempts: 2,
+            })),
+            ..Default::default()
+        };
+        match poller.wait("a", Some(attempts)).await {
+            Err(Error::Timeout {
+                request_id, last, ..
+            }) => {
+                assert_eq!(request_id, "a");
+                assert_eq!(last.unwrap().status.to_string(), "ANALYZING");
+            }
+            other => panic!("Expected Timeout, got: {:?}", other),
+        }
+
+        let deadline = GetResultOptions {
+            deadline: Some(Duration::from_millis(50)),
+            ..Default::default()
+        };
+        match poller.wait("a", Some(deadline)).await {
+            Err(Error::Timeout { elapsed, .. }) => {
+                assert!(elapsed >= Duration::from_millis(50), "{elapsed:?}")
+            }
+            other => panic!("Expected Timeout, got: {:?}", other),
+        }
+
+        let cancel = CancellationToken::new();
+        let canceller = cancel.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(30)).await;
+            canceller.cancel();
+        });
+        let cancelled = GetResultOptions {
+            cancel: Some(cancel),
+            ..Default::default()
+        };
+        match poller.wait("a", Some(cancelled)).await {
+            Err(Error::Cancelled { request_ids }) => assert_eq!(request_ids, ["a"]),
+            other => panic!("Expected Cancelled, got: {:?}", other),
+        }
+        list_mock.assert_async().await;
+    }
+}
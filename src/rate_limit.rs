@@ -0,0 +1,109 @@
+use crate::config::RateLimit;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tokio::time::sleep;
+
+/// Token bucket shared by every request made through a client
+#[derive(Debug)]
+pub struct RateLimiter {
+    rate: f64,
+    burst: f64,
+    bucket: Mutex<Bucket>,
+}
+
+#[derive(Debug)]
+struct Bucket {
+    /// Available tokens. Negative when callers have reserved future tokens.
+    tokens: f64,
+    refilled_at: Instant,
+}
+
+impl RateLimiter {
+    /// Create a limiter that starts with a full bucket
+    pub fn new(limit: &RateLimit) -> Self {
+        let burst = f64::from(limit.burst.max(1));
+        Self {
+            rate: limit.requests_per_second,
+            burst,
+            bucket: Mutex::new(Bucket {
+                tokens: burst,
+                refilled_at: Instant::now(),
+            }),
+        }
+    }
+
+    /// Wait until a request may be sent
+    pub async fn acquire(&self) {
+        let wait = self.reserve();
+        if !wait.is_zero() {
+            sleep(wait).await;
+        }
+    }
+
+    /// Take a token, returning how long to wait before it becomes available.
+    /// Tokens are reserved up front so waiting callers are served in order.
+    fn reserve(&self) -> Duration {
+        let mut bucket = self
+            .bucket
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+
+        let now = Instant::now();
+        let elapsed = now.duration_since(bucket.refilled_at).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * self.rate).min(self.burst);
+        bucket.refilled_at = now;
+
+        bucket.tokens -= 1.0;
+        if bucket.tokens >= 0.0 {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64(-bucket.tokens / self.rate)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn limiter(requests_per_second: f64, burst: u32) -> RateLimiter {
+        RateLimiter::new(&RateLimit {
+            requests_per_second,
+            burst,
+        })
+    }
+
+    #[test]
+    fn test_burst_is_available_immediately() {
+        let limiter = limiter(1.0, 3);
+
+        for _ in 0..3 {
+            assert_eq!(limiter.reserve(), Duration::ZERO);
+        }
+        assert!(limiter.reserve() > Duration::from_millis(900));
+    }
+
+    #[test]
+    fn test_waiting_callers_are_spaced_by_rate() {
+        let limiter = limiter(10.0, 1);
+
+        assert_eq!(limiter.reserve(), Duration::ZERO);
+        let first = limiter.reserve();
+        let second = limiter.reserve();
+        assert!(first > Duration::from_millis(90) && first <= Duration::from_millis(100));
+        assert!(second > Duration::from_millis(190) && second <= Duration::from_millis(200));
+    }
+
+    #[tokio::test]
+    async fn test_acquire_enforces_rate() {
+        let limiter = limiter(20.0, 2);
+
+        let started = Instant::now();
+        for _ in 0..4 {
+            limiter.acquire().await;
+        }
+
+        // Two requests use the burst, the other two wait 50ms each
+        assert!(started.elapsed() >= Duration::from_millis(95));
+    }
+}
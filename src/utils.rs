@@ -1,21 +1,167 @@
 use crate::error::{Error, Result};
+use crate::file::find_media_type;
 use addr::parse_domain_name;
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
 use std::path::Path;
+use std::sync::{Mutex, MutexGuard};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+// Determine the content type of a file based on its extension, ignoring case
+pub fn determine_content_type(path: &Path) -> &'static str {
+    find_media_type(path)
+        .map(|media_type| media_type.mime_type)
+        .unwrap_or("application/octet-stream")
+}
+
+/// Random fraction in `[0, 1)`, good enough for retry jitter.
+/// Each `RandomState` is seeded differently, so no extra dependency is needed.
+pub fn random_fraction() -> f64 {
+    let hash = RandomState::new().build_hasher().finish();
+    (hash >> 11) as f64 / (1u64 << 53) as f64
+}
 
-// Determine the content type of a file based on its extension
-pub fn determine_content_type(path: &Path) -> &str {
-    match path.extension().and_then(|ext| ext.to_str()) {
-        Some("jpg") | Some("jpeg") => "image/jpeg",
-        Some("png") => "image/png",
-        Some("gif") => "image/gif",
-        Some("mp4") => "video/mp4",
-        Some("mov") => "video/quicktime",
-        Some("avi") => "video/x-msvideo",
-        Some("webm") => "video/webm",
-        _ => "application/octet-stream",
+/// Whether an AWS presigned URL is past its expiry, based on the
+/// `X-Amz-Date` and `X-Amz-Expires` query parameters. URLs without these
+/// parameters are assumed to be valid.
+pub fn presigned_url_expired(url: &str) -> bool {
+    let parsed_url = match url::Url::parse(url) {
+        Ok(url) => url,
+        Err(_) => return false,
+    };
+
+    let mut signed_at = None;
+    let mut expires_in = None;
+    for (key, value) in parsed_url.query_pairs() {
+        match key.as_ref() {
+            "X-Amz-Date" => signed_at = parse_amz_date(&value),
+            "X-Amz-Expires" => expires_in = value.parse::<u64>().ok(),
+            _ => {}
+        }
+    }
+
+    match (signed_at, expires_in) {
+        (Some(signed_at), Some(expires_in)) => {
+            let now = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|elapsed| elapsed.as_secs())
+                .unwrap_or(0);
+            now >= signed_at + expires_in
+        }
+        _ => false,
     }
 }
 
+/// Parse an AWS `YYYYMMDDTHHMMSSZ` timestamp into seconds since the Unix epoch
+fn parse_amz_date(value: &str) -> Option<u64> {
+    if value.len() != 16 || !value.is_ascii() || &value[8..9] != "T" || &value[15..] != "Z" {
+        return None;
+    }
+
+    let field = |range: std::ops::Range<usize>| value[range].parse::<u64>().ok();
+    let (year, month, day) = (field(0..4)?, field(4..6)?, field(6..8)?);
+    let (hour, minute, second) = (field(9..11)?, field(11..13)?, field(13..15)?);
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) || hour > 23 || minute > 59 {
+        return None;
+    }
+
+    let days = days_from_civil(year as i64, month as i64, day as i64);
+    let seconds = days * 86400 + (hour * 3600 + minute * 60 + second) as i64;
+    u64::try_from(seconds).ok()
+}
+
+/// Parse a `Retry-After` header value, given either as a number of seconds
+/// or as an HTTP date. Dates in the past yield a zero delay.
+pub fn parse_retry_after(value: &str) -> Option<Duration> {
+    let value = value.trim();
+    if let Ok(seconds) = value.parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
+    }
+
+    let retry_at = parse_http_date(value)?;
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs())
+        .unwrap_or(0);
+    Some(Duration::from_secs(retry_at.saturating_sub(now)))
+}
+
+/// Parse an IMF-fixdate such as `Sun, 06 Nov 1994 08:49:37 GMT` into seconds
+/// since the Unix epoch
+fn parse_http_date(value: &str) -> Option<u64> {
+    const MONTHS: [&str; 12] = [
+        "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+    ];
+
+    let parts: Vec<&str> = value.split_whitespace().collect();
+    if parts.len() != 6 || !parts[0].ends_with(',') || parts[5] != "GMT" {
+        return None;
+    }
+
+    let day = parts[1].parse::<u64>().ok()?;
+    let month = MONTHS.iter().position(|month| *month == parts[2])? as u64 + 1;
+    let year = parts[3].parse::<u64>().ok()?;
+
+    let time: Vec<u64> = parts[4]
+        .split(':')
+        .map(|field| field.parse::<u64>().ok())
+        .collect::<Option<_>>()?;
+    if time.len() != 3 || !(1..=31).contains(&day) || time[0] > 23 || time[1] > 59 {
+        return None;
+    }
+
+    let days = days_from_civil(year as i64, month as i64, day as i64);
+    let seconds = days * 86400 + (time[0] * 3600 + time[1] * 60 + time[2]) as i64;
+    u64::try_from(seconds).ok()
+}
+
+/// Number of days since the Unix epoch for a proleptic Gregorian date
+pub fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146097 + day_of_era - 719468
+}
+
+/// Proleptic Gregorian date of a number of days since the Unix epoch
+pub fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let days = days + 719468;
+    let era = days.div_euclid(146097);
+    let day_of_era = days.rem_euclid(146097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    (year, month as u32, day as u32)
+}
+
+/// Format the UTC date of a point in time as YYYY-MM-DD
+pub fn format_date(time: SystemTime) -> String {
+    let seconds = match time.duration_since(UNIX_EPOCH) {
+        Ok(elapsed) => elapsed.as_secs() as i64,
+        Err(err) => -(err.duration().as_secs_f64().ceil() as i64),
+    };
+    let (year, month, day) = civil_from_days(seconds.div_euclid(86400));
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+/// Lock a mutex, reporting a lock poisoned by a panic as an error instead of
+/// panicking in turn. `name` says what the mutex guards.
+pub(crate) fn lock<'a, T>(mutex: &'a Mutex<T>, name: &str) -> Result<MutexGuard<'a, T>> {
+    mutex
+        .lock()
+        .map_err(|_| Error::UnknownError(format!("Lock of the {name} poisoned")))
+}
+
 /// Validate an URL
 pub fn is_valid_url(url: &str) -> Result<()> {
     // Try to parse the URL
@@ -62,8 +208,12 @@ pub fn is_valid_url(url: &str) -> Result<()> {
 
 #[cfg(test)]
 mod tests {
-    use crate::utils::{determine_content_type, is_valid_url};
+    use crate::utils::{
+        civil_from_days, days_from_civil, determine_content_type, format_date, is_valid_url,
+        parse_amz_date, parse_http_date, parse_retry_after, presigned_url_expired,
+    };
     use std::path::Path;
+    use std::time::{Duration, UNIX_EPOCH};
 
     #[test]
     fn test_determine_content_type_jpg() {
@@ -102,29 +252,44 @@ mod tests {
     }
 
     #[test]
-    fn test_determine_content_type_avi() {
+    fn test_determine_content_type_unsupported_video() {
+        // Formats the upload path rejects have no content type of their own
         let path = Path::new("clip.avi");
-        assert_eq!(determine_content_type(path), "video/x-msvideo");
+        assert_eq!(determine_content_type(path), "application/octet-stream");
+
+        let path = Path::new("web_video.webm");
+        assert_eq!(determine_content_type(path), "application/octet-stream");
     }
 
     #[test]
-    fn test_determine_content_type_webm() {
-        let path = Path::new("web_video.webm");
-        assert_eq!(determine_content_type(path), "video/webm");
+    fn test_determine_content_type_supported_types() {
+        let cases = [
+            ("image.webp", "image/webp"),
+            ("audio.flac", "audio/flac"),
+            ("audio.wav", "audio/wav"),
+            ("audio.mp3", "audio/mpeg"),
+            ("audio.m4a", "audio/mp4"),
+            ("audio.aac", "audio/aac"),
+            ("audio.alac", "audio/mp4"),
+            ("audio.ogg", "audio/ogg"),
+            ("notes.txt", "text/plain"),
+        ];
+        for (file_name, content_type) in cases {
+            assert_eq!(determine_content_type(Path::new(file_name)), content_type);
+        }
     }
 
     #[test]
     fn test_determine_content_type_uppercase_extension() {
         let path = Path::new("IMAGE.JPG");
-        // The current implementation is case-sensitive, so uppercase should default to octet-stream
-        assert_eq!(determine_content_type(path), "application/octet-stream");
+        // Extensions are matched regardless of case
+        assert_eq!(determine_content_type(path), "image/jpeg");
     }
 
     #[test]
     fn test_determine_content_type_mixed_case() {
         let path = Path::new("photo.JpEg");
-        // Case-sensitive implementation should default to octet-stream
-        assert_eq!(determine_content_type(path), "application/octet-stream");
+        assert_eq!(determine_content_type(path), "image/jpeg");
     }
 
     #[test]
@@ -275,4 +440,80 @@ mod tests {
             assert!(is_valid_url(url).is_ok(), "Failed to validate: {}", url);
         }
     }
+
+    #[test]
+    fn test_days_from_civil() {
+        assert_eq!(days_from_civil(1970, 1, 1), 0);
+        assert_eq!(days_from_civil(2000, 3, 1), 11017);
+        assert_eq!(days_from_civil(2024, 2, 29), 19782);
+    }
+
+    #[test]
+    fn test_civil_from_days() {
+        assert_eq!(civil_from_days(0), (1970, 1, 1));
+        assert_eq!(civil_from_days(11017), (2000, 3, 1));
+        assert_eq!(civil_from_days(19782), (2024, 2, 29));
+        assert_eq!(civil_from_days(-1), (1969, 12, 31));
+        for days in [-1000, 0, 59, 365, 10957, 19782, 50000] {
+            let (year, month, day) = civil_from_days(days);
+            assert_eq!(days_from_civil(year, month as i64, day as i64), days);
+        }
+    }
+
+    #[test]
+    fn test_format_date() {
+        assert_eq!(format_date(UNIX_EPOCH), "1970-01-01");
+        let time = UNIX_EPOCH + Duration::from_secs(1709168523);
+        assert_eq!(format_date(time), "2024-02-29");
+    }
+
+    #[test]
+    fn test_parse_amz_date() {
+        assert_eq!(parse_amz_date("19700101T000000Z"), Some(0));
+        assert_eq!(parse_amz_date("20240229T010203Z"), Some(1709168523));
+        assert_eq!(parse_amz_date("2024-02-29"), None);
+        assert_eq!(parse_amz_date("20241301T000000Z"), None);
+    }
+
+    #[test]
+    fn test_presigned_url_expired() {
+        assert!(presigned_url_expired(
+            "https://bucket.s3.amazonaws.com/key?X-Amz-Date=20200101T000000Z&X-Amz-Expires=900"
+        ));
+        assert!(!presigned_url_expired(
+            "https://bucket.s3.amazonaws.com/key?X-Amz-Date=20200101T000000Z&X-Amz-Expires=99999999999"
+        ));
+        // Without expiry parameters the URL is assumed to be valid
+        assert!(!presigned_url_expired(
+            "https://bucket.s3.amazonaws.com/key"
+        ));
+        assert!(!presigned_url_expired("not a url"));
+    }
+
+    #[test]
+    fn test_parse_retry_after() {
+        assert_eq!(parse_retry_after("120"), Some(Duration::from_secs(120)));
+        assert_eq!(parse_retry_after(" 0 "), Some(Duration::ZERO));
+        assert_eq!(
+            parse_retry_after("Sun, 06 Nov 1994 08:49:37 GMT"),
+            Some(Duration::ZERO)
+        );
+        assert_eq!(parse_retry_after("-1"), None);
+        assert_eq!(parse_retry_after("soon"), None);
+    }
+
+    #[test]
+    fn test_parse_http_date() {
+        assert_eq!(
+            parse_http_date("Sun, 06 Nov 1994 08:49:37 GMT"),
+            Some(784111777)
+        );
+        assert_eq!(
+            parse_http_date("Thu, 29 Feb 2024 01:02:03 GMT"),
+            Some(1709168523)
+        );
+        assert_eq!(parse_http_date("Sun, 06 Nov 1994 08:49:37 UTC"), None);
+        assert_eq!(parse_http_date("Sun, 06 Foo 1994 08:49:37 GMT"), None);
+        assert_eq!(parse_http_date("Sunday, 06-Nov-94 08:49:37 GMT"), None);
+    }
 }